@@ -26,6 +26,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         target_url: Some("https://httpbin.org/get".to_string()),
         body: None,
         headers: vec![],
+        ..Default::default()
     };
     shared_config.update_test(test_config.clone());
 
@@ -0,0 +1,36 @@
+;; Example api-check proxy plugin
+;;
+;; - Tags every proxied request and response with `X-Plugin: api-check`
+;; - Rejects requests carrying an `X-Block` header with 403
+;; - Strips the upstream `Server` header from responses
+;;
+;; Load it with `proxy.plugin_path = "examples/plugins/add_header.wat"`
+;; (requires building with `--features wasm-plugins`).
+(module
+  (import "api_check" "get_header" (func $get_header (param i32 i32 i32 i32) (result i32)))
+  (import "api_check" "set_header" (func $set_header (param i32 i32 i32 i32)))
+  (import "api_check" "remove_header" (func $remove_header (param i32 i32)))
+
+  (memory (export "memory") 1)
+
+  (data (i32.const 0) "x-plugin")
+  (data (i32.const 16) "api-check")
+  (data (i32.const 32) "x-block")
+  (data (i32.const 48) "server")
+
+  (func $tag
+    (call $set_header (i32.const 0) (i32.const 8) (i32.const 16) (i32.const 9)))
+
+  (func (export "on_request") (result i32)
+    (call $tag)
+    ;; get_header returns -1 when the header is absent
+    (if (i32.ge_s
+          (call $get_header (i32.const 32) (i32.const 7) (i32.const 64) (i32.const 0))
+          (i32.const 0))
+      (then (return (i32.const 403))))
+    (i32.const 0))
+
+  (func (export "on_response") (result i32)
+    (call $tag)
+    (call $remove_header (i32.const 48) (i32.const 6))
+    (i32.const 0)))
@@ -2,18 +2,40 @@
 //!
 //! Provides HTTP endpoints for configuration management and metrics export.
 
-use crate::config::{AppConfig, ProxyConfig, SharedConfig, TestConfig};
-use crate::metrics::{MetricsSummary, RequestMetric, SharedMetrics};
-use crate::testing::SharedTester;
+mod openapi;
+mod ready;
+#[cfg(feature = "websocket")]
+mod ws;
+
+use crate::config::{
+    AppConfig, AuthConfig, BodyType, CircuitBreakerConfig, Extraction, GraphQlRequest,
+    InvalidConfig, PartialAppConfig, ProxyConfig, ProxyRoute, RetryBackoff, SharedConfig,
+    TestConfig, WeightedStep,
+};
+use crate::metrics::{
+    csv_stream, influx, prometheus, EndpointSummary, MetricSource, MetricsFilter, MetricsPage,
+    MetricsSummary, RequestMetric, SharedMetrics, LOG_HISTOGRAM_BASES, MAX_HISTOGRAM_BUCKETS,
+};
+use crate::proxy::upstream::UpstreamStatus;
+use crate::proxy::{SharedUpstreamPool, UpstreamPool};
+use crate::server::lifecycle::{Lifecycle, SharedLifecycle};
+use crate::testing::{SharedTester, TestRunSummary};
 use axum::{
+    body::Body,
     extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, Method, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Maximum number of response body bytes returned by the proxy test endpoint
+const PROXY_TEST_BODY_LIMIT: usize = 4096;
 
 /// API state shared across handlers
 #[derive(Clone)]
@@ -21,6 +43,10 @@ pub struct ApiState {
     pub config: SharedConfig,
     pub metrics: SharedMetrics,
     pub tester: SharedTester,
+    pub upstreams: SharedUpstreamPool,
+    pub lifecycle: SharedLifecycle,
+    /// Cached proxy target probes for `/api/ready`
+    pub probes: Arc<ready::ProbeCache>,
 }
 
 impl ApiState {
@@ -29,18 +55,38 @@ impl ApiState {
             config,
             metrics,
             tester,
+            upstreams: Arc::new(UpstreamPool::new()),
+            lifecycle: Arc::new(Lifecycle::new()),
+            probes: Arc::new(ready::ProbeCache::default()),
         }
     }
+
+    /// Share the proxy's upstream pool
+    pub fn with_upstreams(mut self, upstreams: SharedUpstreamPool) -> Self {
+        self.upstreams = upstreams;
+        self
+    }
+
+    /// Share the server's lifecycle
+    pub fn with_lifecycle(mut self, lifecycle: SharedLifecycle) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
 }
 
 /// Create the management API router
 ///
-/// Note: The PUT /api/config endpoint replaces the entire configuration.
-/// For production use, consider implementing PATCH endpoints for partial updates.
+/// Note: `PUT /api/config` replaces the entire configuration, while
+/// `PATCH /api/config` merges only the keys it is given.
 pub fn create_api_router(state: Arc<ApiState>) -> Router {
-    Router::new()
+    let router = Router::new()
         // Configuration endpoints
-        .route("/api/config", get(get_config).put(update_config))
+        .route(
+            "/api/config",
+            get(get_config).put(update_config).patch(patch_config),
+        )
+        .route("/api/config/reload", post(reload_config))
+        .route("/api/config/validate", get(validate_config))
         .route(
             "/api/config/proxy",
             get(get_proxy_config).put(update_proxy_config),
@@ -49,32 +95,146 @@ pub fn create_api_router(state: Arc<ApiState>) -> Router {
             "/api/config/test",
             get(get_test_config).put(update_test_config),
         )
+        .route("/api/proxy/upstreams", get(get_upstreams))
+        .route("/api/proxy/test", post(test_proxy_target))
         // Metrics endpoints
         .route("/api/metrics", get(get_metrics))
         .route("/api/metrics/summary", get(get_metrics_summary))
+        .route("/api/metrics/by-endpoint", get(get_metrics_by_endpoint))
+        .route("/api/metrics/prometheus", get(get_prometheus_metrics))
         .route("/api/metrics/recent", get(get_recent_metrics))
+        .route("/api/metrics/history", get(get_metrics_history))
+        .route("/api/metrics/influx", get(get_influx_metrics))
+        .route("/api/metrics/export.csv", get(export_metrics_csv))
+        .route("/api/metrics/export.har", get(export_metrics_har))
+        .route("/api/metrics/histogram", get(get_latency_histogram))
         .route("/api/metrics/clear", post(clear_metrics))
+        .route("/api/metrics/pause", post(pause_metrics))
+        .route("/api/metrics/resume", post(resume_metrics))
+        .route("/api/metrics/mark", post(mark_metrics))
+        .route("/api/metrics/unmark", post(unmark_metrics))
         // Test endpoints
         .route("/api/test/run", post(run_test))
         .route("/api/test/status", get(get_test_status))
         .route("/api/test/stop", post(stop_test))
+        .route("/api/test/history", get(get_test_history))
         // Health check
         .route("/api/health", get(health_check))
+        .route("/api/health/ready", get(readiness_check))
+        .route("/api/ready", get(ready::ready))
+        .route("/api/drain", post(drain))
+        .route("/api/openapi.json", get(openapi::openapi_json));
+
+    // Live request feed
+    #[cfg(feature = "websocket")]
+    let router = router.route("/api/ws/requests", get(ws::request_feed));
+
+    router
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            read_only_guard,
+        ))
         .with_state(state)
 }
 
+/// Reject mutating requests when the API is read-only
+async fn read_only_guard(
+    State(state): State<Arc<ApiState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if !safe && state.config.get().api.read_only {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "API is in read-only mode"
+            })),
+        )
+            .into_response();
+    }
+
+    next.run(req).await
+}
+
 /// Get current configuration
 async fn get_config(State(state): State<Arc<ApiState>>) -> Json<AppConfig> {
     Json(state.config.get())
 }
 
+/// Problems with the current configuration
+async fn validate_config(State(state): State<Arc<ApiState>>) -> Json<serde_json::Value> {
+    let problems = state.config.get().validate().err().unwrap_or_default();
+    Json(serde_json::json!({
+        "valid": problems.is_empty(),
+        "problems": problems,
+    }))
+}
+
 /// Update configuration
+///
+/// Configs that fail validation are rejected with 400 and the list of problems.
 async fn update_config(
     State(state): State<Arc<ApiState>>,
     Json(config): Json<AppConfig>,
-) -> impl IntoResponse {
+) -> Response {
+    if let Err(problems) = config.validate() {
+        return invalid_config(problems);
+    }
     state.config.update(config);
-    (StatusCode::OK, "Configuration updated")
+    (StatusCode::OK, "Configuration updated").into_response()
+}
+
+/// 400 response listing why a configuration was rejected
+fn invalid_config(problems: Vec<String>) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({
+            "error": "Invalid configuration",
+            "problems": problems,
+        })),
+    )
+        .into_response()
+}
+
+/// Merge a partial configuration into the current one
+async fn patch_config(
+    State(state): State<Arc<ApiState>>,
+    Json(partial): Json<PartialAppConfig>,
+) -> Response {
+    match state.config.patch(partial) {
+        Ok(config) => (StatusCode::OK, Json(config)).into_response(),
+        Err(e) => match e.downcast::<InvalidConfig>() {
+            Ok(InvalidConfig(problems)) => invalid_config(problems),
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response(),
+        },
+    }
+}
+
+/// Re-read the configuration file the server was started with
+///
+/// Settings only read at startup (like the listen address) are listed in
+/// `restart_required` when they change.
+async fn reload_config(State(state): State<Arc<ApiState>>) -> Response {
+    match state.config.reload() {
+        Ok(restart_required) => {
+            tracing::info!(path = ?state.config.source_path(), "Configuration reloaded");
+            Json(serde_json::json!({
+                "message": "Configuration reloaded",
+                "restart_required": restart_required,
+            }))
+            .into_response()
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
 }
 
 /// Get proxy configuration
@@ -87,6 +247,22 @@ async fn get_proxy_config(State(state): State<Arc<ApiState>>) -> Json<ProxyConfi
 pub struct UpdateProxyRequest {
     pub enabled: Option<bool>,
     pub target: Option<String>,
+    pub allow_target_header: Option<bool>,
+    pub target_allowlist: Option<Vec<String>>,
+    pub targets: Option<Vec<String>>,
+    pub routes: Option<Vec<ProxyRoute>>,
+    pub health_path: Option<String>,
+    pub health_interval_secs: Option<u64>,
+    pub max_upstream_bytes_per_sec: Option<u64>,
+    pub max_downstream_bytes_per_sec: Option<u64>,
+    pub timeout_secs: Option<u64>,
+    pub max_body_bytes: Option<usize>,
+    pub add_forwarded_headers: Option<bool>,
+    pub request_headers_remove: Option<Vec<String>>,
+    pub request_headers_add: Option<Vec<(String, String)>>,
+    pub response_headers_remove: Option<Vec<String>>,
+    pub response_headers_add: Option<Vec<(String, String)>>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
 }
 
 async fn update_proxy_config(
@@ -101,11 +277,181 @@ async fn update_proxy_config(
     if req.target.is_some() {
         current.target = req.target;
     }
+    if let Some(allow) = req.allow_target_header {
+        current.allow_target_header = allow;
+    }
+    if let Some(allowlist) = req.target_allowlist {
+        current.target_allowlist = allowlist;
+    }
+    if let Some(targets) = req.targets {
+        current.targets = targets;
+    }
+    if let Some(routes) = req.routes {
+        current.routes = routes;
+    }
+    if req.health_path.is_some() {
+        current.health_path = req.health_path;
+    }
+    if let Some(interval) = req.health_interval_secs {
+        current.health_interval_secs = interval;
+    }
+    if req.max_upstream_bytes_per_sec.is_some() {
+        current.max_upstream_bytes_per_sec = req.max_upstream_bytes_per_sec;
+    }
+    if req.max_downstream_bytes_per_sec.is_some() {
+        current.max_downstream_bytes_per_sec = req.max_downstream_bytes_per_sec;
+    }
+    if let Some(timeout) = req.timeout_secs {
+        current.timeout_secs = timeout;
+    }
+    if let Some(max) = req.max_body_bytes {
+        current.max_body_bytes = max;
+    }
+    if let Some(add) = req.add_forwarded_headers {
+        current.add_forwarded_headers = add;
+    }
+    if let Some(remove) = req.request_headers_remove {
+        current.request_headers_remove = remove;
+    }
+    if let Some(add) = req.request_headers_add {
+        current.request_headers_add = add;
+    }
+    if let Some(remove) = req.response_headers_remove {
+        current.response_headers_remove = remove;
+    }
+    if let Some(add) = req.response_headers_add {
+        current.response_headers_add = add;
+    }
+    if req.circuit_breaker.is_some() {
+        current.circuit_breaker = req.circuit_breaker;
+    }
 
     state.config.update_proxy(current);
     (StatusCode::OK, "Proxy configuration updated")
 }
 
+/// Get health of the pooled proxy upstreams
+async fn get_upstreams(State(state): State<Arc<ApiState>>) -> Json<Vec<UpstreamStatus>> {
+    state
+        .upstreams
+        .sync_targets(&state.config.get().proxy.targets);
+    Json(state.upstreams.snapshot())
+}
+
+/// Proxy connectivity test request
+#[derive(Debug, Deserialize, Default)]
+pub struct ProxyTestRequest {
+    pub method: Option<String>,
+    pub path: Option<String>,
+}
+
+/// Proxy connectivity test result
+#[derive(Debug, Serialize)]
+pub struct ProxyTestResponse {
+    pub url: String,
+    pub status: Option<u16>,
+    pub latency_ms: f64,
+    pub body: Option<String>,
+    pub truncated: bool,
+    pub error: Option<String>,
+}
+
+/// Send a single request to the configured proxy target without recording it
+///
+/// Uses the proxy's client settings (HTTP version, DNS overrides, `timeout_secs`).
+async fn test_proxy_target(
+    State(state): State<Arc<ApiState>>,
+    req: Option<Json<ProxyTestRequest>>,
+) -> impl IntoResponse {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    let config = state.config.get();
+    let Some(target) = config.proxy.target.clone() else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "No proxy target configured"
+            })),
+        )
+            .into_response();
+    };
+
+    let method = req.method.as_deref().unwrap_or("GET").to_uppercase();
+    let Ok(method) = reqwest::Method::from_bytes(method.as_bytes()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Invalid method: {}", method)
+            })),
+        )
+            .into_response();
+    };
+
+    let path = req.path.unwrap_or_else(|| "/".to_string());
+    let url = format!(
+        "{}/{}",
+        target.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+
+    // The same client settings the proxy forwards with
+    let client = match crate::proxy::build_client(&config) {
+        Ok(client) => client,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": format!("Failed to create HTTP client: {}", e)
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let start = Instant::now();
+    let result = async {
+        let response = client
+            .request(method, &url)
+            .timeout(Duration::from_secs(config.proxy.timeout_secs))
+            .send()
+            .await?;
+        let status = response.status().as_u16();
+        let body = response.bytes().await?;
+        Ok::<_, reqwest::Error>((status, body))
+    }
+    .await;
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let response = match result {
+        Ok((status, body)) => {
+            let truncated = body.len() > PROXY_TEST_BODY_LIMIT;
+            let shown = &body[..body.len().min(PROXY_TEST_BODY_LIMIT)];
+            ProxyTestResponse {
+                url,
+                status: Some(status),
+                latency_ms,
+                body: Some(String::from_utf8_lossy(shown).into_owned()),
+                truncated,
+                error: None,
+            }
+        }
+        Err(e) => ProxyTestResponse {
+            url,
+            status: None,
+            latency_ms,
+            body: None,
+            truncated: false,
+            error: Some(e.to_string()),
+        },
+    };
+
+    let code = if response.error.is_some() {
+        StatusCode::BAD_GATEWAY
+    } else {
+        StatusCode::OK
+    };
+    (code, Json(response)).into_response()
+}
+
 /// Get test configuration
 async fn get_test_config(State(state): State<Arc<ApiState>>) -> Json<TestConfig> {
     Json(state.config.get().test)
@@ -115,11 +461,34 @@ async fn get_test_config(State(state): State<Arc<ApiState>>) -> Json<TestConfig>
 #[derive(Debug, Deserialize)]
 pub struct UpdateTestRequest {
     pub num_calls: Option<u32>,
+    pub duration_secs: Option<u64>,
     pub frequency_ms: Option<u64>,
+    pub concurrency: Option<u32>,
     pub method: Option<String>,
     pub target_url: Option<String>,
     pub body: Option<String>,
+    pub body_type: Option<BodyType>,
     pub headers: Option<Vec<(String, String)>>,
+    pub honor_retry_after: Option<bool>,
+    pub conditional: Option<bool>,
+    pub prime_connections: Option<bool>,
+    pub expect_status: Option<u16>,
+    pub expect_body_contains: Option<String>,
+    pub auth: Option<AuthConfig>,
+    pub extract: Option<Vec<Extraction>>,
+    pub scenario: Option<Vec<WeightedStep>>,
+    pub retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+    pub retry_backoff: Option<RetryBackoff>,
+    pub retry_on_status: Option<Vec<u16>>,
+    pub follow_redirects: Option<bool>,
+    pub timeout_secs: Option<u64>,
+    pub save_results_path: Option<String>,
+    pub max_rps: Option<u32>,
+    pub enable_cookies: Option<bool>,
+    pub slo_p95_ms: Option<f64>,
+    pub slo_error_rate: Option<f64>,
+    pub graphql: Option<GraphQlRequest>,
 }
 
 async fn update_test_config(
@@ -131,9 +500,15 @@ async fn update_test_config(
     if let Some(num_calls) = req.num_calls {
         current.num_calls = num_calls;
     }
+    if req.duration_secs.is_some() {
+        current.duration_secs = req.duration_secs;
+    }
     if let Some(frequency_ms) = req.frequency_ms {
         current.frequency_ms = frequency_ms;
     }
+    if let Some(concurrency) = req.concurrency {
+        current.concurrency = concurrency;
+    }
     if let Some(method) = req.method {
         current.method = method;
     }
@@ -143,22 +518,218 @@ async fn update_test_config(
     if req.body.is_some() {
         current.body = req.body;
     }
+    if let Some(body_type) = req.body_type {
+        current.body_type = body_type;
+    }
     if let Some(headers) = req.headers {
         current.headers = headers;
     }
+    if let Some(honor) = req.honor_retry_after {
+        current.honor_retry_after = honor;
+    }
+    if let Some(conditional) = req.conditional {
+        current.conditional = conditional;
+    }
+    if let Some(prime) = req.prime_connections {
+        current.prime_connections = prime;
+    }
+    if req.expect_status.is_some() {
+        current.expect_status = req.expect_status;
+    }
+    if req.expect_body_contains.is_some() {
+        current.expect_body_contains = req.expect_body_contains;
+    }
+    if req.auth.is_some() {
+        current.auth = req.auth;
+    }
+    if let Some(extract) = req.extract {
+        current.extract = extract;
+    }
+    if req.scenario.is_some() {
+        current.scenario = req.scenario;
+    }
+    if let Some(retries) = req.retries {
+        current.retries = retries;
+    }
+    if let Some(backoff_ms) = req.retry_backoff_ms {
+        current.retry_backoff_ms = backoff_ms;
+    }
+    if let Some(backoff) = req.retry_backoff {
+        current.retry_backoff = backoff;
+    }
+    if let Some(statuses) = req.retry_on_status {
+        current.retry_on_status = statuses;
+    }
+    if let Some(follow) = req.follow_redirects {
+        current.follow_redirects = follow;
+    }
+    if let Some(timeout) = req.timeout_secs {
+        current.timeout_secs = timeout;
+    }
+    if req.save_results_path.is_some() {
+        current.save_results_path = req.save_results_path;
+    }
+    if req.max_rps.is_some() {
+        current.max_rps = req.max_rps;
+    }
+    if let Some(enable) = req.enable_cookies {
+        current.enable_cookies = enable;
+    }
+    if req.slo_p95_ms.is_some() {
+        current.slo_p95_ms = req.slo_p95_ms;
+    }
+    if req.slo_error_rate.is_some() {
+        current.slo_error_rate = req.slo_error_rate;
+    }
+    if req.graphql.is_some() {
+        current.graphql = req.graphql;
+    }
 
     state.config.update_test(current);
     (StatusCode::OK, "Test configuration updated")
 }
 
-/// Get all metrics
-async fn get_metrics(State(state): State<Arc<ApiState>>) -> Json<Vec<RequestMetric>> {
-    Json(state.metrics.get_all())
+/// Metrics returned by `GET /api/metrics` when no limit is given
+const DEFAULT_METRICS_LIMIT: usize = 100;
+
+/// Query parameters for paging through stored metrics
+#[derive(Debug, Deserialize, Default)]
+pub struct MetricsQuery {
+    /// Maximum number of metrics to return (default 100)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Number of matching metrics to skip, counting back from the newest
+    #[serde(default)]
+    pub offset: usize,
+    /// Only metrics with this HTTP method
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Only metrics with this status code
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Only metrics whose path contains this text
+    #[serde(default)]
+    pub path_contains: Option<String>,
+}
+
+/// Get a page of stored metrics, newest first
+async fn get_metrics(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Query(query): axum::extract::Query<MetricsQuery>,
+) -> Json<MetricsPage> {
+    let filter = MetricsFilter {
+        method: query.method,
+        status: query.status,
+        path_contains: query.path_contains,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_METRICS_LIMIT);
+    Json(state.metrics.query(&filter, limit, query.offset))
+}
+
+/// Query parameters for the metrics summary
+#[derive(Debug, Deserialize, Default)]
+pub struct SummaryQuery {
+    /// Only summarize metrics from this source (`server`, `test` or `proxy`)
+    #[serde(default)]
+    pub source: Option<MetricSource>,
 }
 
 /// Get metrics summary
-async fn get_metrics_summary(State(state): State<Arc<ApiState>>) -> Json<MetricsSummary> {
-    Json(state.metrics.get_summary())
+async fn get_metrics_summary(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Query(query): axum::extract::Query<SummaryQuery>,
+) -> Json<MetricsSummary> {
+    Json(state.metrics.get_summary_for(query.source))
+}
+
+/// Metrics for one endpoint in the by-endpoint breakdown
+#[derive(Debug, Serialize)]
+pub struct EndpointMetrics {
+    pub method: String,
+    pub path: String,
+    #[serde(flatten)]
+    pub summary: EndpointSummary,
+}
+
+/// Get per-endpoint metrics, slowest average latency first
+async fn get_metrics_by_endpoint(State(state): State<Arc<ApiState>>) -> Json<Vec<EndpointMetrics>> {
+    let mut endpoints: Vec<EndpointMetrics> = state
+        .metrics
+        .get_summary_by_endpoint()
+        .into_iter()
+        .map(|((method, path), summary)| EndpointMetrics {
+            method,
+            path,
+            summary,
+        })
+        .collect();
+    endpoints.sort_by(|a, b| {
+        b.summary
+            .avg_latency_ms
+            .total_cmp(&a.summary.avg_latency_ms)
+    });
+    Json(endpoints)
+}
+
+/// Query parameters for the CSV export
+#[derive(Debug, Deserialize, Default)]
+pub struct ExportQuery {
+    /// Only export metrics from the last N seconds
+    #[serde(default)]
+    pub seconds: Option<i64>,
+}
+
+/// Download stored metrics as CSV
+async fn export_metrics_csv(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Query(query): axum::extract::Query<ExportQuery>,
+) -> impl IntoResponse {
+    let metrics = match query.seconds {
+        Some(seconds) => state.metrics.get_recent(seconds),
+        None => state.metrics.get_all(),
+    };
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"metrics.csv\"",
+            ),
+        ],
+        csv_stream::export_csv(&metrics),
+    )
+}
+
+/// Query parameters for the HAR export
+#[derive(Debug, Deserialize, Default)]
+pub struct HarExportQuery {
+    /// Base URL for relative metric paths (defaults to this server)
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// Download stored metrics as a HAR log, for replay with `api-check replay-har`
+async fn export_metrics_har(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Query(query): axum::extract::Query<HarExportQuery>,
+) -> impl IntoResponse {
+    let base_url = query.base_url.unwrap_or_else(|| {
+        let server = state.config.get().server;
+        format!("http://{}:{}", server.host, server.port)
+    });
+    (
+        [(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"metrics.har\"",
+        )],
+        Json(state.metrics.export_har(&base_url)),
+    )
+}
+
+/// Get the metrics summary in the Prometheus text format
+async fn get_prometheus_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    let body = prometheus::render(&state.metrics.get_summary());
+    ([(header::CONTENT_TYPE, prometheus::CONTENT_TYPE)], body)
 }
 
 /// Query parameters for recent metrics
@@ -180,12 +751,164 @@ async fn get_recent_metrics(
     Json(state.metrics.get_recent(query.seconds))
 }
 
+/// Query parameters for metrics history
+#[derive(Debug, Deserialize, Default)]
+pub struct HistoryQuery {
+    /// Only metrics recorded at or after this time (RFC 3339)
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Maximum number of metrics to return, newest kept (default 100)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Get metrics history, reading the SQLite database when one is configured
+async fn get_metrics_history(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
+) -> Json<Vec<RequestMetric>> {
+    let limit = query.limit.unwrap_or(DEFAULT_METRICS_LIMIT);
+    let metrics = state.metrics.clone();
+    // The database read blocks, so keep it off the async workers
+    let history = tokio::task::spawn_blocking(move || metrics.history(query.since, limit))
+        .await
+        .unwrap_or_default();
+    Json(history)
+}
+
+/// Get recent metrics in InfluxDB line protocol
+async fn get_influx_metrics(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Query(query): axum::extract::Query<RecentMetricsQuery>,
+) -> impl IntoResponse {
+    let body = influx::render(&state.metrics.get_recent(query.seconds));
+    ([(header::CONTENT_TYPE, influx::CONTENT_TYPE)], body)
+}
+
+/// Histogram bucket spacing
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistogramScale {
+    /// Evenly spaced buckets between the fastest and slowest request
+    Linear,
+    /// Exponentially growing buckets, suited to heavy-tailed latency
+    #[default]
+    Log,
+}
+
+/// Query parameters for the latency histogram
+///
+/// Without `scale`, giving `buckets` picks the linear scale; otherwise log.
+#[derive(Debug, Deserialize)]
+pub struct HistogramQuery {
+    #[serde(default)]
+    pub scale: Option<HistogramScale>,
+    /// Number of buckets (linear scale)
+    #[serde(default)]
+    pub buckets: Option<usize>,
+    /// Bucket growth factor (log scale)
+    #[serde(default = "default_histogram_base")]
+    pub base: f64,
+}
+
+fn default_histogram_buckets() -> usize {
+    20
+}
+
+fn default_histogram_base() -> f64 {
+    2.0
+}
+
+/// A single histogram bucket
+#[derive(Debug, Serialize)]
+pub struct HistogramBucket {
+    /// Lower bound of the bucket in milliseconds
+    pub bucket_start_ms: f64,
+    /// Number of requests in the bucket
+    pub count: u64,
+}
+
+/// Get the latency histogram
+async fn get_latency_histogram(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Query(query): axum::extract::Query<HistogramQuery>,
+) -> Response {
+    let buckets = query.buckets.unwrap_or_else(default_histogram_buckets);
+    if !(1..=MAX_HISTOGRAM_BUCKETS).contains(&buckets) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("buckets must be between 1 and {}", MAX_HISTOGRAM_BUCKETS)
+            })),
+        )
+            .into_response();
+    }
+    if !LOG_HISTOGRAM_BASES.contains(&query.base) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!(
+                    "base must be between {} and {}",
+                    LOG_HISTOGRAM_BASES.start(),
+                    LOG_HISTOGRAM_BASES.end()
+                )
+            })),
+        )
+            .into_response();
+    }
+
+    let scale = query.scale.unwrap_or(if query.buckets.is_some() {
+        HistogramScale::Linear
+    } else {
+        HistogramScale::Log
+    });
+    let histogram = match scale {
+        HistogramScale::Linear => state.metrics.get_latency_histogram(buckets),
+        HistogramScale::Log => state.metrics.get_log_histogram(query.base),
+    };
+
+    Json(
+        histogram
+            .into_iter()
+            .map(|(bucket_start_ms, count)| HistogramBucket {
+                bucket_start_ms,
+                count,
+            })
+            .collect::<Vec<_>>(),
+    )
+    .into_response()
+}
+
 /// Clear all metrics
 async fn clear_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
     state.metrics.clear();
     (StatusCode::OK, "Metrics cleared")
 }
 
+/// Stop recording metrics, keeping those already stored
+async fn pause_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    state.metrics.set_paused(true);
+    (StatusCode::OK, "Metrics collection paused")
+}
+
+/// Resume recording metrics
+async fn resume_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    state.metrics.set_paused(false);
+    (StatusCode::OK, "Metrics collection resumed")
+}
+
+/// Restart summaries from now without dropping stored metrics
+async fn mark_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    let since = state.metrics.set_summary_baseline();
+    Json(serde_json::json!({ "since": since }))
+}
+
+/// Summarize all stored metrics again
+async fn unmark_metrics(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    state.metrics.clear_summary_baseline();
+    (StatusCode::OK, "Summary baseline cleared")
+}
+
 /// Test status response
 #[derive(Debug, Serialize)]
 pub struct TestStatusResponse {
@@ -199,6 +922,11 @@ async fn get_test_status(State(state): State<Arc<ApiState>>) -> Json<TestStatusR
     })
 }
 
+/// Summaries of recent test runs, oldest first
+async fn get_test_history(State(state): State<Arc<ApiState>>) -> Json<Vec<TestRunSummary>> {
+    Json(state.tester.history())
+}
+
 /// Run test request
 #[derive(Debug, Deserialize)]
 pub struct RunTestRequest {
@@ -279,13 +1007,183 @@ async fn health_check() -> Json<HealthResponse> {
     })
 }
 
+/// Readiness check: 503 once draining so load balancers stop routing here
+async fn readiness_check(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    if state.lifecycle.is_ready() {
+        (
+            StatusCode::OK,
+            Json(serde_json::json!({ "status": "ready" })),
+        )
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "status": "draining" })),
+        )
+    }
+}
+
+/// Stop advertising readiness, then exit after the configured grace period
+async fn drain(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    let grace_secs = state.config.get().server.drain_grace_secs;
+    if !state
+        .lifecycle
+        .drain(std::time::Duration::from_secs(grace_secs))
+    {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "Already draining" })),
+        );
+    }
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Draining",
+            "grace_secs": grace_secs
+        })),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tower::ServiceExt;
 
     #[tokio::test]
     async fn test_health_check() {
         let response = health_check().await;
         assert_eq!(response.status, "healthy");
     }
+
+    #[tokio::test]
+    async fn test_read_only_rejects_mutations() {
+        let mut app_config = AppConfig::default();
+        app_config.api.read_only = true;
+        let config = SharedConfig::new(app_config);
+        let metrics = crate::metrics::create_shared_metrics(100);
+        let tester = crate::testing::create_shared_tester(config.clone(), metrics.clone());
+        let router = create_api_router(Arc::new(ApiState::new(config, metrics, tester)));
+
+        let request = |method: &str, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(request("POST", "/api/metrics/clear"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = router
+            .oneshot(request("GET", "/api/metrics/summary"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_config_updates_are_validated() {
+        let config = SharedConfig::new(AppConfig::default());
+        let metrics = crate::metrics::create_shared_metrics(100);
+        let tester = crate::testing::create_shared_tester(config.clone(), metrics.clone());
+        let router = create_api_router(Arc::new(ApiState::new(config.clone(), metrics, tester)));
+
+        let mut invalid = AppConfig::default();
+        invalid.server.port = 0;
+        for (method, body) in [
+            ("PUT", serde_json::to_string(&invalid).unwrap()),
+            ("PATCH", r#"{"server": {"port": 0}}"#.to_string()),
+        ] {
+            let request = Request::builder()
+                .method(method)
+                .uri("/api/config")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", method);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(
+                body["problems"][0],
+                "server.port must be between 1 and 65535"
+            );
+        }
+        assert_eq!(config.get().server.port, 3000);
+
+        let request = Request::builder()
+            .method("PATCH")
+            .uri("/api/config")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"test": {"num_call": 5}}"#))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["problems"][0], "unknown field test.num_call");
+    }
+
+    #[tokio::test]
+    async fn test_proxy_test_requires_target() {
+        let config = SharedConfig::new(AppConfig::default());
+        let metrics = crate::metrics::create_shared_metrics(100);
+        let tester = crate::testing::create_shared_tester(config.clone(), metrics.clone());
+        let state = Arc::new(ApiState::new(config, metrics.clone(), tester));
+
+        let response = test_proxy_target(State(state), None).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(metrics.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_histogram_validates_buckets() {
+        let config = SharedConfig::new(AppConfig::default());
+        let metrics = crate::metrics::create_shared_metrics(1000);
+        let tester = crate::testing::create_shared_tester(config.clone(), metrics.clone());
+        metrics.record(RequestMetric::new("GET".to_string(), "/".to_string()).with_latency(5.0));
+        let app = create_api_router(Arc::new(ApiState::new(config, metrics, tester)));
+
+        let get = |uri: &str| {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(request)
+        };
+
+        for uri in [
+            "/api/metrics/histogram?scale=linear&buckets=0",
+            "/api/metrics/histogram?scale=linear&buckets=10001",
+            "/api/metrics/histogram?base=1.000001",
+            "/api/metrics/histogram?base=11",
+        ] {
+            assert_eq!(get(uri).await.unwrap().status(), StatusCode::BAD_REQUEST);
+        }
+
+        let response = get("/api/metrics/histogram?scale=linear&buckets=20")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let buckets: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(buckets[0]["bucket_start_ms"], 5.0);
+        assert_eq!(buckets[0]["count"], 1);
+
+        // `buckets` without `scale` keeps the linear histogram
+        let response = get("/api/metrics/histogram?buckets=20").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let linear: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(linear, buckets);
+    }
 }
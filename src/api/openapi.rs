@@ -0,0 +1,920 @@
+//! OpenAPI 3.0 description of the management API
+//!
+//! `GET /api/openapi.json` serves this document for client generators.
+//! Paths and schemas are listed by hand next to the router; a test checks
+//! that the config, metric and test-run schemas declare exactly the fields
+//! those structs serialize.
+
+use axum::Json;
+use serde_json::{json, Map, Value};
+
+/// Serve the OpenAPI document
+pub async fn openapi_json() -> Json<Value> {
+    Json(spec())
+}
+
+/// Build the OpenAPI document
+pub fn spec() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "api-check management API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Configuration, metrics, API testing and health endpoints. \
+                With `api.read_only` set, every non-GET request is rejected with 403.",
+        },
+        "paths": with_read_only_responses(paths()),
+        "components": { "schemas": schemas() },
+    })
+}
+
+fn paths() -> Value {
+    let metrics_seconds = query("seconds", "integer", "Only metrics from the last N seconds");
+    json!({
+        "/api/config": {
+            "get": op("getConfig", "Current configuration", None, vec![ok(schema_ref("AppConfig"))]),
+            "put": op(
+                "replaceConfig",
+                "Replace the entire configuration",
+                Some(schema_ref("AppConfig")),
+                vec![ok_text(), bad_request()],
+            ),
+            "patch": op(
+                "patchConfig",
+                "Merge the given keys into the configuration",
+                Some(schema_ref("AppConfig")),
+                vec![ok(schema_ref("AppConfig")), bad_request()],
+            ),
+        },
+        "/api/config/reload": {
+            "post": op(
+                "reloadConfig",
+                "Re-read the configuration file",
+                None,
+                vec![
+                    ok(json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "restart_required": { "type": "array", "items": { "type": "string" } },
+                        },
+                    })),
+                    bad_request(),
+                ],
+            ),
+        },
+        "/api/config/validate": {
+            "get": op(
+                "validateConfig",
+                "Problems with the current configuration",
+                None,
+                vec![ok(json!({
+                    "type": "object",
+                    "properties": {
+                        "valid": { "type": "boolean" },
+                        "problems": { "type": "array", "items": { "type": "string" } },
+                    },
+                }))],
+            ),
+        },
+        "/api/config/proxy": {
+            "get": op("getProxyConfig", "Proxy configuration", None, vec![ok(schema_ref("ProxyConfig"))]),
+            "put": op(
+                "updateProxyConfig",
+                "Update the given proxy settings",
+                Some(schema_ref("ProxyConfig")),
+                vec![ok_text()],
+            ),
+        },
+        "/api/config/test": {
+            "get": op("getTestConfig", "Test configuration", None, vec![ok(schema_ref("TestConfig"))]),
+            "put": op(
+                "updateTestConfig",
+                "Update the given test settings",
+                Some(schema_ref("TestConfig")),
+                vec![ok_text()],
+            ),
+        },
+        "/api/proxy/upstreams": {
+            "get": op("getUpstreams", "Health of the proxy upstream pool", None, vec![ok(array_of("UpstreamStatus"))]),
+        },
+        "/api/proxy/test": {
+            "post": op(
+                "testProxyTarget",
+                "Send one request to the proxy target without recording it",
+                Some(schema_ref("ProxyTestRequest")),
+                vec![
+                    ok(schema_ref("ProxyTestResponse")),
+                    bad_request(),
+                    ("502", "Upstream request failed", json_content(schema_ref("ProxyTestResponse"))),
+                ],
+            ),
+        },
+        "/api/metrics": {
+            "get": with_params(
+                op("getMetrics", "A page of stored request metrics, newest first", None, vec![ok(schema_ref("MetricsPage"))]),
+                vec![
+                    query("limit", "integer", "Maximum number of metrics to return (default 100)"),
+                    query("offset", "integer", "Matching metrics to skip, counting back from the newest"),
+                    query("method", "string", "Only metrics with this HTTP method"),
+                    query("status", "integer", "Only metrics with this status code"),
+                    query("path_contains", "string", "Only metrics whose path contains this text"),
+                ],
+            ),
+        },
+        "/api/metrics/summary": {
+            "get": with_params(
+                op("getMetricsSummary", "Aggregated metrics since the summary baseline", None, vec![ok(schema_ref("MetricsSummary"))]),
+                vec![json!({
+                    "name": "source",
+                    "in": "query",
+                    "description": "Only summarize metrics from this source",
+                    "schema": { "type": "string", "enum": ["server", "test", "proxy"] },
+                })],
+            ),
+        },
+        "/api/metrics/by-endpoint": {
+            "get": op("getMetricsByEndpoint", "Per-endpoint metrics, slowest first", None, vec![ok(array_of("EndpointMetrics"))]),
+        },
+        "/api/metrics/prometheus": {
+            "get": op("getPrometheusMetrics", "Metrics in Prometheus text format", None, vec![ok_text()]),
+        },
+        "/api/metrics/recent": {
+            "get": with_params(
+                op("getRecentMetrics", "Metrics from the last N seconds (default 60)", None, vec![ok(array_of("RequestMetric"))]),
+                vec![metrics_seconds.clone()],
+            ),
+        },
+        "/api/metrics/history": {
+            "get": with_params(
+                op(
+                    "getMetricsHistory",
+                    "Stored metrics, oldest first, read from the SQLite database when configured",
+                    None,
+                    vec![ok(array_of("RequestMetric"))],
+                ),
+                vec![
+                    json!({
+                        "name": "since",
+                        "in": "query",
+                        "description": "Only metrics recorded at or after this time",
+                        "schema": { "type": "string", "format": "date-time" },
+                    }),
+                    query("limit", "integer", "Maximum number of metrics to return, keeping the newest (default 100)"),
+                ],
+            ),
+        },
+        "/api/metrics/influx": {
+            "get": with_params(
+                op("getInfluxMetrics", "Recent metrics in InfluxDB line protocol", None, vec![ok_text()]),
+                vec![metrics_seconds.clone()],
+            ),
+        },
+        "/api/metrics/export.csv": {
+            "get": with_params(
+                op(
+                    "exportMetricsCsv",
+                    "Download metrics as CSV",
+                    None,
+                    vec![("200", "CSV file", content("text/csv", json!({ "type": "string" })))],
+                ),
+                vec![metrics_seconds],
+            ),
+        },
+        "/api/metrics/export.har": {
+            "get": with_params(
+                op(
+                    "exportMetricsHar",
+                    "Download metrics as a HAR 1.2 log (method, URL, status and timing only)",
+                    None,
+                    vec![ok(json!({ "type": "object" }))],
+                ),
+                vec![query(
+                    "base_url",
+                    "string",
+                    "Base URL for relative paths (defaults to this server)",
+                )],
+            ),
+        },
+        "/api/metrics/histogram": {
+            "get": with_params(
+                op(
+                    "getLatencyHistogram",
+                    "Latency histogram",
+                    None,
+                    vec![ok(array_of("HistogramBucket")), bad_request()],
+                ),
+                vec![
+                    json!({
+                        "name": "scale",
+                        "in": "query",
+                        "description": "Defaults to linear when buckets is given, log otherwise",
+                        "schema": { "type": "string", "enum": ["log", "linear"] },
+                    }),
+                    query("buckets", "integer", "Number of buckets for the linear scale (1-10000)"),
+                    query("base", "number", "Bucket growth factor for the log scale (1.1-10)"),
+                ],
+            ),
+        },
+        "/api/metrics/clear": {
+            "post": op("clearMetrics", "Delete all stored metrics", None, vec![ok_text()]),
+        },
+        "/api/metrics/pause": {
+            "post": op("pauseMetrics", "Stop recording metrics", None, vec![ok_text()]),
+        },
+        "/api/metrics/resume": {
+            "post": op("resumeMetrics", "Resume recording metrics", None, vec![ok_text()]),
+        },
+        "/api/metrics/mark": {
+            "post": op(
+                "markMetrics",
+                "Start summaries afresh from now, keeping stored metrics",
+                None,
+                vec![ok(json!({
+                    "type": "object",
+                    "properties": { "since": { "type": "string", "format": "date-time" } },
+                }))],
+            ),
+        },
+        "/api/metrics/unmark": {
+            "post": op("unmarkMetrics", "Summarize all stored metrics again", None, vec![ok_text()]),
+        },
+        "/api/test/run": {
+            "post": op(
+                "runTest",
+                "Start a test run in the background (the stored test config if no body is sent)",
+                Some(schema_ref("TestConfig")),
+                vec![
+                    ("202", "Test started", json_content(schema_ref("Message"))),
+                    ("409", "A test is already running", json_content(schema_ref("Error"))),
+                ],
+            ),
+        },
+        "/api/test/status": {
+            "get": op("getTestStatus", "Whether a test is running", None, vec![ok(schema_ref("TestStatus"))]),
+        },
+        "/api/test/stop": {
+            "post": op("stopTest", "Stop the running test", None, vec![ok_text()]),
+        },
+        "/api/test/history": {
+            "get": op("getTestHistory", "Summaries of recent test runs, oldest first", None, vec![ok(array_of("TestRunSummary"))]),
+        },
+        "/api/health": {
+            "get": op("healthCheck", "Liveness check", None, vec![ok(schema_ref("Health"))]),
+        },
+        "/api/health/ready": {
+            "get": op(
+                "readinessCheck",
+                "Readiness check (503 while draining)",
+                None,
+                vec![ok(schema_ref("Status")), unavailable(schema_ref("Status"))],
+            ),
+        },
+        "/api/ready": {
+            "get": op(
+                "ready",
+                "Readiness including a probe of the proxy target",
+                None,
+                vec![ok(schema_ref("Ready")), unavailable(schema_ref("Ready"))],
+            ),
+        },
+        "/api/drain": {
+            "post": op(
+                "drain",
+                "Stop advertising readiness, then exit after the drain grace period",
+                None,
+                vec![
+                    ("202", "Draining", json_content(json!({
+                        "type": "object",
+                        "properties": {
+                            "message": { "type": "string" },
+                            "grace_secs": { "type": "integer" },
+                        },
+                    }))),
+                    ("409", "Already draining", json_content(schema_ref("Error"))),
+                ],
+            ),
+        },
+        "/api/openapi.json": {
+            "get": op("getOpenApi", "This document", None, vec![ok(json!({ "type": "object" }))]),
+        },
+    })
+}
+
+fn schemas() -> Value {
+    let header_pairs = json!({
+        "type": "array",
+        "items": { "type": "array", "items": string(), "minItems": 2, "maxItems": 2 },
+    });
+
+    json!({
+        "AppConfig": properties(vec![
+            ("server", schema_ref("ServerConfig")),
+            ("proxy", schema_ref("ProxyConfig")),
+            ("test", schema_ref("TestConfig")),
+            ("metrics", schema_ref("MetricsConfig")),
+            ("api", schema_ref("ApiConfig")),
+            ("dns_overrides", header_pairs.clone()),
+        ]),
+        "ServerConfig": properties(vec![
+            ("host", string()),
+            ("port", integer()),
+            ("listen", array(string())),
+            ("otlp_endpoint", nullable(string())),
+            ("echo_headers_as_object", boolean()),
+            ("echo_headers_collect_duplicates", boolean()),
+            ("drain_grace_secs", integer()),
+            ("shutdown_grace_secs", integer()),
+            ("max_echo_body_bytes", integer()),
+            ("compression", boolean()),
+            ("access_log_path", nullable(string())),
+            ("inject_delay_ms", nullable(integer())),
+            ("cors", schema_ref("CorsConfig")),
+        ]),
+        "CorsConfig": properties(vec![
+            ("allowed_origins", array(string())),
+            ("allowed_methods", array(string())),
+            ("allowed_headers", array(string())),
+        ]),
+        "ProxyConfig": properties(vec![
+            ("enabled", boolean()),
+            ("target", nullable(string())),
+            ("allow_target_header", boolean()),
+            ("target_allowlist", array(string())),
+            ("targets", array(string())),
+            ("routes", array_of("ProxyRoute")),
+            ("health_path", nullable(string())),
+            ("health_interval_secs", integer()),
+            ("plugin_path", nullable(string())),
+            ("plugin_fuel", integer()),
+            ("max_upstream_bytes_per_sec", nullable(integer())),
+            ("max_downstream_bytes_per_sec", nullable(integer())),
+            ("max_concurrent_upstream", nullable(integer())),
+            ("timeout_secs", integer()),
+            ("max_body_bytes", integer()),
+            ("add_forwarded_headers", boolean()),
+            ("request_headers_remove", array(string())),
+            ("request_headers_add", header_pairs.clone()),
+            ("response_headers_remove", array(string())),
+            ("response_headers_add", header_pairs.clone()),
+            ("http_version", string_enum(&["auto", "http1", "http2"])),
+            ("circuit_breaker", nullable(schema_ref("CircuitBreakerConfig"))),
+        ]),
+        "ProxyRoute": required(
+            properties(vec![
+                ("prefix", string()),
+                ("target", string()),
+                ("strip_prefix", boolean()),
+            ]),
+            &["prefix", "target"],
+        ),
+        "CircuitBreakerConfig": properties(vec![
+            ("failure_threshold", integer()),
+            ("window_secs", integer()),
+            ("cooldown_secs", integer()),
+        ]),
+        "MetricsConfig": properties(vec![
+            ("max_entries", integer()),
+            ("eviction", string_enum(&["bulk", "ring"])),
+            ("evict_fraction", number()),
+            ("max_bytes", nullable(integer())),
+            ("retention_secs", nullable(integer())),
+            ("coalesce_identical", boolean()),
+            ("coalesce_tolerance_ms", number()),
+            ("csv_stream_path", nullable(string())),
+            ("csv_max_bytes", nullable(integer())),
+            ("sqlite_path", nullable(string())),
+            ("sqlite_max_rows", nullable(integer())),
+            ("id_format", string_enum(&["uuid4", "uuid7", "ulid", "sequential"])),
+            ("slo", nullable(schema_ref("SloConfig"))),
+            ("sample_rate", number()),
+            ("ewma_alpha", number()),
+        ]),
+        "SloConfig": required(
+            properties(vec![("target_pct", number()), ("latency_ms", number())]),
+            &["target_pct", "latency_ms"],
+        ),
+        "ApiConfig": properties(vec![("read_only", boolean())]),
+        "TestConfig": properties(vec![
+            ("num_calls", integer()),
+            ("duration_secs", nullable(integer())),
+            ("frequency_ms", integer()),
+            ("concurrency", integer()),
+            ("method", string()),
+            ("target_url", nullable(string())),
+            ("body", nullable(string())),
+            ("body_type", schema_ref("BodyType")),
+            ("headers", header_pairs.clone()),
+            ("honor_retry_after", boolean()),
+            ("conditional", boolean()),
+            ("prime_connections", boolean()),
+            ("expect_status", nullable(integer())),
+            ("expect_body_contains", nullable(string())),
+            ("auth", nullable(schema_ref("AuthConfig"))),
+            ("extract", array_of("Extraction")),
+            ("scenario", nullable(array_of("WeightedStep"))),
+            ("retries", integer()),
+            ("retry_backoff_ms", integer()),
+            ("retry_backoff", string_enum(&["linear", "exponential"])),
+            ("retry_on_status", array(integer())),
+            ("follow_redirects", boolean()),
+            ("timeout_secs", integer()),
+            ("save_results_path", nullable(string())),
+            ("max_rps", nullable(integer())),
+            ("enable_cookies", boolean()),
+            ("slo_p95_ms", nullable(number())),
+            ("slo_error_rate", nullable(number())),
+            ("graphql", nullable(schema_ref("GraphQlRequest"))),
+        ]),
+        "BodyType": {
+            "oneOf": [
+                tagged("raw", vec![]),
+                tagged("json", vec![]),
+                tagged("form", vec![]),
+                tagged("multipart", vec![("files", header_pairs)]),
+            ],
+            "discriminator": { "propertyName": "type" },
+        },
+        "AuthConfig": {
+            "oneOf": [
+                required(tagged("bearer", vec![("token", string())]), &["type", "token"]),
+                required(
+                    tagged("basic", vec![("username", string()), ("password", string())]),
+                    &["type", "username", "password"],
+                ),
+            ],
+            "discriminator": { "propertyName": "type" },
+        },
+        "WeightedStep": required(
+            properties(vec![
+                ("weight", integer()),
+                ("method", string()),
+                ("path_or_url", string()),
+                ("body", nullable(string())),
+            ]),
+            &["path_or_url"],
+        ),
+        "Extraction": required(
+            properties(vec![("json_path", string()), ("var_name", string())]),
+            &["json_path", "var_name"],
+        ),
+        "GraphQlRequest": required(
+            properties(vec![
+                ("query", string()),
+                ("variables", json!({ "nullable": true })),
+            ]),
+            &["query"],
+        ),
+        "MetricsSummary": properties(vec![
+            ("total_requests", integer()),
+            ("successful_requests", integer()),
+            ("failed_requests", integer()),
+            ("avg_latency_ms", number()),
+            ("min_latency_ms", number()),
+            ("max_latency_ms", number()),
+            ("total_bytes_in", integer()),
+            ("total_bytes_out", integer()),
+            ("stddev_latency_ms", number()),
+            ("error_rate", number()),
+            ("p50_latency_ms", number()),
+            ("p95_latency_ms", number()),
+            ("p99_latency_ms", number()),
+            ("proxied_requests", integer()),
+            (
+                "status_distribution",
+                json!({ "type": "object", "additionalProperties": integer() }),
+            ),
+            ("count_2xx", integer()),
+            ("count_3xx", integer()),
+            ("count_4xx", integer()),
+            ("count_5xx", integer()),
+            ("count_other", integer()),
+            ("success_rate", number()),
+            ("requests_per_second", number()),
+            ("estimated_bytes", integer()),
+            ("avg_queue_wait_ms", number()),
+            ("avg_upstream_ms", number()),
+            ("burn_rate_5m", nullable(number())),
+            ("burn_rate_1h", nullable(number())),
+            ("since", nullable(date_time())),
+            ("sample_rate", nullable(number())),
+            ("exact_total_requests", nullable(integer())),
+            ("exact_successful_requests", nullable(integer())),
+            ("exact_failed_requests", nullable(integer())),
+            ("ewma_latency_ms", number()),
+        ]),
+        "RequestMetric": properties(vec![
+            ("id", string()),
+            ("method", string()),
+            ("path", string()),
+            ("status_code", nullable(integer())),
+            ("latency_ms", number()),
+            ("timestamp", date_time()),
+            ("proxied", boolean()),
+            ("upstream", nullable(string())),
+            ("client_cancelled", boolean()),
+            ("circuit_open", boolean()),
+            ("repeat_count", integer()),
+            ("source", string_enum(&["server", "test", "proxy"])),
+            ("bytes", nullable(integer())),
+            ("request_bytes", nullable(integer())),
+            ("response_bytes", nullable(integer())),
+            ("bytes_per_sec", nullable(number())),
+            ("queue_wait_ms", nullable(number())),
+            ("upstream_ms", nullable(number())),
+        ]),
+        "MetricsPage": properties(vec![
+            ("total", integer()),
+            ("offset", integer()),
+            ("limit", integer()),
+            ("items", array_of("RequestMetric")),
+        ]),
+        "TestResult": properties(vec![
+            ("index", integer()),
+            ("success", boolean()),
+            ("status_code", nullable(integer())),
+            ("latency_ms", number()),
+            ("error", nullable(string())),
+            ("connect_ms", nullable(number())),
+            ("ttfb_ms", nullable(number())),
+            ("download_ms", nullable(number())),
+            ("total_ms", nullable(number())),
+            ("step", nullable(integer())),
+            ("attempts", integer()),
+            ("operation", nullable(string())),
+        ]),
+        "TestRunSummary": properties(vec![
+            ("started_at", nullable(date_time())),
+            ("total_requests", integer()),
+            ("successful", integer()),
+            ("failed", integer()),
+            ("avg_latency_ms", number()),
+            ("min_latency_ms", number()),
+            ("max_latency_ms", number()),
+            ("total_duration_ms", number()),
+            ("p95_latency_ms", number()),
+            ("met_slo", nullable(boolean())),
+            ("backoff_ms", number()),
+            ("not_modified_count", integer()),
+            ("connections_primed", integer()),
+            ("retries", integer()),
+            ("latency_over_index", array(number())),
+            ("results", array_of("TestResult")),
+        ]),
+        "UpstreamStatus": object(&[
+            ("url", "string"),
+            ("healthy", "boolean"),
+            ("last_checked", "date-time"),
+            ("last_error", "string"),
+        ]),
+        "ProxyTestRequest": object(&[("method", "string"), ("path", "string")]),
+        "ProxyTestResponse": object(&[
+            ("url", "string"),
+            ("status", "integer"),
+            ("latency_ms", "number"),
+            ("body", "string"),
+            ("truncated", "boolean"),
+            ("error", "string"),
+        ]),
+        "EndpointMetrics": object(&[
+            ("method", "string"),
+            ("path", "string"),
+            ("count", "integer"),
+            ("avg_latency_ms", "number"),
+            ("p95_latency_ms", "number"),
+            ("error_count", "integer"),
+        ]),
+        "HistogramBucket": object(&[("bucket_start_ms", "number"), ("count", "integer")]),
+        "TestStatus": object(&[("running", "boolean")]),
+        "Health": object(&[("status", "string"), ("version", "string")]),
+        "Status": object(&[("status", "string")]),
+        "Ready": {
+            "type": "object",
+            "properties": {
+                "status": { "type": "string", "enum": ["ready", "draining", "upstream_unavailable"] },
+                "upstream": {
+                    "nullable": true,
+                    "allOf": [object(&[
+                        ("target", "string"),
+                        ("reachable", "boolean"),
+                        ("status", "integer"),
+                        ("latency_ms", "number"),
+                        ("error", "string"),
+                        ("checked_at", "date-time"),
+                    ])],
+                },
+            },
+        },
+        "Message": object(&[("message", "string")]),
+        "Error": object(&[("error", "string")]),
+    })
+}
+
+/// An operation with a JSON request body (if any) and `(status, description, content)` responses
+fn op(
+    operation_id: &str,
+    summary: &str,
+    body: Option<Value>,
+    responses: Vec<(&str, &str, Value)>,
+) -> Value {
+    let responses: Map<String, Value> = responses
+        .into_iter()
+        .map(|(status, description, content)| {
+            (
+                status.to_string(),
+                json!({ "description": description, "content": content }),
+            )
+        })
+        .collect();
+
+    let mut operation = json!({
+        "operationId": operation_id,
+        "summary": summary,
+        "responses": responses,
+    });
+    if let Some(body) = body {
+        operation["requestBody"] = json!({ "required": false, "content": json_content(body) });
+    }
+    operation
+}
+
+/// Document the read-only 403 on every non-GET operation
+fn with_read_only_responses(mut paths: Value) -> Value {
+    let operations = paths
+        .as_object_mut()
+        .into_iter()
+        .flat_map(|paths| paths.values_mut())
+        .filter_map(Value::as_object_mut)
+        .flat_map(|item| item.iter_mut())
+        .filter(|(method, _)| *method != "get");
+    for (_, operation) in operations {
+        operation["responses"]["403"] = json!({
+            "description": "The API is read-only",
+            "content": json_content(schema_ref("Error")),
+        });
+    }
+    paths
+}
+
+fn with_params(mut operation: Value, parameters: Vec<Value>) -> Value {
+    operation["parameters"] = Value::Array(parameters);
+    operation
+}
+
+fn query(name: &str, kind: &str, description: &str) -> Value {
+    json!({ "name": name, "in": "query", "description": description, "schema": { "type": kind } })
+}
+
+fn ok(schema: Value) -> (&'static str, &'static str, Value) {
+    ("200", "OK", json_content(schema))
+}
+
+fn ok_text() -> (&'static str, &'static str, Value) {
+    (
+        "200",
+        "OK",
+        content("text/plain", json!({ "type": "string" })),
+    )
+}
+
+fn bad_request() -> (&'static str, &'static str, Value) {
+    ("400", "Invalid request", json_content(schema_ref("Error")))
+}
+
+fn unavailable(schema: Value) -> (&'static str, &'static str, Value) {
+    ("503", "Not ready", json_content(schema))
+}
+
+fn content(media_type: &str, schema: Value) -> Value {
+    json!({ media_type: { "schema": schema } })
+}
+
+fn json_content(schema: Value) -> Value {
+    content("application/json", schema)
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn array_of(name: &str) -> Value {
+    json!({ "type": "array", "items": schema_ref(name) })
+}
+
+/// Object schema from `(name, type)` pairs; every property is nullable
+///
+/// A type of `date-time` means a string in that format.
+fn object(properties: &[(&str, &str)]) -> Value {
+    let properties: Map<String, Value> = properties
+        .iter()
+        .map(|(name, kind)| {
+            let schema = match *kind {
+                "date-time" => json!({ "type": "string", "format": "date-time", "nullable": true }),
+                kind => json!({ "type": kind, "nullable": true }),
+            };
+            (name.to_string(), schema)
+        })
+        .collect();
+    json!({ "type": "object", "properties": properties })
+}
+
+/// Object schema from `(name, schema)` pairs
+fn properties(fields: Vec<(&str, Value)>) -> Value {
+    let properties: Map<String, Value> = fields
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect();
+    json!({ "type": "object", "properties": properties })
+}
+
+fn required(mut schema: Value, names: &[&str]) -> Value {
+    schema["required"] = json!(names);
+    schema
+}
+
+/// One variant of an enum internally tagged by `type`
+fn tagged(tag: &str, mut fields: Vec<(&str, Value)>) -> Value {
+    fields.insert(0, ("type", string_enum(&[tag])));
+    required(properties(fields), &["type"])
+}
+
+/// `schema` or `null`; references are wrapped since `$ref` ignores siblings
+fn nullable(mut schema: Value) -> Value {
+    if schema.get("$ref").is_some() {
+        return json!({ "nullable": true, "allOf": [schema] });
+    }
+    schema["nullable"] = json!(true);
+    schema
+}
+
+fn string_enum(values: &[&str]) -> Value {
+    json!({ "type": "string", "enum": values })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn integer() -> Value {
+    json!({ "type": "integer" })
+}
+
+fn number() -> Value {
+    json!({ "type": "number" })
+}
+
+fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+fn date_time() -> Value {
+    json!({ "type": "string", "format": "date-time" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        ApiConfig, AppConfig, CircuitBreakerConfig, CorsConfig, MetricsConfig, ProxyConfig,
+        ServerConfig, TestConfig,
+    };
+    use crate::metrics::{MetricsPage, MetricsSummary, RequestMetric};
+    use crate::testing::{TestResult, TestRunSummary};
+
+    /// Collect every `$ref` in `value`
+    fn refs<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(fields) => {
+                if let Some(Value::String(target)) = fields.get("$ref") {
+                    found.push(target);
+                }
+                fields.values().for_each(|v| refs(v, found));
+            }
+            Value::Array(items) => items.iter().for_each(|v| refs(v, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_spec_refs_resolve() {
+        let spec = spec();
+        assert_eq!(spec["openapi"], "3.0.3");
+        assert!(spec["paths"]["/api/metrics/clear"]["post"]["responses"]["403"].is_object());
+        assert!(spec["paths"]["/api/metrics"]["get"]["responses"]["403"].is_null());
+
+        let mut found = Vec::new();
+        refs(&spec, &mut found);
+        for target in found {
+            let name = target.trim_start_matches("#/components/schemas/");
+            assert!(
+                spec["components"]["schemas"][name].is_object(),
+                "unresolved {}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_field_types() {
+        let spec = spec();
+        let schemas = &spec["components"]["schemas"];
+
+        let test = &schemas["TestConfig"]["properties"];
+        assert_eq!(test["num_calls"]["type"], "integer");
+        assert_eq!(
+            test["target_url"],
+            json!({ "type": "string", "nullable": true })
+        );
+        assert_eq!(
+            test["slo_p95_ms"],
+            json!({ "type": "number", "nullable": true })
+        );
+        assert_eq!(
+            test["retry_backoff"]["enum"],
+            json!(["linear", "exponential"])
+        );
+        assert_eq!(
+            test["auth"]["allOf"][0]["$ref"],
+            "#/components/schemas/AuthConfig"
+        );
+
+        let proxy = &schemas["ProxyConfig"]["properties"];
+        assert_eq!(
+            proxy["http_version"]["enum"],
+            json!(["auto", "http1", "http2"])
+        );
+        assert_eq!(proxy["max_concurrent_upstream"]["type"], "integer");
+        assert_eq!(proxy["max_concurrent_upstream"]["nullable"], true);
+
+        let metric = &schemas["RequestMetric"]["properties"];
+        assert_eq!(metric["status_code"]["type"], "integer");
+        assert_eq!(metric["timestamp"]["format"], "date-time");
+        assert_eq!(metric["source"]["enum"], json!(["server", "test", "proxy"]));
+
+        let summary = &schemas["MetricsSummary"]["properties"];
+        assert_eq!(summary["since"]["format"], "date-time");
+        assert_eq!(
+            summary["status_distribution"]["additionalProperties"]["type"],
+            "integer"
+        );
+    }
+
+    /// Every field a struct serializes is declared in its schema, and nothing else
+    #[test]
+    fn test_schemas_match_structs() {
+        let spec = spec();
+        let defaults = [
+            ("AppConfig", serde_json::to_value(AppConfig::default())),
+            (
+                "ServerConfig",
+                serde_json::to_value(ServerConfig::default()),
+            ),
+            ("CorsConfig", serde_json::to_value(CorsConfig::default())),
+            ("ProxyConfig", serde_json::to_value(ProxyConfig::default())),
+            (
+                "CircuitBreakerConfig",
+                serde_json::to_value(CircuitBreakerConfig::default()),
+            ),
+            (
+                "MetricsConfig",
+                serde_json::to_value(MetricsConfig::default()),
+            ),
+            ("ApiConfig", serde_json::to_value(ApiConfig::default())),
+            ("TestConfig", serde_json::to_value(TestConfig::default())),
+            (
+                "MetricsSummary",
+                serde_json::to_value(MetricsSummary::default()),
+            ),
+            (
+                "RequestMetric",
+                serde_json::to_value(RequestMetric::new("GET".into(), "/".into())),
+            ),
+            ("MetricsPage", serde_json::to_value(MetricsPage::default())),
+            ("TestResult", serde_json::to_value(TestResult::default())),
+            (
+                "TestRunSummary",
+                serde_json::to_value(TestRunSummary::default()),
+            ),
+        ];
+        for (name, value) in defaults {
+            let fields: Vec<String> = value
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            let declared: Vec<String> = spec["components"]["schemas"][name]["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            assert_eq!(fields, declared, "{}", name);
+        }
+    }
+}
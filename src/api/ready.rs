@@ -0,0 +1,180 @@
+//! Readiness probe reflecting proxy upstream health
+//!
+//! `GET /api/ready` reports not-ready while draining, and, with the proxy
+//! enabled, when a quick `HEAD` to `proxy.target` fails or returns 5xx.
+//! Probe results are cached for `PROBE_CACHE_TTL` so frequent readiness
+//! checks don't hammer the upstream.
+
+use super::ApiState;
+use crate::config::{apply_dns_overrides, AppConfig};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// How long a probe result is reused
+pub const PROBE_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Timeout for a single upstream probe
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Outcome of probing the proxy target
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamProbe {
+    /// Probed URL
+    pub target: String,
+    /// Whether the upstream answered without a 5xx status
+    pub reachable: bool,
+    /// Upstream response status, if it answered
+    pub status: Option<u16>,
+    /// Probe round-trip time in milliseconds
+    pub latency_ms: f64,
+    /// Error if the probe failed to get a response
+    pub error: Option<String>,
+    /// When the probe ran
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Most recent probe, reused until it expires or the target changes
+#[derive(Debug, Default)]
+pub struct ProbeCache {
+    last: Mutex<Option<(Instant, UpstreamProbe)>>,
+}
+
+impl ProbeCache {
+    /// Return a fresh-enough probe of `target`, probing it if needed
+    ///
+    /// Concurrent callers wait for one probe rather than each sending their own.
+    pub async fn probe(&self, config: &AppConfig, target: &str) -> UpstreamProbe {
+        let mut last = self.last.lock().await;
+        if let Some((at, probe)) = last.as_ref() {
+            if probe.target == target && at.elapsed() < PROBE_CACHE_TTL {
+                return probe.clone();
+            }
+        }
+
+        let probe = probe_upstream(config, target).await;
+        *last = Some((Instant::now(), probe.clone()));
+        probe
+    }
+}
+
+async fn probe_upstream(config: &AppConfig, target: &str) -> UpstreamProbe {
+    let checked_at = Utc::now();
+    let start = Instant::now();
+    let result = match apply_dns_overrides(reqwest::Client::builder(), config)
+        .timeout(PROBE_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client.head(target).send().await,
+        Err(e) => Err(e),
+    };
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    match result {
+        Ok(response) => {
+            let status = response.status();
+            UpstreamProbe {
+                target: target.to_string(),
+                reachable: !status.is_server_error(),
+                status: Some(status.as_u16()),
+                latency_ms,
+                error: None,
+                checked_at,
+            }
+        }
+        Err(e) => UpstreamProbe {
+            target: target.to_string(),
+            reachable: false,
+            status: None,
+            latency_ms,
+            error: Some(e.to_string()),
+            checked_at,
+        },
+    }
+}
+
+/// Readiness response
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    /// `ready`, `draining` or `upstream_unavailable`
+    pub status: &'static str,
+    /// Proxy target probe, when the proxy is enabled with a target
+    pub upstream: Option<UpstreamProbe>,
+}
+
+/// Readiness check: 503 while draining or when the proxy target is unreachable
+pub async fn ready(State(state): State<Arc<ApiState>>) -> impl IntoResponse {
+    if !state.lifecycle.is_ready() {
+        let response = ReadyResponse {
+            status: "draining",
+            upstream: None,
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+    }
+
+    let config = state.config.get();
+    let upstream = match &config.proxy.target {
+        Some(target) if config.proxy.enabled => Some(state.probes.probe(&config, target).await),
+        _ => None,
+    };
+
+    if upstream.as_ref().is_some_and(|probe| !probe.reachable) {
+        let response = ReadyResponse {
+            status: "upstream_unavailable",
+            upstream,
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+    }
+
+    let response = ReadyResponse {
+        status: "ready",
+        upstream,
+    };
+    (StatusCode::OK, Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ProxyConfig, SharedConfig};
+
+    fn state_with_target(target: String) -> Arc<ApiState> {
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(target),
+            ..Default::default()
+        });
+        let metrics = crate::metrics::create_shared_metrics(100);
+        let tester = crate::testing::create_shared_tester(config.clone(), metrics.clone());
+        Arc::new(ApiState::new(config, metrics, tester))
+    }
+
+    #[tokio::test]
+    async fn test_ready_reflects_upstream() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().fallback(|| async { "ok" });
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let state = state_with_target(format!("http://{}", addr));
+        let response = ready(State(state.clone())).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A dead target is unavailable, and the failed probe is cached
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let state = state_with_target(dead.clone());
+        let response = ready(State(state.clone())).await.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let config = state.config.get();
+        let first = state.probes.probe(&config, &dead).await;
+        let second = state.probes.probe(&config, &dead).await;
+        assert_eq!(first.checked_at, second.checked_at);
+    }
+}
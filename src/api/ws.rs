@@ -0,0 +1,51 @@
+//! Live request feed over WebSocket
+//!
+//! `GET /api/ws/requests` pushes every newly recorded metric to the client as
+//! a JSON text frame. Clients that fall behind skip the metrics they missed
+//! rather than slowing down recording.
+
+use super::ApiState;
+use crate::metrics::RequestMetric;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Upgrade to a WebSocket streaming recorded metrics
+pub async fn request_feed(State(state): State<Arc<ApiState>>, ws: WebSocketUpgrade) -> Response {
+    let feed = state.metrics.subscribe();
+    ws.on_upgrade(move |socket| stream_metrics(socket, feed))
+}
+
+async fn stream_metrics(mut socket: WebSocket, mut feed: broadcast::Receiver<RequestMetric>) {
+    loop {
+        tokio::select! {
+            metric = feed.recv() => {
+                let metric = match metric {
+                    Ok(metric) => metric,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!(skipped = %skipped, "WebSocket client lagging, skipped metrics");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Ok(json) = serde_json::to_string(&metric) else {
+                    continue;
+                };
+                if socket.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            // Stop when the client closes or the connection drops
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
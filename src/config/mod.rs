@@ -3,7 +3,9 @@
 //! Supports configuration via file and environment variables.
 
 use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
 /// Server configuration
@@ -15,6 +17,70 @@ pub struct ServerConfig {
     /// Port to listen on
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Additional `addr:port` listen addresses; when set, replaces host/port
+    #[serde(default)]
+    pub listen: Vec<String>,
+    /// OTLP/HTTP endpoint to export traces to (requires the `otel` feature)
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// Return echoed headers as a JSON object instead of `[key, value]` pairs
+    #[serde(default)]
+    pub echo_headers_as_object: bool,
+    /// With `echo_headers_as_object`, collect repeated headers into arrays
+    /// instead of keeping the last value
+    #[serde(default)]
+    pub echo_headers_collect_duplicates: bool,
+    /// Seconds to keep serving after `POST /api/drain` before exiting
+    #[serde(default = "default_drain_grace_secs")]
+    pub drain_grace_secs: u64,
+    /// Seconds to let in-flight requests finish after SIGINT/SIGTERM or a drain
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// Largest request body the echo handler accepts (larger bodies get 413)
+    #[serde(default = "default_max_echo_body_bytes")]
+    pub max_echo_body_bytes: usize,
+    /// Compress responses with gzip/deflate when the client accepts it
+    #[serde(default = "default_compression")]
+    pub compression: bool,
+    /// Append one NDJSON line per non-API request to this file
+    #[serde(default)]
+    pub access_log_path: Option<String>,
+    /// Delay every echo response by this many milliseconds (`X-Delay-Ms` overrides it)
+    #[serde(default)]
+    pub inject_delay_ms: Option<u64>,
+    /// Cross-origin requests the server allows
+    #[serde(default)]
+    pub cors: CorsConfig,
+}
+
+/// CORS settings; an empty list (or one containing `"*"`) allows any value
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CorsConfig {
+    /// Origins sent back in `Access-Control-Allow-Origin`, e.g. `http://localhost:5173`
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed in preflight responses
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed in preflight responses
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+}
+
+fn default_compression() -> bool {
+    true
+}
+
+fn default_drain_grace_secs() -> u64 {
+    30
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    10
+}
+
+fn default_max_echo_body_bytes() -> usize {
+    1024 * 1024
 }
 
 fn default_host() -> String {
@@ -30,12 +96,39 @@ impl Default for ServerConfig {
         Self {
             host: default_host(),
             port: default_port(),
+            listen: Vec::new(),
+            otlp_endpoint: None,
+            echo_headers_as_object: false,
+            echo_headers_collect_duplicates: false,
+            drain_grace_secs: default_drain_grace_secs(),
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            max_echo_body_bytes: default_max_echo_body_bytes(),
+            compression: default_compression(),
+            access_log_path: None,
+            inject_delay_ms: None,
+            cors: CorsConfig::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Addresses to bind, falling back to `host:port` when `listen` is empty
+    pub fn listen_addrs(&self) -> Vec<String> {
+        if !self.listen.is_empty() {
+            return self.listen.clone();
+        }
+
+        // Bracket IPv6 literals so the port separator is unambiguous
+        if self.host.contains(':') && !self.host.starts_with('[') {
+            vec![format!("[{}]:{}", self.host, self.port)]
+        } else {
+            vec![format!("{}:{}", self.host, self.port)]
         }
     }
 }
 
 /// Proxy configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyConfig {
     /// Whether proxy mode is enabled
     #[serde(default)]
@@ -43,17 +136,329 @@ pub struct ProxyConfig {
     /// Target URL to forward requests to
     #[serde(default)]
     pub target: Option<String>,
+    /// Allow overriding the target per request via the `X-Proxy-Target` header
+    #[serde(default)]
+    pub allow_target_header: bool,
+    /// Hosts (or `host:port`) that `X-Proxy-Target` may point to
+    #[serde(default)]
+    pub target_allowlist: Vec<String>,
+    /// Pool of upstreams to round-robin across (takes precedence over `target`)
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Path-prefix routes; the longest matching prefix wins over `targets` and `target`
+    #[serde(default)]
+    pub routes: Vec<ProxyRoute>,
+    /// Path probed on each pooled upstream to determine its health
+    #[serde(default)]
+    pub health_path: Option<String>,
+    /// Interval between health checks in seconds
+    #[serde(default = "default_health_interval_secs")]
+    pub health_interval_secs: u64,
+    /// WASM plugin applied to proxied requests and responses (loaded at startup)
+    #[serde(default)]
+    pub plugin_path: Option<String>,
+    /// Fuel each plugin hook call may burn, roughly one unit per WASM instruction (applied at startup)
+    #[serde(default = "default_plugin_fuel")]
+    pub plugin_fuel: u64,
+    /// Pace request bodies sent upstream to this many bytes per second
+    #[serde(default)]
+    pub max_upstream_bytes_per_sec: Option<u64>,
+    /// Pace response bodies from the upstream to this many bytes per second
+    #[serde(default)]
+    pub max_downstream_bytes_per_sec: Option<u64>,
+    /// Maximum number of in-flight upstream requests; others queue (applied at startup)
+    #[serde(default)]
+    pub max_concurrent_upstream: Option<usize>,
+    /// Give up on an upstream request after this many seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Largest request or upstream response body the proxy passes on, in bytes
+    #[serde(default = "default_proxy_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Send `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` upstream
+    #[serde(default)]
+    pub add_forwarded_headers: bool,
+    /// Request headers dropped before forwarding (names are case-insensitive)
+    #[serde(default)]
+    pub request_headers_remove: Vec<String>,
+    /// Headers set on forwarded requests, replacing any the client sent
+    #[serde(default)]
+    pub request_headers_add: Vec<(String, String)>,
+    /// Upstream response headers dropped before replying (names are case-insensitive)
+    #[serde(default)]
+    pub response_headers_remove: Vec<String>,
+    /// Headers set on proxied responses, replacing any the upstream sent
+    #[serde(default)]
+    pub response_headers_add: Vec<(String, String)>,
+    /// HTTP version spoken to upstreams; applies to the whole proxy client,
+    /// so changing it takes effect on restart
+    #[serde(default)]
+    pub http_version: HttpVersion,
+    /// Answer 503 without contacting an upstream that keeps failing
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+/// HTTP version the proxy uses to reach upstreams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    /// HTTP/1.1, upgrading to HTTP/2 when negotiated over TLS (ALPN)
+    #[default]
+    Auto,
+    /// HTTP/1.1 only
+    Http1,
+    /// HTTP/2 with prior knowledge, including cleartext h2c
+    Http2,
+}
+
+/// When an upstream's circuit opens and how long it stays open
+///
+/// Only transport failures (connection errors, timeouts) count; an upstream
+/// that answers, even with a 5xx, is up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    #[serde(default = "default_breaker_failures")]
+    pub failure_threshold: u32,
+    /// The failures must all happen within this many seconds
+    #[serde(default = "default_breaker_window_secs")]
+    pub window_secs: u64,
+    /// Seconds the circuit stays open before a single probe request is let through
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+fn default_breaker_failures() -> u32 {
+    5
+}
+
+fn default_breaker_window_secs() -> u64 {
+    30
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    10
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_breaker_failures(),
+            window_secs: default_breaker_window_secs(),
+            cooldown_secs: default_breaker_cooldown_secs(),
+        }
+    }
+}
+
+/// Route requests under a path prefix to a specific upstream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyRoute {
+    /// Path prefix, matched on segment boundaries (`/api` matches `/api/x`, not `/apix`)
+    pub prefix: String,
+    /// Upstream base URL
+    pub target: String,
+    /// Remove the prefix from the forwarded path
+    #[serde(default)]
+    pub strip_prefix: bool,
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_health_interval_secs() -> u64 {
+    10
+}
+
+fn default_plugin_fuel() -> u64 {
+    100_000_000
+}
+
+fn default_proxy_max_body_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: None,
+            allow_target_header: false,
+            target_allowlist: Vec::new(),
+            targets: Vec::new(),
+            routes: Vec::new(),
+            health_path: None,
+            health_interval_secs: default_health_interval_secs(),
+            plugin_path: None,
+            plugin_fuel: default_plugin_fuel(),
+            max_upstream_bytes_per_sec: None,
+            max_downstream_bytes_per_sec: None,
+            max_concurrent_upstream: None,
+            timeout_secs: default_timeout_secs(),
+            max_body_bytes: default_proxy_max_body_bytes(),
+            add_forwarded_headers: false,
+            request_headers_remove: Vec::new(),
+            request_headers_add: Vec::new(),
+            response_headers_remove: Vec::new(),
+            response_headers_add: Vec::new(),
+            http_version: HttpVersion::default(),
+            circuit_breaker: None,
+        }
+    }
+}
+
+/// Metrics collection configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// Maximum number of metrics to keep in memory
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// How the oldest metrics are evicted once `max_entries` is reached
+    #[serde(default)]
+    pub eviction: EvictionMode,
+    /// Fraction of `max_entries` removed per bulk eviction
+    #[serde(default = "default_evict_fraction")]
+    pub evict_fraction: f64,
+    /// Maximum estimated memory for stored metrics, in bytes
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+    /// Prune metrics older than this many seconds, regardless of count
+    #[serde(default)]
+    pub retention_secs: Option<i64>,
+    /// Fold identical consecutive requests (method, path, status) into one entry
+    #[serde(default)]
+    pub coalesce_identical: bool,
+    /// Maximum latency difference in milliseconds for requests to be coalesced
+    #[serde(default = "default_coalesce_tolerance_ms")]
+    pub coalesce_tolerance_ms: f64,
+    /// Append every recorded metric to this CSV file as it arrives
+    #[serde(default)]
+    pub csv_stream_path: Option<String>,
+    /// Rotate the CSV stream to a new numbered file past this size, in bytes
+    #[serde(default)]
+    pub csv_max_bytes: Option<u64>,
+    /// Persist metrics to this SQLite database (requires the `sqlite` feature)
+    #[serde(default)]
+    pub sqlite_path: Option<String>,
+    /// Metrics the SQLite database keeps (defaults to `max_entries`)
+    ///
+    /// `/api/metrics/history` reads the database, so a larger value makes
+    /// history beyond the in-memory window reachable.
+    #[serde(default)]
+    pub sqlite_max_rows: Option<usize>,
+    /// Format of generated metric IDs
+    #[serde(default)]
+    pub id_format: IdFormat,
+    /// SLO used for burn-rate calculation (e.g. 99% of requests under 200ms)
+    #[serde(default)]
+    pub slo: Option<SloConfig>,
+    /// Fraction (0.0-1.0) of requests whose metrics are stored; exact counts are kept apart
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    /// Weight (0.0-1.0] of each new latency in the moving average `ewma_latency_ms`;
+    /// lower is smoother
+    #[serde(default = "default_ewma_alpha")]
+    pub ewma_alpha: f64,
+}
+
+/// Service level objective: `target_pct` of requests succeed within `latency_ms`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SloConfig {
+    /// Percentage of requests that must be good (e.g. 99.0)
+    pub target_pct: f64,
+    /// Latency threshold in milliseconds for a request to count as good
+    pub latency_ms: f64,
+}
+
+/// Eviction strategy for a full metrics buffer
+///
+/// Bulk eviction removes `evict_fraction` of the buffer at once, so evictions
+/// are rare but the retained window (and memory) saw-tooths. Ring eviction
+/// drops exactly one oldest entry per insert for a steady window at the cost
+/// of evicting on every insert once full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionMode {
+    /// Drop `evict_fraction` of the oldest entries when full
+    #[default]
+    Bulk,
+    /// Drop one oldest entry per insert when full
+    Ring,
+}
+
+fn default_evict_fraction() -> f64 {
+    0.1
+}
+
+fn default_sample_rate() -> f64 {
+    1.0
+}
+
+pub(crate) fn default_ewma_alpha() -> f64 {
+    0.1
+}
+
+/// Format of generated metric IDs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdFormat {
+    /// Random UUIDv4
+    #[default]
+    Uuid4,
+    /// Time-ordered UUIDv7
+    Uuid7,
+    /// Sortable ULID
+    Ulid,
+    /// Process-wide counter starting at 1, zero-padded to 20 digits
+    Sequential,
+}
+
+fn default_coalesce_tolerance_ms() -> f64 {
+    50.0
+}
+
+fn default_max_entries() -> usize {
+    10000
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: default_max_entries(),
+            eviction: EvictionMode::default(),
+            evict_fraction: default_evict_fraction(),
+            max_bytes: None,
+            retention_secs: None,
+            coalesce_identical: false,
+            coalesce_tolerance_ms: default_coalesce_tolerance_ms(),
+            csv_stream_path: None,
+            csv_max_bytes: None,
+            sqlite_path: None,
+            sqlite_max_rows: None,
+            id_format: IdFormat::default(),
+            slo: None,
+            sample_rate: default_sample_rate(),
+            ewma_alpha: default_ewma_alpha(),
+        }
+    }
 }
 
 /// API testing configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestConfig {
-    /// Number of times to call the API
+    /// Number of times to call the API (0 = unlimited when `duration_secs` is set)
     #[serde(default = "default_num_calls")]
     pub num_calls: u32,
-    /// Frequency in milliseconds between calls
+    /// Stop dispatching after this many seconds, or at `num_calls`, whichever comes first
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+    /// Interval in milliseconds between dispatched calls, across all workers
     #[serde(default = "default_frequency_ms")]
     pub frequency_ms: u64,
+    /// Maximum number of in-flight calls (workers sharing the `num_calls` total)
+    #[serde(default = "default_concurrency")]
+    pub concurrency: u32,
     /// HTTP method (GET, POST, PUT, DELETE, etc.)
     #[serde(default = "default_method")]
     pub method: String,
@@ -63,9 +468,191 @@ pub struct TestConfig {
     /// Request body (for POST/PUT)
     #[serde(default)]
     pub body: Option<String>,
+    /// How `body` is encoded, and any files to upload
+    #[serde(default)]
+    pub body_type: BodyType,
     /// Custom headers as key-value pairs
     #[serde(default)]
     pub headers: Vec<(String, String)>,
+    /// Pause dispatch when a 429/503 response carries `Retry-After`
+    #[serde(default)]
+    pub honor_retry_after: bool,
+    /// Revalidate with `If-None-Match`/`If-Modified-Since` using the validators
+    /// from earlier responses, counting 304s as cache hits
+    #[serde(default)]
+    pub conditional: bool,
+    /// Open `concurrency` connections with untimed requests before the run starts
+    #[serde(default)]
+    pub prime_connections: bool,
+    /// Require this exact status code (instead of any 2xx) for a call to succeed
+    #[serde(default)]
+    pub expect_status: Option<u16>,
+    /// Require the response body to contain this text for a call to succeed
+    #[serde(default)]
+    pub expect_body_contains: Option<String>,
+    /// Credentials sent as the `Authorization` header (an explicit header in `headers` wins)
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+    /// Response values captured into `{{var_name}}` variables for later calls
+    #[serde(default)]
+    pub extract: Vec<Extraction>,
+    /// Weighted mix of endpoints; each call picks a step at random by weight
+    /// instead of calling `target_url` with `method` and `body`
+    #[serde(default)]
+    pub scenario: Option<Vec<WeightedStep>>,
+    /// Extra attempts for calls that fail to connect or return a `retry_on_status` code
+    #[serde(default)]
+    pub retries: u32,
+    /// Base delay before a retry, in milliseconds
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// How the retry delay grows with each attempt
+    #[serde(default)]
+    pub retry_backoff: RetryBackoff,
+    /// Status codes worth retrying
+    #[serde(default = "default_retry_on_status")]
+    pub retry_on_status: Vec<u16>,
+    /// Follow 3xx redirects; when off, the 3xx response itself is recorded
+    #[serde(default = "default_follow_redirects")]
+    pub follow_redirects: bool,
+    /// Fail a call that takes longer than this many seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Write each run's summary as JSON here (a timestamped file if it is a directory)
+    #[serde(default)]
+    pub save_results_path: Option<String>,
+    /// Ceiling on calls started per second across all workers (wins over a shorter `frequency_ms`)
+    #[serde(default)]
+    pub max_rps: Option<u32>,
+    /// Keep cookies set by responses and send them on later calls of the same run
+    #[serde(default)]
+    pub enable_cookies: bool,
+    /// SLO: the run's 95th percentile latency must be at most this many milliseconds
+    #[serde(default)]
+    pub slo_p95_ms: Option<f64>,
+    /// SLO: at most this fraction (0.0-1.0) of the run's calls may fail
+    #[serde(default)]
+    pub slo_error_rate: Option<f64>,
+    /// POST this GraphQL query to `target_url` instead of sending `method` and `body`
+    /// (ignored with a `scenario`)
+    #[serde(default)]
+    pub graphql: Option<GraphQlRequest>,
+}
+
+/// A GraphQL operation sent by test calls
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlRequest {
+    /// Query or mutation document
+    pub query: String,
+    /// Variables for the operation
+    #[serde(default)]
+    pub variables: Option<serde_json::Value>,
+}
+
+impl GraphQlRequest {
+    /// The JSON request body: `{"query": ..., "variables": ...}`
+    pub fn body(&self) -> String {
+        let mut body = serde_json::json!({ "query": self.query });
+        if let Some(variables) = &self.variables {
+            body["variables"] = variables.clone();
+        }
+        body.to_string()
+    }
+}
+
+/// Encoding of a test call's request body
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BodyType {
+    /// `body` sent as-is; set `Content-Type` in `headers`
+    Raw,
+    /// `body` sent as-is with `Content-Type: application/json`
+    #[default]
+    Json,
+    /// `body` is a JSON object of fields, sent URL-encoded
+    Form,
+    /// `multipart/form-data` with `body` (a JSON object, optional) as text
+    /// fields and each `(field, path)` file attached
+    Multipart {
+        #[serde(default)]
+        files: Vec<(String, String)>,
+    },
+}
+
+/// Growth of the delay between retries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetryBackoff {
+    /// `retry_backoff_ms`, then twice that, three times, ...
+    Linear,
+    /// `retry_backoff_ms`, then doubling with each attempt
+    #[default]
+    Exponential,
+}
+
+impl RetryBackoff {
+    /// Delay before retry number `retry` (1-based)
+    pub fn delay(self, base_ms: u64, retry: u32) -> std::time::Duration {
+        let ms = match self {
+            Self::Linear => base_ms.saturating_mul(retry as u64),
+            Self::Exponential => base_ms.saturating_mul(1u64 << retry.saturating_sub(1).min(16)),
+        };
+        std::time::Duration::from_millis(ms)
+    }
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    100
+}
+
+fn default_retry_on_status() -> Vec<u16> {
+    vec![502, 503, 504]
+}
+
+fn default_follow_redirects() -> bool {
+    true
+}
+
+/// One endpoint in a weighted test scenario
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeightedStep {
+    /// Relative share of calls sent to this step
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// HTTP method
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Absolute URL, or a path resolved against `target_url`
+    pub path_or_url: String,
+    /// Request body
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// A JSON response value captured into a test variable
+///
+/// `target_url`, `headers` and `body` can reference it as `{{var_name}}`.
+/// When the path matches nothing the variable is set to an empty string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Extraction {
+    /// Path into the JSON response, e.g. `$.data.token`
+    pub json_path: String,
+    /// Variable to store the value in
+    pub var_name: String,
+}
+
+/// Authorization for API test requests
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AuthConfig {
+    /// `Authorization: Bearer <token>`
+    Bearer { token: String },
+    /// `Authorization: Basic <base64(username:password)>`
+    Basic { username: String, password: String },
 }
 
 fn default_num_calls() -> u32 {
@@ -76,6 +663,10 @@ fn default_frequency_ms() -> u64 {
     100
 }
 
+fn default_concurrency() -> u32 {
+    1
+}
+
 fn default_method() -> String {
     "GET".to_string()
 }
@@ -84,15 +675,46 @@ impl Default for TestConfig {
     fn default() -> Self {
         Self {
             num_calls: default_num_calls(),
+            duration_secs: None,
             frequency_ms: default_frequency_ms(),
+            concurrency: default_concurrency(),
             method: default_method(),
             target_url: None,
             body: None,
+            body_type: BodyType::default(),
             headers: Vec::new(),
+            honor_retry_after: false,
+            conditional: false,
+            prime_connections: false,
+            expect_status: None,
+            expect_body_contains: None,
+            auth: None,
+            extract: Vec::new(),
+            scenario: None,
+            retries: 0,
+            retry_backoff_ms: default_retry_backoff_ms(),
+            retry_backoff: RetryBackoff::default(),
+            retry_on_status: default_retry_on_status(),
+            follow_redirects: default_follow_redirects(),
+            timeout_secs: default_timeout_secs(),
+            save_results_path: None,
+            max_rps: None,
+            enable_cookies: false,
+            slo_p95_ms: None,
+            slo_error_rate: None,
+            graphql: None,
         }
     }
 }
 
+/// Management API configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ApiConfig {
+    /// Reject mutating (non-GET) requests to `/api/*` with 403
+    #[serde(default)]
+    pub read_only: bool,
+}
+
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
@@ -105,6 +727,16 @@ pub struct AppConfig {
     /// Test configuration
     #[serde(default)]
     pub test: TestConfig,
+    /// Metrics configuration
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// Management API configuration
+    #[serde(default)]
+    pub api: ApiConfig,
+    /// Hostname to IP overrides for outgoing requests, like an in-process
+    /// `/etc/hosts`; repeat a host or comma-separate IPs to give it several
+    #[serde(default)]
+    pub dns_overrides: Vec<(String, String)>,
 }
 
 impl AppConfig {
@@ -132,23 +764,544 @@ impl AppConfig {
 
         let config = config.build()?;
         let app_config: AppConfig = config.try_deserialize()?;
+        app_config.validate_strict()?;
 
         Ok(app_config)
     }
 
     /// Load configuration from a specific file
+    ///
+    /// The format is picked by extension (`.toml`, `.json`, `.yaml`/`.yml`);
+    /// if that fails or the extension is unknown, every format is tried.
     pub fn load_from_file(path: &str) -> anyhow::Result<Self> {
         let contents = std::fs::read_to_string(path)?;
-        let config: AppConfig =
-            toml::from_str(&contents).or_else(|_| serde_json::from_str(&contents))?;
+        let config: Self = Self::parse_as(path, &contents)?;
+        config.validate_strict()?;
         Ok(config)
     }
+
+    /// Load configuration from a file, deep-merging `[profiles.<name>]` over it
+    ///
+    /// Profile values replace base values key by key, recursing into tables;
+    /// anything the profile leaves out keeps the base setting.
+    pub fn load_profile(path: &str, name: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut value: serde_json::Value = Self::parse_as(path, &contents)?;
+        let profiles = value
+            .as_object_mut()
+            .and_then(|root| root.remove("profiles"))
+            .unwrap_or_default();
+
+        let Some(profile) = profiles.get(name) else {
+            let available: Vec<&str> = profiles
+                .as_object()
+                .map(|profiles| profiles.keys().map(String::as_str).collect())
+                .unwrap_or_default();
+            if available.is_empty() {
+                anyhow::bail!("Unknown profile '{}': {} defines no profiles", name, path);
+            }
+            anyhow::bail!(
+                "Unknown profile '{}' (available: {})",
+                name,
+                available.join(", ")
+            );
+        };
+        merge_json(&mut value, profile.clone());
+
+        let config: Self = serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("Invalid config for profile '{}': {}", name, e))?;
+        config.validate_strict()?;
+        Ok(config)
+    }
+
+    /// Settings that differ in `other` but are only read at startup
+    pub fn restart_required_changes(&self, other: &AppConfig) -> Vec<&'static str> {
+        fn changed<T: Serialize>(a: &T, b: &T) -> bool {
+            serde_json::to_value(a).ok() != serde_json::to_value(b).ok()
+        }
+
+        [
+            (
+                "server.host",
+                changed(&self.server.host, &other.server.host),
+            ),
+            (
+                "server.port",
+                changed(&self.server.port, &other.server.port),
+            ),
+            (
+                "server.listen",
+                changed(&self.server.listen, &other.server.listen),
+            ),
+            (
+                "server.otlp_endpoint",
+                changed(&self.server.otlp_endpoint, &other.server.otlp_endpoint),
+            ),
+            (
+                "server.access_log_path",
+                changed(&self.server.access_log_path, &other.server.access_log_path),
+            ),
+            (
+                "server.cors",
+                changed(&self.server.cors, &other.server.cors),
+            ),
+            (
+                "proxy.http_version",
+                changed(&self.proxy.http_version, &other.proxy.http_version),
+            ),
+            (
+                "proxy.plugin_path",
+                changed(&self.proxy.plugin_path, &other.proxy.plugin_path),
+            ),
+            (
+                "proxy.plugin_fuel",
+                changed(&self.proxy.plugin_fuel, &other.proxy.plugin_fuel),
+            ),
+            (
+                "proxy.max_concurrent_upstream",
+                changed(
+                    &self.proxy.max_concurrent_upstream,
+                    &other.proxy.max_concurrent_upstream,
+                ),
+            ),
+            ("metrics", changed(&self.metrics, &other.metrics)),
+            (
+                "dns_overrides",
+                changed(&self.dns_overrides, &other.dns_overrides),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+
+    /// Parse `contents` as any config format into `T`
+    fn parse_as<T: DeserializeOwned>(path: &str, contents: &str) -> anyhow::Result<T> {
+        let preferred = ConfigFormat::from_extension(path);
+        let formats = preferred.into_iter().chain(
+            ConfigFormat::ALL
+                .into_iter()
+                .filter(|f| Some(*f) != preferred),
+        );
+
+        let mut errors = Vec::new();
+        for format in formats {
+            match format.parse(contents) {
+                Ok(config) => return Ok(config),
+                Err(e) => errors.push(format!("{}: {}", format.name(), e)),
+            }
+        }
+        anyhow::bail!(
+            "Could not parse {} as TOML, JSON or YAML:\n  {}",
+            path,
+            errors.join("\n  ")
+        )
+    }
+
+    /// Check settings that deserialize fine but are semantically invalid
+    ///
+    /// Returns every problem found. Loading only rejects the ones that would
+    /// break the server outright (see `validate_strict`); the rest surface
+    /// here so they can be reported before they fail deep in a request.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        if let Err(e) = self.validate_strict() {
+            problems.push(e.to_string());
+        }
+
+        if self.server.listen.is_empty() {
+            if self.server.host.trim().is_empty() {
+                problems.push("server.host is empty".to_string());
+            }
+            if self.server.port == 0 {
+                problems.push("server.port must be between 1 and 65535".to_string());
+            }
+        }
+
+        if self.proxy.enabled {
+            let targets =
+                self.proxy
+                    .target
+                    .iter()
+                    .map(|target| ("proxy.target".to_string(), target))
+                    .chain(
+                        self.proxy
+                            .targets
+                            .iter()
+                            .enumerate()
+                            .map(|(i, target)| (format!("proxy.targets[{}]", i), target)),
+                    )
+                    .chain(
+                        self.proxy.routes.iter().enumerate().map(|(i, route)| {
+                            (format!("proxy.routes[{}].target", i), &route.target)
+                        }),
+                    );
+            for (field, target) in targets {
+                if let Err(e) = check_http_url(target) {
+                    problems.push(format!("{} {:?} is not a valid URL: {}", field, target, e));
+                }
+            }
+        }
+
+        let cors = &self.server.cors;
+        for origin in &cors.allowed_origins {
+            if origin != "*" && reqwest::header::HeaderValue::from_str(origin).is_err() {
+                problems.push(format!(
+                    "server.cors.allowed_origins has invalid origin {:?}",
+                    origin
+                ));
+            }
+        }
+        for method in &cors.allowed_methods {
+            if method != "*" && reqwest::Method::from_bytes(method.as_bytes()).is_err() {
+                problems.push(format!(
+                    "server.cors.allowed_methods has invalid method {:?}",
+                    method
+                ));
+            }
+        }
+        for name in &cors.allowed_headers {
+            if name != "*" && reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                problems.push(format!(
+                    "server.cors.allowed_headers has invalid header {:?}",
+                    name
+                ));
+            }
+        }
+
+        if self.metrics.max_entries == 0 {
+            problems.push("metrics.max_entries must be at least 1".to_string());
+        }
+        let fraction = self.metrics.evict_fraction;
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            problems.push(format!(
+                "metrics.evict_fraction {} must be in (0, 1]",
+                fraction
+            ));
+        }
+
+        let test = &self.test;
+        if reqwest::Method::from_bytes(test.method.as_bytes()).is_err() {
+            problems.push(format!(
+                "test.method {:?} is not a valid HTTP method",
+                test.method
+            ));
+        }
+        for (i, step) in test.scenario.iter().flatten().enumerate() {
+            if reqwest::Method::from_bytes(step.method.as_bytes()).is_err() {
+                problems.push(format!(
+                    "test.scenario[{}].method {:?} is not a valid HTTP method",
+                    i, step.method
+                ));
+            }
+        }
+        if let Some(target) = &test.target_url {
+            if let Err(e) = check_http_url(target) {
+                problems.push(format!(
+                    "test.target_url {:?} is not a valid URL: {}",
+                    target, e
+                ));
+            }
+        }
+        if test.num_calls == 0 && test.duration_secs.is_none() {
+            problems
+                .push("test.num_calls must be at least 1 unless duration_secs is set".to_string());
+        }
+        if test.frequency_ms > MAX_FREQUENCY_MS {
+            problems.push(format!(
+                "test.frequency_ms must be at most {} (one hour)",
+                MAX_FREQUENCY_MS
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Reject settings the server can't start or run with
+    fn validate_strict(&self) -> anyhow::Result<()> {
+        parse_dns_overrides(&self.dns_overrides)?;
+        validate_header_rules(&self.proxy)?;
+        Ok(())
+    }
+
+    /// Parsed `dns_overrides`, grouped by host; invalid entries are skipped
+    pub fn resolved_dns_overrides(&self) -> Vec<(String, Vec<SocketAddr>)> {
+        parse_dns_overrides(&self.dns_overrides).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Ignoring invalid dns_overrides");
+            Vec::new()
+        })
+    }
+}
+
+/// Problems `AppConfig::validate` found in a configuration
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid configuration: {}", .0.join("; "))]
+pub struct InvalidConfig(pub Vec<String>);
+
+/// Config file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Every format, in the order content is sniffed
+    const ALL: [Self; 3] = [Self::Toml, Self::Json, Self::Yaml];
+
+    fn from_extension(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Toml => "TOML",
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+        }
+    }
+
+    fn parse<T: DeserializeOwned>(self, contents: &str) -> anyhow::Result<T> {
+        match self {
+            Self::Toml => Ok(toml::from_str(contents)?),
+            Self::Json => Ok(serde_json::from_str(contents)?),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => Ok(serde_yaml::from_str(contents)?),
+            #[cfg(not(feature = "yaml"))]
+            Self::Yaml => anyhow::bail!("api-check was built without the `yaml` feature"),
+        }
+    }
+}
+
+/// Longest sensible interval between test calls
+const MAX_FREQUENCY_MS: u64 = 60 * 60 * 1000;
+
+/// Check `url` is an absolute `http`/`https` URL
+fn check_http_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(format!("unsupported scheme {}", scheme)),
+    }
+}
+
+/// Check the proxy's header rules name valid headers with valid values
+fn validate_header_rules(proxy: &ProxyConfig) -> anyhow::Result<()> {
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    let removed = proxy
+        .request_headers_remove
+        .iter()
+        .chain(&proxy.response_headers_remove);
+    let added = proxy
+        .request_headers_add
+        .iter()
+        .chain(&proxy.response_headers_add);
+
+    for name in removed.chain(added.clone().map(|(name, _)| name)) {
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid proxy header name: {}", name))?;
+    }
+    for (name, value) in added {
+        HeaderValue::from_str(value)
+            .map_err(|_| anyhow::anyhow!("Invalid value for proxy header {}", name))?;
+    }
+    Ok(())
+}
+
+/// Parse `(host, ips)` overrides into socket addresses grouped by host
+///
+/// Port 0 tells the HTTP client to use the URL's port.
+pub fn parse_dns_overrides(
+    overrides: &[(String, String)],
+) -> anyhow::Result<Vec<(String, Vec<SocketAddr>)>> {
+    let mut resolved: Vec<(String, Vec<SocketAddr>)> = Vec::new();
+
+    for (host, ips) in overrides {
+        let host = host.trim().to_ascii_lowercase();
+        if host.is_empty() {
+            anyhow::bail!("dns_overrides entry has an empty hostname");
+        }
+
+        let addrs = ips
+            .split(',')
+            .map(|ip| {
+                ip.trim()
+                    .parse::<IpAddr>()
+                    .map(|ip| SocketAddr::new(ip, 0))
+                    .map_err(|_| {
+                        anyhow::anyhow!(
+                            "Invalid IP {:?} for host {} in dns_overrides",
+                            ip.trim(),
+                            host
+                        )
+                    })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        match resolved.iter_mut().find(|(h, _)| *h == host) {
+            Some((_, existing)) => existing.extend(addrs),
+            None => resolved.push((host, addrs)),
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Apply the configured DNS overrides to an HTTP client builder
+pub fn apply_dns_overrides(
+    mut builder: reqwest::ClientBuilder,
+    config: &AppConfig,
+) -> reqwest::ClientBuilder {
+    for (host, addrs) in config.resolved_dns_overrides() {
+        builder = builder.resolve_to_addrs(&host, &addrs);
+    }
+    builder
+}
+
+/// Partial configuration for `PATCH /api/config`
+///
+/// Each section is a JSON object whose keys are merged into the current
+/// section field by field (nested objects merge recursively, arrays and other
+/// values replace); absent sections and keys are left untouched. Keys that
+/// name no configuration field are rejected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PartialAppConfig {
+    #[serde(default)]
+    pub server: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub proxy: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub test: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub metrics: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub api: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub dns_overrides: Option<Vec<(String, String)>>,
+    /// Unknown top-level sections, reported by `apply_to`
+    #[serde(flatten, skip_serializing)]
+    pub unknown: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PartialAppConfig {
+    /// Apply the patch to `config`
+    ///
+    /// Fails if a merged section no longer parses, or with `InvalidConfig`
+    /// listing every key that names no configuration field.
+    pub fn apply_to(self, config: &AppConfig) -> anyhow::Result<AppConfig> {
+        let mut merged = serde_json::to_value(config)?;
+        let sections: Vec<_> = [
+            ("server", self.server),
+            ("proxy", self.proxy),
+            ("test", self.test),
+            ("metrics", self.metrics),
+            ("api", self.api),
+        ]
+        .into_iter()
+        .filter_map(|(name, patch)| patch.map(|patch| (name, patch)))
+        .collect();
+        for (name, patch) in &sections {
+            merge_json(&mut merged[name], serde_json::Value::Object(patch.clone()));
+        }
+        if let Some(dns_overrides) = self.dns_overrides {
+            merged["dns_overrides"] = serde_json::to_value(dns_overrides)?;
+        }
+
+        let patched: AppConfig = serde_json::from_value(merged)
+            .map_err(|e| anyhow::anyhow!("Invalid config patch: {}", e))?;
+
+        // serde skips unknown keys, so they are missing once the result is serialized again
+        let applied = serde_json::to_value(&patched)?;
+        let mut unknown: Vec<String> = self.unknown.keys().cloned().collect();
+        for (name, patch) in &sections {
+            unknown_keys(patch, &applied[name], name, &mut unknown);
+        }
+        if !unknown.is_empty() {
+            let problems = unknown
+                .into_iter()
+                .map(|key| format!("unknown field {}", key))
+                .collect();
+            return Err(InvalidConfig(problems).into());
+        }
+
+        Ok(patched)
+    }
+}
+
+/// Collect the keys of `patch` missing from `applied`, as dotted paths under `prefix`
+fn unknown_keys(
+    patch: &serde_json::Map<String, serde_json::Value>,
+    applied: &serde_json::Value,
+    prefix: &str,
+    unknown: &mut Vec<String>,
+) {
+    for (key, value) in patch {
+        let path = format!("{}.{}", prefix, key);
+        match (applied.get(key), value) {
+            (None, _) => unknown.push(path),
+            (Some(applied), serde_json::Value::Object(nested)) => {
+                unknown_keys(nested, applied, &path, unknown)
+            }
+            (Some(_), _) => {}
+        }
+    }
+}
+
+/// Merge `patch` into `target`, recursing into objects present in both
+fn merge_json(target: &mut serde_json::Value, patch: serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge_json(target.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// Settings given on the command line, which win over the config file
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// `--host`
+    pub host: Option<String>,
+    /// `--port`
+    pub port: Option<u16>,
+}
+
+impl ConfigOverrides {
+    /// Apply every set override to `config`
+    pub fn apply(&self, config: &mut AppConfig) {
+        if let Some(host) = &self.host {
+            config.server.host = host.clone();
+        }
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+    }
 }
 
 /// Shared application state that holds runtime configuration
 #[derive(Debug, Clone)]
 pub struct SharedConfig {
     inner: Arc<RwLock<AppConfig>>,
+    /// Command-line settings re-applied over every reload
+    overrides: ConfigOverrides,
+    /// File the configuration was loaded from, for reloads
+    source_path: Option<Arc<str>>,
+    /// Profile applied on top of the file, kept across reloads
+    profile: Option<Arc<str>>,
 }
 
 impl SharedConfig {
@@ -156,7 +1309,54 @@ impl SharedConfig {
     pub fn new(config: AppConfig) -> Self {
         Self {
             inner: Arc::new(RwLock::new(config)),
+            overrides: ConfigOverrides::default(),
+            source_path: None,
+            profile: None,
+        }
+    }
+
+    /// Remember the file the configuration was loaded from, enabling `reload`
+    pub fn with_source_path(mut self, path: &str) -> Self {
+        self.source_path = Some(path.into());
+        self
+    }
+
+    /// File the configuration was loaded from, if any
+    pub fn source_path(&self) -> Option<&str> {
+        self.source_path.as_deref()
+    }
+
+    /// Remember the profile the configuration was loaded with, so reloads apply it too
+    pub fn with_profile(mut self, name: &str) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Remember the command-line overrides the configuration was loaded with,
+    /// so reloads apply them too
+    pub fn with_overrides(mut self, overrides: ConfigOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Re-read the configuration file and replace the current configuration
+    ///
+    /// Returns the changed settings that only take effect after a restart.
+    pub fn reload(&self) -> anyhow::Result<Vec<&'static str>> {
+        let path = self
+            .source_path()
+            .ok_or_else(|| anyhow::anyhow!("Configuration was not loaded from a file"))?;
+        let mut config = match &self.profile {
+            Some(profile) => AppConfig::load_profile(path, profile),
+            None => AppConfig::load_from_file(path),
         }
+        .map_err(|e| anyhow::anyhow!("Failed to reload {}: {}", path, e))?;
+        self.overrides.apply(&mut config);
+
+        let mut current = self.inner.write();
+        let restart_required = current.restart_required_changes(&config);
+        *current = config;
+        Ok(restart_required)
     }
 
     /// Get a read-only copy of the configuration
@@ -178,12 +1378,127 @@ impl SharedConfig {
     pub fn update(&self, config: AppConfig) {
         *self.inner.write() = config;
     }
+
+    /// Merge a partial configuration into the current one, returning the result
+    ///
+    /// The merge happens under the write lock, so concurrent patches to
+    /// different fields do not overwrite each other. A merged config that
+    /// fails `AppConfig::validate` is rejected with `InvalidConfig`.
+    pub fn patch(&self, partial: PartialAppConfig) -> anyhow::Result<AppConfig> {
+        let mut config = self.inner.write();
+        let patched = partial.apply_to(&config)?;
+        patched.validate().map_err(InvalidConfig)?;
+        *config = patched.clone();
+        Ok(patched)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_error_names_every_format() {
+        let err = AppConfig::parse_as::<AppConfig>("config.conf", "not [a config").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("config.conf"));
+        for format in ["TOML:", "JSON:", "YAML:"] {
+            assert!(
+                message.contains(format),
+                "{} missing from {}",
+                format,
+                message
+            );
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_parse_yaml() {
+        let yaml = "server:\n  port: 4000\nproxy:\n  enabled: true\n";
+        let config = AppConfig::parse_as::<AppConfig>("config.yml", yaml).unwrap();
+        assert_eq!(config.server.port, 4000);
+        assert!(config.proxy.enabled);
+    }
+
+    #[test]
+    fn test_reload_reports_restart_required() {
+        let path = std::env::temp_dir().join(format!("api-check-{}.toml", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        std::fs::write(
+            path,
+            "[server]\nport = 4000\n[proxy]\nenabled = true\nmax_concurrent_upstream = 8\n",
+        )
+        .unwrap();
+
+        let shared = SharedConfig::new(AppConfig::default()).with_source_path(path);
+        assert_eq!(
+            shared.reload().unwrap(),
+            vec!["server.port", "proxy.max_concurrent_upstream"]
+        );
+        assert!(shared.get().proxy.enabled);
+
+        std::fs::write(path, "[server\n").unwrap();
+        assert!(shared.reload().is_err());
+        assert!(shared.get().proxy.enabled);
+        std::fs::remove_file(path).unwrap();
+
+        assert!(SharedConfig::new(AppConfig::default()).reload().is_err());
+    }
+
+    #[test]
+    fn test_reload_keeps_cli_overrides() {
+        let path = std::env::temp_dir().join(format!("api-check-{}.toml", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "[server]\nhost = \"0.0.0.0\"\nport = 4000\n").unwrap();
+
+        let overrides = ConfigOverrides {
+            host: None,
+            port: Some(9000),
+        };
+        let mut config = AppConfig::load_from_file(path).unwrap();
+        overrides.apply(&mut config);
+        let shared = SharedConfig::new(config)
+            .with_source_path(path)
+            .with_overrides(overrides);
+
+        assert!(shared.reload().unwrap().is_empty());
+        assert_eq!(shared.get().server.port, 9000);
+        assert_eq!(shared.get().server.host, "0.0.0.0");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_profile_merges_over_base() {
+        let path = std::env::temp_dir().join(format!("api-check-{}.toml", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        std::fs::write(
+            path,
+            "[server]\nport = 4000\n[test]\nnum_calls = 5\nfrequency_ms = 50\n\n\
+             [profiles.staging.test]\nnum_calls = 100\n\
+             [profiles.prod.server]\nport = 8000\n",
+        )
+        .unwrap();
+
+        let staging = AppConfig::load_profile(path, "staging").unwrap();
+        assert_eq!(staging.server.port, 4000);
+        assert_eq!(staging.test.num_calls, 100);
+        assert_eq!(staging.test.frequency_ms, 50);
+        assert_eq!(
+            AppConfig::load_profile(path, "prod").unwrap().server.port,
+            8000
+        );
+        // Without a profile the section is ignored
+        assert_eq!(AppConfig::load_from_file(path).unwrap().test.num_calls, 5);
+
+        let err = AppConfig::load_profile(path, "dev")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unknown profile 'dev'"), "{}", err);
+        assert!(err.contains("prod") && err.contains("staging"), "{}", err);
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn test_default_config() {
         let config = AppConfig::default();
@@ -193,6 +1508,102 @@ mod tests {
         assert_eq!(config.test.num_calls, 10);
     }
 
+    #[test]
+    fn test_validate_reports_every_problem() {
+        assert_eq!(AppConfig::default().validate(), Ok(()));
+
+        let mut config = AppConfig::default();
+        config.server.port = 0;
+        config.proxy.enabled = true;
+        config.proxy.target = Some("localhost:8080".to_string());
+        config.proxy.request_headers_remove = vec!["bad header".to_string()];
+        config.test.method = "GE T".to_string();
+        config.test.num_calls = 0;
+        config.metrics.max_entries = 0;
+        config.metrics.evict_fraction = 1.5;
+        let problems = config.validate().unwrap_err();
+        assert_eq!(problems.len(), 7, "{:?}", problems);
+        assert!(problems[0].contains("bad header"));
+        assert_eq!(problems[1], "server.port must be between 1 and 65535");
+        assert!(problems[2].starts_with("proxy.target \"localhost:8080\""));
+
+        // Extra listen addresses replace host/port
+        config.server.listen = vec!["127.0.0.1:0".to_string()];
+        assert_eq!(config.validate().unwrap_err().len(), 6);
+    }
+
+    #[test]
+    fn test_patch_merges_nested_fields() {
+        let mut config = AppConfig::default();
+        config.proxy.target = Some("http://backend:8080".to_string());
+        let shared = SharedConfig::new(config);
+        let partial: PartialAppConfig = serde_json::from_value(serde_json::json!({
+            "proxy": {"enabled": true},
+            "test": {"num_calls": 50, "auth": {"type": "bearer", "token": "t"}}
+        }))
+        .unwrap();
+
+        let patched = shared.patch(partial).unwrap();
+        assert!(patched.proxy.enabled);
+        assert_eq!(patched.proxy.target.as_deref(), Some("http://backend:8080"));
+        assert_eq!(patched.test.num_calls, 50);
+        assert_eq!(patched.test.frequency_ms, 100);
+        assert_eq!(shared.get().test.num_calls, 50);
+
+        let invalid: PartialAppConfig =
+            serde_json::from_value(serde_json::json!({"test": {"num_calls": "many"}})).unwrap();
+        assert!(shared.patch(invalid).is_err());
+        assert_eq!(shared.get().test.num_calls, 50);
+
+        // Misspelled keys are reported rather than dropped
+        let misspelled: PartialAppConfig = serde_json::from_value(serde_json::json!({
+            "test": {"num_call": 5, "auth": {"type": "bearer", "tokn": "t"}},
+            "tset": {}
+        }))
+        .unwrap();
+        let err = shared.patch(misspelled).unwrap_err();
+        let InvalidConfig(problems) = err.downcast::<InvalidConfig>().unwrap();
+        assert_eq!(
+            problems,
+            [
+                "unknown field tset",
+                "unknown field test.auth.tokn",
+                "unknown field test.num_call"
+            ]
+        );
+        assert_eq!(shared.get().test.num_calls, 50);
+    }
+
+    #[test]
+    fn test_listen_addrs() {
+        let mut server = ServerConfig::default();
+        assert_eq!(server.listen_addrs(), vec!["127.0.0.1:3000"]);
+
+        server.host = "::1".to_string();
+        assert_eq!(server.listen_addrs(), vec!["[::1]:3000"]);
+
+        server.listen = vec!["0.0.0.0:8080".to_string(), "[::]:8080".to_string()];
+        assert_eq!(server.listen_addrs(), server.listen);
+    }
+
+    #[test]
+    fn test_parse_dns_overrides() {
+        let overrides = vec![
+            (
+                "API.example.com".to_string(),
+                "10.0.0.1, 10.0.0.2".to_string(),
+            ),
+            ("api.example.com".to_string(), "::1".to_string()),
+        ];
+        let resolved = parse_dns_overrides(&overrides).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].0, "api.example.com");
+        assert_eq!(resolved[0].1.len(), 3);
+
+        let invalid = vec![("api.example.com".to_string(), "not-an-ip".to_string())];
+        assert!(parse_dns_overrides(&invalid).is_err());
+    }
+
     #[test]
     fn test_shared_config() {
         let config = AppConfig::default();
@@ -201,6 +1612,7 @@ mod tests {
         let proxy = ProxyConfig {
             enabled: true,
             target: Some("http://example.com".to_string()),
+            ..Default::default()
         };
         shared.update_proxy(proxy.clone());
 
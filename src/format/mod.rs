@@ -0,0 +1,95 @@
+//! Output formatting helpers
+//!
+//! Shared by the CLI and TUI so durations render consistently.
+
+/// Default number of decimal places for formatted durations
+pub const DEFAULT_PRECISION: usize = 2;
+
+/// Format a duration given in milliseconds with an appropriate unit
+///
+/// Uses µs below one millisecond, ms below one second and s above.
+pub fn format_duration_ms(ms: f64) -> String {
+    format_duration_ms_with_precision(ms, DEFAULT_PRECISION)
+}
+
+/// Format a duration given in milliseconds with a fixed number of decimals
+pub fn format_duration_ms_with_precision(ms: f64, precision: usize) -> String {
+    if !ms.is_finite() {
+        return format!("{} ms", ms);
+    }
+
+    let abs = ms.abs();
+    if abs > 0.0 && abs < 1.0 {
+        format!("{:.*} µs", precision, ms * 1000.0)
+    } else if abs < 1000.0 {
+        format!("{:.*} ms", precision, ms)
+    } else {
+        format!("{:.*} s", precision, ms / 1000.0)
+    }
+}
+
+/// Render values as a compact ASCII sparkline using block characters
+pub fn render_sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = values.iter().cloned().fold(f64::MAX, f64::min);
+    let max = values.iter().cloned().fold(f64::MIN, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|v| {
+            if range <= 0.0 || !range.is_finite() {
+                BARS[0]
+            } else {
+                let level = ((v - min) / range * (BARS.len() - 1) as f64).round() as usize;
+                BARS[level.min(BARS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// Render named segments as a single stacked bar of `width` characters
+///
+/// Each segment uses its own fill character; returns the bar and a legend.
+pub fn render_stacked_bar(segments: &[(String, f64)], width: usize) -> (String, String) {
+    const FILLS: [char; 4] = ['█', '▓', '▒', '░'];
+
+    let total: f64 = segments.iter().map(|(_, v)| v.max(0.0)).sum();
+    if total <= 0.0 || !total.is_finite() {
+        return (String::new(), String::new());
+    }
+
+    let mut bar = String::new();
+    let mut legend = Vec::new();
+    for (i, (name, value)) in segments.iter().enumerate() {
+        let fill = FILLS[i % FILLS.len()];
+        let share = value.max(0.0) / total;
+        let cells = (share * width as f64).round() as usize;
+        bar.extend(std::iter::repeat_n(fill, cells));
+        legend.push(format!("{} {} {:.0}%", fill, name, share * 100.0));
+    }
+
+    (bar, legend.join("  "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration_units() {
+        assert_eq!(format_duration_ms(0.0), "0.00 ms");
+        assert_eq!(format_duration_ms(0.25), "250.00 µs");
+        assert_eq!(format_duration_ms(12.345), "12.35 ms");
+        assert_eq!(format_duration_ms(2500.0), "2.50 s");
+        assert_eq!(format_duration_ms_with_precision(1.5, 0), "2 ms");
+    }
+
+    #[test]
+    fn test_render_sparkline() {
+        assert_eq!(render_sparkline(&[1.0, 2.0, 3.0]), "▁▅█");
+        assert_eq!(render_sparkline(&[5.0, 5.0]), "▁▁");
+        assert_eq!(render_sparkline(&[]), "");
+    }
+}
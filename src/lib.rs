@@ -9,9 +9,11 @@
 
 pub mod api;
 pub mod config;
+pub mod format;
 pub mod metrics;
 pub mod proxy;
 pub mod server;
+pub mod telemetry;
 pub mod testing;
 pub mod tui;
 
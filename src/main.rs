@@ -3,14 +3,30 @@
 //! A Rust application for HTTP request monitoring, proxy support, and API testing.
 
 use api_check::{
-    config::{AppConfig, SharedConfig},
-    metrics::create_shared_metrics,
-    server::start_server,
-    testing::create_shared_tester,
+    config::{AppConfig, BodyType, ConfigOverrides, SharedConfig},
+    format::{
+        format_duration_ms_with_precision, render_sparkline, render_stacked_bar, DEFAULT_PRECISION,
+    },
+    metrics::{create_shared_metrics_from_config, id::set_id_format},
+    server::{lifecycle::Lifecycle, start_server, start_server_with_lifecycle},
+    telemetry,
+    testing::{
+        assertions::Thresholds,
+        create_shared_tester,
+        har::{HarFile, HarReplayer},
+        junit,
+        openapi::{OpenApiRunner, OpenApiSpec},
+        scenario::{Scenario, ScenarioRunner},
+        snapshot::{load_requests, GoldenFile, SnapshotRunner},
+        TestRunSummary,
+    },
     tui::TuiApp,
 };
-use clap::{Parser, Subcommand};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::sync::Arc;
+use tracing_subscriber::{
+    fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+};
 
 /// API Check - HTTP Server with Metrics Collection and API Testing
 #[derive(Parser)]
@@ -25,6 +41,10 @@ struct Cli {
     #[arg(short, long, default_value = "config.toml")]
     config: String,
 
+    /// Apply the `[profiles.<name>]` section of the config file over the base settings
+    #[arg(long, env = "API_CHECK_PROFILE")]
+    profile: Option<String>,
+
     /// Server host
     #[arg(long, env = "API_CHECK_SERVER_HOST")]
     host: Option<String>,
@@ -36,8 +56,23 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
+
+    /// OTLP/HTTP endpoint to export traces to (requires the `otel` feature)
+    #[arg(long, env = "API_CHECK_OTEL_ENDPOINT")]
+    otel_endpoint: Option<String>,
 }
 
+/// How the test command prints its results
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable summary
+    Text,
+    /// The full `TestRunSummary`, including per-request results
+    Json,
+}
+
+// Parsed once at startup, so the size of the `Test` variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Start the HTTP server
@@ -49,57 +84,277 @@ enum Commands {
         /// Target URL to test
         #[arg(short, long)]
         target: Option<String>,
-        /// Number of requests
-        #[arg(short, long, default_value = "10")]
-        num_calls: u32,
-        /// Frequency in milliseconds
+        /// Number of requests (defaults to 10, or unlimited with --duration)
+        #[arg(short, long)]
+        num_calls: Option<u32>,
+        /// Run for this many seconds (stops early if --num-calls is reached)
+        #[arg(short, long)]
+        duration: Option<u64>,
+        /// Interval in milliseconds between request starts
         #[arg(short, long, default_value = "100")]
         frequency: u64,
         /// HTTP method
         #[arg(short, long, default_value = "GET")]
         method: String,
+        /// Maximum number of requests in flight at once (defaults to the config's, or 1)
+        #[arg(long)]
+        concurrency: Option<u32>,
+        /// Decimal places for latency output
+        #[arg(long, default_value_t = DEFAULT_PRECISION)]
+        precision: usize,
+        /// Print latency by request index as an ASCII trend
+        #[arg(long)]
+        show_trend: bool,
+        /// Print where request time went, per phase
+        #[arg(long)]
+        phases: bool,
+        /// Pause when the target answers 429/503 with Retry-After
+        #[arg(long)]
+        honor_retry_after: bool,
+        /// Send If-None-Match/If-Modified-Since from earlier responses
+        #[arg(long)]
+        conditional: bool,
+        /// Open --concurrency connections before the timed run
+        #[arg(long)]
+        prime_connections: bool,
+        /// Keep cookies from responses and send them on later requests (session logins)
+        #[arg(long)]
+        enable_cookies: bool,
+        /// Count a request as successful only with this status code
+        #[arg(long)]
+        expect_status: Option<u16>,
+        /// Retry connection errors and 502/503/504 responses up to this many times
+        #[arg(long)]
+        retries: Option<u32>,
+        /// Count a request as successful only if its body contains this text
+        #[arg(long)]
+        expect_body_contains: Option<String>,
+        /// Record 3xx responses instead of following redirects
+        #[arg(long)]
+        no_follow_redirects: bool,
+        /// Fail a request that takes longer than this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+        /// Write the run summary as JSON to this file (or a timestamped file in this directory)
+        #[arg(long, value_name = "PATH")]
+        save_results: Option<String>,
+        /// Start at most this many requests per second, whatever the concurrency
+        #[arg(long, value_name = "RPS")]
+        max_rps: Option<u32>,
+        /// Send a multipart/form-data body uploading this file (repeatable)
+        #[arg(long, value_name = "FIELD=PATH")]
+        upload: Vec<String>,
+        /// Print the summary as text, or the full run as JSON (exits non-zero if any request failed)
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+        /// Also write a JUnit XML report (one test case per request) to this file
+        #[arg(long, value_name = "PATH")]
+        junit: Option<String>,
+        /// Print the resolved test plan and exit without sending any requests
+        #[arg(long)]
+        dry_run: bool,
+        /// Fail if the 95th percentile latency exceeds this many milliseconds
+        #[arg(long, value_name = "MS")]
+        assert_p95: Option<f64>,
+        /// Fail if the 99th percentile latency exceeds this many milliseconds
+        #[arg(long, value_name = "MS")]
+        assert_p99: Option<f64>,
+        /// Fail if fewer than this percentage of requests succeed
+        #[arg(long, value_name = "PCT")]
+        assert_success_rate: Option<f64>,
+        /// Fail if any request takes longer than this many milliseconds
+        #[arg(long, value_name = "MS")]
+        assert_max_latency: Option<f64>,
+    },
+    /// Record responses for a list of requests into a golden file
+    Snapshot {
+        /// JSON file containing the list of requests to record
+        requests: String,
+        /// Golden file to write
+        #[arg(short, long, default_value = "golden.json")]
+        output: String,
+        /// JSON paths to ignore when verifying (e.g. $.body.updated_at)
+        #[arg(short, long)]
+        ignore: Vec<String>,
+    },
+    /// Re-run a golden file and report responses that differ
+    Verify {
+        /// Golden file to verify against
+        #[arg(default_value = "golden.json")]
+        golden: String,
+        /// Additional JSON paths to ignore
+        #[arg(short, long)]
+        ignore: Vec<String>,
+    },
+    /// Replay the requests recorded in a HAR (HTTP Archive) file
+    ReplayHar {
+        /// HAR file to replay
+        file: String,
+        /// Send requests to this base URL (e.g. http://staging:8080) instead of the recorded host
+        #[arg(long)]
+        rewrite_host: Option<String>,
+        /// Wait between requests as long as the recording did
+        #[arg(long)]
+        preserve_timing: bool,
+        /// Decimal places for latency output
+        #[arg(long, default_value_t = DEFAULT_PRECISION)]
+        precision: usize,
+    },
+    /// Call every operation of an OpenAPI spec once, using the spec's examples
+    Openapi {
+        /// OpenAPI spec file (JSON, or YAML with the `yaml` feature)
+        spec: String,
+        /// Base URL the spec's paths are relative to (e.g. http://localhost:8080)
+        #[arg(long)]
+        base_url: String,
+        /// Decimal places for latency output
+        #[arg(long, default_value_t = DEFAULT_PRECISION)]
+        precision: usize,
+    },
+    /// Run a multi-step scenario, chaining values between requests
+    Scenario {
+        /// JSON file describing the scenario steps
+        file: String,
+
+        /// Resolve relative step URLs against this base URL (overrides the file's base_url)
+        #[arg(long)]
+        base_url: Option<String>,
     },
     /// Show current configuration
-    Config,
+    Config {
+        /// Check the configuration instead, exiting non-zero if it has problems
+        #[arg(long)]
+        validate: bool,
+    },
+}
+
+/// Print the headline numbers of a test run
+fn print_summary(summary: &TestRunSummary, precision: usize) {
+    let fmt = |ms| format_duration_ms_with_precision(ms, precision);
+
+    println!("\n=== Test Results ===");
+    println!("Total requests: {}", summary.total_requests);
+    println!("Successful: {}", summary.successful);
+    println!("Failed: {}", summary.failed);
+    println!("Average latency: {}", fmt(summary.avg_latency_ms));
+    println!("Min latency: {}", fmt(summary.min_latency_ms));
+    println!("Max latency: {}", fmt(summary.max_latency_ms));
+    println!("Total duration: {}", fmt(summary.total_duration_ms));
+    if summary.not_modified_count > 0 {
+        println!("Not modified (304): {}", summary.not_modified_count);
+    }
+    if summary.connections_primed > 0 {
+        println!("Connections primed: {}", summary.connections_primed);
+    }
+    if summary.retries > 0 {
+        println!("Retries: {}", summary.retries);
+    }
+    if summary.backoff_ms > 0.0 {
+        println!("Retry-After backoff: {}", fmt(summary.backoff_ms));
+    }
+    if let Some(met) = summary.met_slo {
+        println!("SLO: {}", if met { "met" } else { "missed" });
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    // Initialize logging
+    // Load configuration
+    let config_result = if let Some(profile) = &cli.profile {
+        AppConfig::load_profile(&cli.config, profile)
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", cli.config, e))
+    } else if std::path::Path::new(&cli.config).exists() {
+        AppConfig::load_from_file(&cli.config)
+    } else {
+        AppConfig::load()
+    };
+
+    // Initialize logging, exporting spans over OTLP when an endpoint is configured
+    let otel_endpoint = cli.otel_endpoint.clone().or_else(|| {
+        config_result
+            .as_ref()
+            .ok()
+            .and_then(|c| c.server.otlp_endpoint.clone())
+    });
+    let otel_layer = match &otel_endpoint {
+        Some(endpoint) => telemetry::otel_layer(endpoint)?,
+        None => None,
+    };
+
+    // Keep stdout clean for JSON test output
+    let json_output = matches!(
+        cli.command,
+        Some(Commands::Test {
+            output: OutputFormat::Json,
+            ..
+        })
+    );
+    let log_writer = if json_output {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
     let log_level = if cli.verbose { "debug" } else { "info" };
+    // Exported traces also get the per-request spans from tower-http's TraceLayer
+    let default_filter = if otel_endpoint.is_some() {
+        format!("api_check={},tower_http=info", log_level)
+    } else {
+        format!("api_check={}", log_level)
+    };
     tracing_subscriber::registry()
+        .with(otel_layer)
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("api_check={}", log_level).into()),
+                .unwrap_or_else(|_| default_filter.into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(log_writer))
         .init();
 
-    // Load configuration
-    let mut config = if std::path::Path::new(&cli.config).exists() {
-        AppConfig::load_from_file(&cli.config).unwrap_or_else(|e| {
-            tracing::warn!(error = %e, "Failed to load config file, using defaults");
-            AppConfig::default()
-        })
-    } else {
-        AppConfig::load().unwrap_or_else(|e| {
+    if otel_endpoint.is_some() && !cfg!(feature = "otel") {
+        tracing::warn!(
+            "OTLP endpoint configured but api-check was built without the `otel` feature"
+        );
+    }
+
+    let mut config = match config_result {
+        Ok(config) => config,
+        // Falling back to defaults could point a run at the wrong environment
+        Err(e) if cli.profile.is_some() => return Err(e),
+        Err(e) => {
             tracing::warn!(error = %e, "Failed to load config, using defaults");
             AppConfig::default()
-        })
+        }
     };
 
-    // Override with CLI args
-    if let Some(host) = cli.host {
-        config.server.host = host;
-    }
-    if let Some(port) = cli.port {
-        config.server.port = port;
+    // Override with CLI args, here and on every reload
+    let overrides = ConfigOverrides {
+        host: cli.host,
+        port: cli.port,
+    };
+    overrides.apply(&mut config);
+
+    // Surface bad values now rather than deep in the request path
+    if !matches!(cli.command, Some(Commands::Config { validate: true })) {
+        if let Err(problems) = config.validate() {
+            for problem in problems {
+                tracing::warn!(problem = %problem, "Invalid configuration");
+            }
+        }
     }
 
-    let shared_config = SharedConfig::new(config.clone());
-    let metrics = create_shared_metrics(10000);
+    let mut shared_config = SharedConfig::new(config.clone()).with_overrides(overrides);
+    if std::path::Path::new(&cli.config).exists() {
+        shared_config = shared_config.with_source_path(&cli.config);
+    }
+    if let Some(profile) = &cli.profile {
+        tracing::info!(profile = %profile, "Using config profile");
+        shared_config = shared_config.with_profile(profile);
+    }
+    set_id_format(config.metrics.id_format);
+    let metrics = create_shared_metrics_from_config(&config.metrics);
     let tester = create_shared_tester(shared_config.clone(), metrics.clone());
 
     match cli.command {
@@ -117,10 +372,19 @@ async fn main() -> anyhow::Result<()> {
             let server_config = shared_config.clone();
             let server_metrics = metrics.clone();
             let server_tester = tester.clone();
+            let lifecycle = Arc::new(Lifecycle::new());
+            let server_lifecycle = lifecycle.clone();
 
             // Start server in background
-            tokio::spawn(async move {
-                if let Err(e) = start_server(server_config, server_metrics, server_tester).await {
+            let server = tokio::spawn(async move {
+                if let Err(e) = start_server_with_lifecycle(
+                    server_config,
+                    server_metrics,
+                    server_tester,
+                    server_lifecycle,
+                )
+                .await
+                {
                     tracing::error!(error = %e, "Server error");
                 }
             });
@@ -130,46 +394,331 @@ async fn main() -> anyhow::Result<()> {
 
             // Run TUI
             let mut app = TuiApp::new(shared_config, metrics, tester);
-            app.run().await?;
+            let result = app.run().await;
+
+            // Let in-flight requests finish before exiting
+            lifecycle.shutdown();
+            let _ = server.await;
+            result?;
         }
         Some(Commands::Test {
             target,
             num_calls,
+            duration,
             frequency,
             method,
+            concurrency,
+            precision,
+            show_trend,
+            phases,
+            honor_retry_after,
+            conditional,
+            prime_connections,
+            enable_cookies,
+            expect_status,
+            retries,
+            expect_body_contains,
+            no_follow_redirects,
+            timeout,
+            save_results,
+            max_rps,
+            upload,
+            output,
+            junit: junit_path,
+            dry_run,
+            assert_p95,
+            assert_p99,
+            assert_success_rate,
+            assert_max_latency,
         }) => {
             // Run API tests
             let mut test_config = config.test;
-            test_config.num_calls = num_calls;
+            test_config.num_calls = num_calls.unwrap_or(if duration.is_some() { 0 } else { 10 });
+            test_config.duration_secs = duration;
             test_config.frequency_ms = frequency;
             test_config.method = method;
+            if let Some(concurrency) = concurrency {
+                test_config.concurrency = concurrency;
+            }
+            test_config.honor_retry_after |= honor_retry_after;
+            test_config.conditional |= conditional;
+            test_config.prime_connections |= prime_connections;
+            test_config.enable_cookies |= enable_cookies;
+            if expect_status.is_some() {
+                test_config.expect_status = expect_status;
+            }
+            if expect_body_contains.is_some() {
+                test_config.expect_body_contains = expect_body_contains;
+            }
+            if let Some(retries) = retries {
+                test_config.retries = retries;
+            }
+            if no_follow_redirects {
+                test_config.follow_redirects = false;
+            }
+            if let Some(timeout) = timeout {
+                test_config.timeout_secs = timeout;
+            }
+            if save_results.is_some() {
+                test_config.save_results_path = save_results;
+            }
+            if max_rps.is_some() {
+                test_config.max_rps = max_rps;
+            }
+            if !upload.is_empty() {
+                let files = upload
+                    .iter()
+                    .map(|upload| match upload.split_once('=') {
+                        Some((field, path)) => Ok((field.to_string(), path.to_string())),
+                        None => anyhow::bail!("--upload expects FIELD=PATH, got {}", upload),
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                test_config.body_type = BodyType::Multipart { files };
+            }
             test_config.target_url = target;
 
+            if dry_run {
+                print!("{}", tester.describe_plan(&test_config));
+                return Ok(());
+            }
+
             shared_config.update_test(test_config.clone());
 
             tracing::info!(
                 target = %test_config.target_url.as_deref().unwrap_or("(default)"),
-                num_calls = %num_calls,
+                num_calls = %test_config.num_calls,
+                duration_secs = ?test_config.duration_secs,
                 frequency = %frequency,
                 "Running API tests"
             );
 
+            let suite = test_config
+                .target_url
+                .clone()
+                .unwrap_or_else(|| "api-check".to_string());
             let summary = tester.run_with_config(test_config).await?;
+            if let Some(path) = &junit_path {
+                junit::write(&summary, &suite, path)?;
+                tracing::info!(path = %path, "Wrote JUnit report");
+            }
+
+            let text = output == OutputFormat::Text;
+            if text {
+                print_summary(&summary, precision);
+            } else {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            }
+            let fmt = |ms| format_duration_ms_with_precision(ms, precision);
+
+            if show_trend && text {
+                if let (Some(first), Some(last)) = (
+                    summary.latency_over_index.first(),
+                    summary.latency_over_index.last(),
+                ) {
+                    println!("\n=== Latency Trend (by request index) ===");
+                    println!("{}", render_sparkline(&summary.latency_over_index));
+                    println!("First: {}  Last: {}", fmt(*first), fmt(*last));
+                }
+            }
+
+            if phases && text {
+                let breakdown = summary.phase_breakdown();
+                let segments: Vec<(String, f64)> = breakdown
+                    .iter()
+                    .map(|p| (p.phase.clone(), p.avg_ms))
+                    .collect();
+                let (bar, legend) = render_stacked_bar(&segments, 50);
+
+                println!("\n=== Phase Breakdown (average per request) ===");
+                println!("{}", bar);
+                println!("{}", legend);
+                for phase in &breakdown {
+                    println!("{:>10}: {}", phase.phase, fmt(phase.avg_ms));
+                }
+            }
+
+            let thresholds = Thresholds {
+                p95_ms: assert_p95,
+                p99_ms: assert_p99,
+                success_rate_pct: assert_success_rate,
+                max_latency_ms: assert_max_latency,
+            };
+            if !thresholds.is_empty() {
+                let failures = thresholds.evaluate(&summary);
+                if failures.is_empty() {
+                    if text {
+                        println!("\nAll assertions passed");
+                    }
+                } else {
+                    // Failures go to stderr in JSON mode, keeping stdout parseable
+                    if text {
+                        println!("\n=== Failed Assertions ===");
+                    }
+                    for failure in &failures {
+                        if text {
+                            println!("{}", failure);
+                        } else {
+                            eprintln!("{}", failure);
+                        }
+                    }
+                    anyhow::bail!("{} assertion(s) failed", failures.len());
+                }
+            }
+
+            if summary.met_slo == Some(false) {
+                anyhow::bail!(
+                    "Run missed its SLO (p95 {}, error rate {:.2}%)",
+                    fmt(summary.p95_latency_ms),
+                    summary.error_rate() * 100.0
+                );
+            }
 
-            println!("\n=== Test Results ===");
-            println!("Total requests: {}", summary.total_requests);
-            println!("Successful: {}", summary.successful);
-            println!("Failed: {}", summary.failed);
-            println!("Average latency: {:.2} ms", summary.avg_latency_ms);
-            println!("Min latency: {:.2} ms", summary.min_latency_ms);
-            println!("Max latency: {:.2} ms", summary.max_latency_ms);
-            println!("Total duration: {:.2} ms", summary.total_duration_ms);
+            if !text && summary.failed > 0 {
+                anyhow::bail!(
+                    "{} of {} requests failed",
+                    summary.failed,
+                    summary.total_requests
+                );
+            }
         }
-        Some(Commands::Config) => {
+        Some(Commands::Snapshot {
+            requests,
+            output,
+            ignore,
+        }) => {
+            let requests = load_requests(&requests)?;
+            tracing::info!(count = %requests.len(), output = %output, "Recording snapshot");
+
+            let golden = SnapshotRunner::new().record(requests, ignore).await?;
+            golden.save(&output)?;
+
+            println!("Recorded {} responses to {}", golden.entries.len(), output);
+        }
+        Some(Commands::Verify { golden, ignore }) => {
+            let golden_file = GoldenFile::load(&golden)?;
+            let mismatches = SnapshotRunner::new().verify(&golden_file, &ignore).await?;
+
+            if mismatches.is_empty() {
+                println!(
+                    "All {} responses match {}",
+                    golden_file.entries.len(),
+                    golden
+                );
+            } else {
+                println!("\n=== Snapshot Mismatches ===");
+                for m in &mismatches {
+                    println!(
+                        "[{}] {} {}: expected {}, got {}",
+                        m.index, m.request, m.path, m.expected, m.actual
+                    );
+                }
+                anyhow::bail!("{} snapshot mismatches", mismatches.len());
+            }
+        }
+        Some(Commands::ReplayHar {
+            file,
+            rewrite_host,
+            preserve_timing,
+            precision,
+        }) => {
+            let har = HarFile::load(&file)?;
+            tracing::info!(entries = %har.log.entries.len(), file = %file, "Replaying HAR");
+
+            let summary = HarReplayer::new()
+                .with_rewrite_host(rewrite_host)
+                .with_preserve_timing(preserve_timing)
+                .replay(&har.log.entries)
+                .await?;
+            print_summary(&summary, precision);
+        }
+        Some(Commands::Openapi {
+            spec,
+            base_url,
+            precision,
+        }) => {
+            let summary = OpenApiRunner::new()
+                .run(&OpenApiSpec::load(&spec)?, &base_url)
+                .await?;
+
+            println!("\n=== Operations ===");
+            for result in &summary.results {
+                let outcome = if result.success { "PASS" } else { "FAIL" };
+                let status = match (result.status_code, &result.error) {
+                    (Some(status), _) => status.to_string(),
+                    (None, Some(error)) => error.clone(),
+                    (None, None) => "-".to_string(),
+                };
+                println!(
+                    "[{}] {} {} ({}, {})",
+                    result.index,
+                    outcome,
+                    result.operation.as_deref().unwrap_or_default(),
+                    status,
+                    format_duration_ms_with_precision(result.latency_ms, precision)
+                );
+            }
+            print_summary(&summary, precision);
+            if summary.failed > 0 {
+                anyhow::bail!("{} operations failed", summary.failed);
+            }
+        }
+        Some(Commands::Scenario { file, base_url }) => {
+            let mut scenario = Scenario::load(&file)?;
+            if base_url.is_some() {
+                scenario.base_url = base_url;
+            }
+            tracing::info!(steps = %scenario.steps.len(), file = %file, "Running scenario");
+
+            let summary = ScenarioRunner::new().run(&scenario).await?;
+
+            println!("\n=== Scenario Results ===");
+            for step in &summary.steps {
+                let outcome = if step.passed() { "PASS" } else { "FAIL" };
+                let status = step
+                    .status_code
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                println!(
+                    "[{}] {} {} ({}, {})",
+                    step.index,
+                    outcome,
+                    step.name,
+                    status,
+                    format_duration_ms_with_precision(step.latency_ms, DEFAULT_PRECISION)
+                );
+                if let Some(error) = &step.error {
+                    println!("    error: {}", error);
+                }
+                for failure in &step.assertion_failures {
+                    println!("    {}", failure);
+                }
+            }
+
+            let failed = summary.failed_steps();
+            if failed > 0 {
+                anyhow::bail!(
+                    "{} of {} scenario steps failed",
+                    failed,
+                    summary.steps.len()
+                );
+            }
+        }
+        Some(Commands::Config { validate: true }) => match config.validate() {
+            Ok(()) => println!("Configuration is valid"),
+            Err(problems) => {
+                for problem in &problems {
+                    println!("- {}", problem);
+                }
+                anyhow::bail!("{} configuration problems", problems.len());
+            }
+        },
+        Some(Commands::Config { validate: false }) => {
             // Show current configuration
             println!("{}", serde_json::to_string_pretty(&config)?);
         }
     }
 
+    telemetry::shutdown();
+
     Ok(())
 }
@@ -0,0 +1,282 @@
+//! Continuous CSV metrics stream
+//!
+//! Appends every recorded metric to `metrics.csv_stream_path` as it arrives.
+//! Rows are sent over a channel to a background writer thread so recording
+//! never blocks on file I/O. When `metrics.csv_max_bytes` is set, the stream
+//! rotates to numbered files (`metrics.1.csv`, `metrics.2.csv`, ...).
+//! `export_csv` renders stored metrics for one-off downloads.
+
+use super::RequestMetric;
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+/// CSV header row written at the top of each file
+pub const CSV_HEADER: &str =
+    "timestamp,id,method,path,status_code,latency_ms,proxied,upstream,client_cancelled";
+
+/// Message sent to the background writer
+#[derive(Debug)]
+enum Message {
+    /// Append a metric
+    Row(Box<RequestMetric>),
+    /// Acknowledge once everything queued before it is on disk
+    Flush(Sender<()>),
+}
+
+/// Handle feeding metrics to the background CSV writer
+#[derive(Debug)]
+pub struct CsvStream {
+    sender: Sender<Message>,
+}
+
+impl CsvStream {
+    /// Open the stream file and spawn the background writer
+    pub fn spawn(path: impl Into<PathBuf>, max_bytes: Option<u64>) -> Result<Self> {
+        let writer = RotatingWriter::open(path.into(), max_bytes)?;
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("metrics-csv".to_string())
+            .spawn(move || writer.run(receiver))
+            .context("Failed to spawn CSV writer thread")?;
+
+        Ok(Self { sender })
+    }
+
+    /// Queue a metric to be written
+    pub fn send(&self, metric: &RequestMetric) {
+        // The writer only stops after a write error, which it has already logged
+        let _ = self.sender.send(Message::Row(Box::new(metric.clone())));
+    }
+
+    /// Wait up to `timeout` for queued rows to be written and flushed
+    ///
+    /// Returns `false` if the writer did not confirm in time.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let (ack, done) = mpsc::channel();
+        self.sender.send(Message::Flush(ack)).is_ok() && done.recv_timeout(timeout).is_ok()
+    }
+}
+
+/// Format a metric as a CSV row (without trailing newline)
+pub fn csv_row(metric: &RequestMetric) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    // Writing to a Vec cannot fail
+    let _ = writer.write_record([
+        metric.timestamp.to_rfc3339().as_str(),
+        &metric.id,
+        &metric.method,
+        &metric.path,
+        &status(metric),
+        &format!("{:.3}", metric.latency_ms),
+        &metric.proxied.to_string(),
+        metric.upstream.as_deref().unwrap_or_default(),
+        &metric.client_cancelled.to_string(),
+    ]);
+    let mut row = into_string(writer);
+    row.pop();
+    row
+}
+
+/// CSV header row of `export_csv`
+pub const EXPORT_HEADER: &str = "id,timestamp,method,path,status_code,latency_ms,proxied";
+
+/// Render metrics as a CSV document with `EXPORT_HEADER` columns
+pub fn export_csv(metrics: &[RequestMetric]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::with_capacity((metrics.len() + 1) * 96));
+    // Writing to a Vec cannot fail
+    let _ = writer.write_record(EXPORT_HEADER.split(','));
+    for metric in metrics {
+        let _ = writer.write_record([
+            metric.id.as_str(),
+            &metric.timestamp.to_rfc3339(),
+            &metric.method,
+            &metric.path,
+            &status(metric),
+            &format!("{:.3}", metric.latency_ms),
+            &metric.proxied.to_string(),
+        ]);
+    }
+    into_string(writer)
+}
+
+/// Status code column (empty when the request never got a response)
+fn status(metric: &RequestMetric) -> String {
+    metric
+        .status_code
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+/// Take the buffered output of an in-memory CSV writer
+fn into_string(writer: csv::Writer<Vec<u8>>) -> String {
+    let bytes = writer.into_inner().unwrap_or_default();
+    // Every field written is a Rust string, so the output is valid UTF-8
+    String::from_utf8(bytes).unwrap_or_default()
+}
+
+/// Path of the `index`-th rotated file (`index` 0 is the configured path)
+fn rotated_path(base: &Path, index: u32) -> PathBuf {
+    if index == 0 {
+        return base.to_path_buf();
+    }
+
+    let stem = base
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match base.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, index),
+    };
+    base.with_file_name(name)
+}
+
+/// Size-rotating CSV file writer
+struct RotatingWriter {
+    base: PathBuf,
+    max_bytes: Option<u64>,
+    index: u32,
+    written: u64,
+    file: BufWriter<File>,
+}
+
+impl RotatingWriter {
+    fn open(base: PathBuf, max_bytes: Option<u64>) -> Result<Self> {
+        let (file, written) = open_file(&base)?;
+        Ok(Self {
+            base,
+            max_bytes,
+            index: 0,
+            written,
+            file,
+        })
+    }
+
+    fn run(mut self, receiver: Receiver<Message>) {
+        while let Ok(message) = receiver.recv() {
+            // Write everything already queued before flushing
+            let mut acks = Vec::new();
+            for message in std::iter::once(message).chain(receiver.try_iter()) {
+                let metric = match message {
+                    Message::Row(metric) => metric,
+                    Message::Flush(ack) => {
+                        acks.push(ack);
+                        continue;
+                    }
+                };
+                if let Err(e) = self.write_row(&csv_row(&metric)) {
+                    tracing::error!(error = %e, path = %self.base.display(), "CSV metrics stream stopped");
+                    return;
+                }
+            }
+
+            // Flush once the queue is drained so tailing readers see rows promptly
+            if let Err(e) = self.file.flush() {
+                tracing::warn!(error = %e, "Failed to flush CSV metrics stream");
+            }
+            for ack in acks {
+                let _ = ack.send(());
+            }
+        }
+    }
+
+    fn write_row(&mut self, row: &str) -> Result<()> {
+        let len = row.len() as u64 + 1;
+        if let Some(max_bytes) = self.max_bytes {
+            if self.written + len > max_bytes && self.written > CSV_HEADER.len() as u64 + 1 {
+                self.rotate()?;
+            }
+        }
+
+        writeln!(self.file, "{}", row)?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+        self.index += 1;
+
+        let path = rotated_path(&self.base, self.index);
+        let (file, written) = open_file(&path)?;
+        tracing::info!(path = %path.display(), "Rotated CSV metrics stream");
+
+        self.file = file;
+        self.written = written;
+        Ok(())
+    }
+}
+
+/// Open a file for appending, writing the header if it is empty
+fn open_file(path: &Path) -> Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open CSV stream {}", path.display()))?;
+
+    let mut written = file.metadata()?.len();
+    let mut file = BufWriter::new(file);
+    if written == 0 {
+        writeln!(file, "{}", CSV_HEADER)?;
+        file.flush()?;
+        written = CSV_HEADER.len() as u64 + 1;
+    }
+
+    Ok((file, written))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row_escaping() {
+        let metric = RequestMetric::new("GET".to_string(), "/a,\"b\"".to_string())
+            .with_status(200)
+            .with_latency(1.5);
+
+        let row = csv_row(&metric);
+        assert!(row.contains(",GET,\"/a,\"\"b\"\"\",200,1.500,false,,false"));
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let metric = RequestMetric::new("GET".to_string(), "/search?q=a,b".to_string())
+            .with_status(200)
+            .with_latency(2.0);
+
+        let csv = export_csv(std::slice::from_ref(&metric));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], EXPORT_HEADER);
+        assert!(lines[1].starts_with(&metric.id));
+        assert!(lines[1].ends_with(",GET,\"/search?q=a,b\",200,2.000,false"));
+    }
+
+    #[test]
+    fn test_rotation_creates_numbered_files() {
+        let dir = std::env::temp_dir().join(format!("api-check-csv-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("metrics.csv");
+
+        let mut writer = RotatingWriter::open(base.clone(), Some(300)).unwrap();
+        for _ in 0..6 {
+            let metric = RequestMetric::new("GET".to_string(), "/test".to_string());
+            writer.write_row(&csv_row(&metric)).unwrap();
+        }
+        writer.file.flush().unwrap();
+
+        let first = std::fs::read_to_string(&base).unwrap();
+        let second = std::fs::read_to_string(dir.join("metrics.1.csv")).unwrap();
+        assert!(first.starts_with(CSV_HEADER));
+        assert!(second.starts_with(CSV_HEADER));
+        assert!(first.len() <= 300);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! Metric ID generation
+//!
+//! The format is process-wide and set once at startup from `metrics.id_format`.
+
+use crate::config::IdFormat;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+static FORMAT: AtomicU8 = AtomicU8::new(IdFormat::Uuid4 as u8);
+static SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+/// Set the format used for new metric IDs
+pub fn set_id_format(format: IdFormat) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// Get the format used for new metric IDs
+pub fn id_format() -> IdFormat {
+    match FORMAT.load(Ordering::Relaxed) {
+        f if f == IdFormat::Uuid7 as u8 => IdFormat::Uuid7,
+        f if f == IdFormat::Ulid as u8 => IdFormat::Ulid,
+        f if f == IdFormat::Sequential as u8 => IdFormat::Sequential,
+        _ => IdFormat::Uuid4,
+    }
+}
+
+/// Continue sequential IDs from at least `next`, e.g. after persisted history
+pub fn seed_sequence(next: u64) {
+    SEQUENCE.fetch_max(next, Ordering::Relaxed);
+}
+
+/// Generate an ID in the given format
+pub fn generate_id(format: IdFormat) -> String {
+    match format {
+        IdFormat::Uuid4 => uuid::Uuid::new_v4().to_string(),
+        IdFormat::Uuid7 => uuid::Uuid::now_v7().to_string(),
+        IdFormat::Ulid => ulid::Ulid::new().to_string(),
+        IdFormat::Sequential => sequential(SEQUENCE.fetch_add(1, Ordering::Relaxed)),
+    }
+}
+
+/// Zero-padded so sequential IDs sort as strings in numeric order
+fn sequential(n: u64) -> String {
+    format!("{:020}", n)
+}
+
+/// Generate a metric ID in the configured format
+pub fn next_id() -> String {
+    generate_id(id_format())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_ordered_ids_sort_by_arrival() {
+        for format in [IdFormat::Uuid7, IdFormat::Ulid] {
+            let first = generate_id(format);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            let second = generate_id(format);
+            assert!(first < second, "{:?}: {} >= {}", format, first, second);
+        }
+
+        let first = generate_id(IdFormat::Sequential);
+        let second = generate_id(IdFormat::Sequential);
+        assert_eq!(first.len(), 20);
+        assert!(first < second, "{} >= {}", first, second);
+        assert!(sequential(9) < sequential(10));
+        assert_eq!(sequential(u64::MAX).len(), 20);
+    }
+}
@@ -0,0 +1,67 @@
+//! InfluxDB line protocol
+//!
+//! Renders recorded metrics as `api_check_request,<tags> <fields> <timestamp>`
+//! lines, tagged by method, status and proxied, with nanosecond timestamps.
+
+use super::RequestMetric;
+use std::fmt::Write;
+
+/// Measurement name used for every line
+pub const MEASUREMENT: &str = "api_check_request";
+
+/// Content type of the line protocol
+pub const CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Render metrics as line protocol, one line per metric
+pub fn render(metrics: &[RequestMetric]) -> String {
+    let mut out = String::new();
+    for metric in metrics {
+        let _ = write!(out, "{},method={}", MEASUREMENT, escape_tag(&metric.method));
+        if let Some(status) = metric.status_code {
+            let _ = write!(out, ",status={}", status);
+        }
+        let _ = write!(
+            out,
+            ",proxied={} latency_ms={}",
+            metric.proxied, metric.latency_ms
+        );
+        if let Some(bytes) = metric.bytes {
+            let _ = write!(out, ",bytes={}i", bytes);
+        }
+        let timestamp = metric.timestamp.timestamp_nanos_opt().unwrap_or_default();
+        let _ = writeln!(out, " {}", timestamp);
+    }
+    out
+}
+
+/// Escape commas, spaces and equals signs in a tag value
+fn escape_tag(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(' ', "\\ ")
+        .replace('=', "\\=")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_line_protocol() {
+        let mut metric = RequestMetric::new("GET".to_string(), "/".to_string())
+            .with_status(200)
+            .with_latency(1.5)
+            .with_bytes(128);
+        metric.timestamp = chrono::DateTime::from_timestamp(1_700_000_000, 5).unwrap();
+        let unanswered = RequestMetric::new("M,1".to_string(), "/".to_string());
+
+        let text = render(&[metric, unanswered]);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "api_check_request,method=GET,status=200,proxied=false latency_ms=1.5,bytes=128i 1700000000000000005"
+        );
+        assert!(lines[1].starts_with("api_check_request,method=M\\,1,proxied=false latency_ms=0 "));
+    }
+}
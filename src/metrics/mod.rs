@@ -2,11 +2,39 @@
 //!
 //! Collects and stores metrics about requests, latency, and status codes.
 
+pub mod csv_stream;
+pub mod id;
+pub mod influx;
+pub mod prometheus;
+pub mod sqlite;
+
+use crate::config::{EvictionMode, MetricsConfig, SloConfig};
+use crate::testing::har::HarFile;
 use chrono::{DateTime, Utc};
-use parking_lot::RwLock;
+use csv_stream::CsvStream;
+use parking_lot::{Mutex, RwLock};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sqlite::SqliteStore;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Where a metric was recorded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MetricSource {
+    /// A request received by the dev server
+    #[default]
+    #[serde(rename = "server")]
+    ServerInbound,
+    /// A request sent by the API tester
+    #[serde(rename = "test")]
+    TestClient,
+    /// A request forwarded by the proxy
+    #[serde(rename = "proxy")]
+    Proxy,
+}
 
 /// A single request metric entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,19 +53,67 @@ pub struct RequestMetric {
     pub timestamp: DateTime<Utc>,
     /// Whether this was a proxied request
     pub proxied: bool,
+    /// Upstream the request was forwarded to (proxied requests only)
+    #[serde(default)]
+    pub upstream: Option<String>,
+    /// Whether the client disconnected before a response was sent
+    #[serde(default)]
+    pub client_cancelled: bool,
+    /// Whether the proxy answered 503 itself because the upstream's circuit was open
+    #[serde(default)]
+    pub circuit_open: bool,
+    /// Number of identical consecutive requests this entry represents
+    #[serde(default = "default_repeat_count")]
+    pub repeat_count: u64,
+    /// Where the metric was recorded
+    #[serde(default)]
+    pub source: MetricSource,
+    /// Body bytes transferred in both directions (proxied requests only)
+    #[serde(default)]
+    pub bytes: Option<u64>,
+    /// Request body size in bytes, when measured or declared by `Content-Length`
+    #[serde(default)]
+    pub request_bytes: Option<u64>,
+    /// Response body size in bytes, when measured or declared by `Content-Length`
+    #[serde(default)]
+    pub response_bytes: Option<u64>,
+    /// Effective body throughput in bytes per second (proxied requests only)
+    #[serde(default)]
+    pub bytes_per_sec: Option<f64>,
+    /// Time spent waiting for an upstream concurrency permit, in milliseconds
+    #[serde(default)]
+    pub queue_wait_ms: Option<f64>,
+    /// Time spent on the upstream request itself, in milliseconds
+    #[serde(default)]
+    pub upstream_ms: Option<f64>,
+}
+
+fn default_repeat_count() -> u64 {
+    1
 }
 
 impl RequestMetric {
     /// Create a new request metric
     pub fn new(method: String, path: String) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: id::next_id(),
             method,
             path,
             status_code: None,
             latency_ms: 0.0,
             timestamp: Utc::now(),
             proxied: false,
+            upstream: None,
+            client_cancelled: false,
+            circuit_open: false,
+            repeat_count: 1,
+            source: MetricSource::default(),
+            bytes: None,
+            request_bytes: None,
+            response_bytes: None,
+            bytes_per_sec: None,
+            queue_wait_ms: None,
+            upstream_ms: None,
         }
     }
 
@@ -58,6 +134,80 @@ impl RequestMetric {
         self.proxied = proxied;
         self
     }
+
+    /// Set where the metric was recorded
+    pub fn with_source(mut self, source: MetricSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Mark as cancelled by the client
+    pub fn with_client_cancelled(mut self, cancelled: bool) -> Self {
+        self.client_cancelled = cancelled;
+        self
+    }
+
+    /// Mark as short-circuited by an open circuit breaker
+    pub fn with_circuit_open(mut self, open: bool) -> Self {
+        self.circuit_open = open;
+        self
+    }
+
+    /// Whether `other` repeats this request within the latency tolerance
+    fn is_repeat_of(&self, other: &RequestMetric, tolerance_ms: f64) -> bool {
+        self.method == other.method
+            && self.path == other.path
+            && self.status_code == other.status_code
+            && self.proxied == other.proxied
+            && self.circuit_open == other.circuit_open
+            && self.source == other.source
+            && (self.latency_ms - other.latency_ms).abs() <= tolerance_ms
+    }
+
+    /// Estimate the serialized size of this metric in bytes
+    ///
+    /// Cheap approximation of the JSON encoding: a fixed overhead for field
+    /// names, timestamp and numbers plus the length of each string field.
+    pub fn estimated_size(&self) -> usize {
+        const FIXED_OVERHEAD: usize = 160;
+        FIXED_OVERHEAD
+            + self.id.len()
+            + self.method.len()
+            + self.path.len()
+            + self.upstream.as_ref().map_or(0, |u| u.len())
+    }
+
+    /// Set the number of body bytes transferred
+    pub fn with_bytes(mut self, bytes: u64) -> Self {
+        self.bytes = Some(bytes);
+        self
+    }
+
+    /// Set the request and response body sizes, where known
+    pub fn with_body_sizes(mut self, request: Option<u64>, response: Option<u64>) -> Self {
+        self.request_bytes = request;
+        self.response_bytes = response;
+        self
+    }
+
+    /// Set the effective throughput
+    pub fn with_throughput(mut self, bytes_per_sec: f64) -> Self {
+        self.bytes_per_sec = Some(bytes_per_sec);
+        self
+    }
+
+    /// Split the latency into time queued for a permit and time upstream
+    pub fn with_queue_split(mut self, queue_wait_ms: f64, upstream_ms: f64) -> Self {
+        self.queue_wait_ms = Some(queue_wait_ms);
+        self.upstream_ms = Some(upstream_ms);
+        self
+    }
+
+    /// Set the upstream target
+    pub fn with_upstream(mut self, upstream: impl Into<String>) -> Self {
+        self.upstream = Some(upstream.into());
+        self
+    }
 }
 
 /// Aggregated metrics summary
@@ -75,45 +225,613 @@ pub struct MetricsSummary {
     pub min_latency_ms: f64,
     /// Maximum latency in milliseconds
     pub max_latency_ms: f64,
+    /// Request body bytes received, over requests with a known size
+    #[serde(default)]
+    pub total_bytes_in: u64,
+    /// Response body bytes sent, over requests with a known size
+    #[serde(default)]
+    pub total_bytes_out: u64,
+    /// Population standard deviation of latency in milliseconds
+    #[serde(default)]
+    pub stddev_latency_ms: f64,
+    /// Fraction (0..1) of requests with a status code that failed (4xx, 5xx)
+    #[serde(default)]
+    pub error_rate: f64,
+    /// Median latency in milliseconds
+    #[serde(default)]
+    pub p50_latency_ms: f64,
+    /// 95th percentile latency in milliseconds
+    #[serde(default)]
+    pub p95_latency_ms: f64,
+    /// 99th percentile latency in milliseconds
+    #[serde(default)]
+    pub p99_latency_ms: f64,
     /// Number of proxied requests
     pub proxied_requests: u64,
     /// Status code distribution
     pub status_distribution: HashMap<u16, u64>,
-    /// Requests per second (over last minute)
+    /// Requests answered with a 2xx status
+    #[serde(default)]
+    pub count_2xx: u64,
+    /// Requests answered with a 3xx status
+    #[serde(default)]
+    pub count_3xx: u64,
+    /// Requests answered with a 4xx status
+    #[serde(default)]
+    pub count_4xx: u64,
+    /// Requests answered with a 5xx status
+    #[serde(default)]
+    pub count_5xx: u64,
+    /// Requests with a 1xx or nonstandard status, or none at all
+    #[serde(default)]
+    pub count_other: u64,
+    /// Fraction (0..1) of all requests that succeeded (2xx)
+    #[serde(default)]
+    pub success_rate: f64,
+    /// Requests per second over the last minute (or since the oldest metric, if sooner)
     pub requests_per_second: f64,
+    /// Estimated memory used by stored metrics, in bytes
+    pub estimated_bytes: usize,
+    /// Average time proxied requests waited for an upstream permit, in milliseconds
+    #[serde(default)]
+    pub avg_queue_wait_ms: f64,
+    /// Average time proxied requests spent upstream, in milliseconds
+    #[serde(default)]
+    pub avg_upstream_ms: f64,
+    /// SLO error budget burn rate over the last 5 minutes (1.0 = on budget)
+    #[serde(default)]
+    pub burn_rate_5m: Option<f64>,
+    /// SLO error budget burn rate over the last hour (1.0 = on budget)
+    #[serde(default)]
+    pub burn_rate_1h: Option<f64>,
+    /// Summary baseline: only metrics recorded since then are summarized
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Fraction of requests stored, when sampling; every other count and rate
+    /// is over the stored sample, except the `exact_*` counters
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+    /// Requests recorded since the last clear or summary baseline, counted
+    /// before sampling and regardless of retention or eviction (sampling only)
+    #[serde(default)]
+    pub exact_total_requests: Option<u64>,
+    /// Exact count of 2xx requests, like `exact_total_requests`
+    #[serde(default)]
+    pub exact_successful_requests: Option<u64>,
+    /// Exact count of 4xx and 5xx requests, like `exact_total_requests`
+    #[serde(default)]
+    pub exact_failed_requests: Option<u64>,
+    /// Exponentially weighted moving average of latency since the last clear, in
+    /// milliseconds (ignores the summary baseline; 0 before the first request)
+    #[serde(default)]
+    pub ewma_latency_ms: f64,
+}
+
+/// Per-endpoint metrics, keyed by method and raw path
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointSummary {
+    /// Number of requests
+    pub count: u64,
+    /// Average latency in milliseconds
+    pub avg_latency_ms: f64,
+    /// 95th percentile latency in milliseconds
+    pub p95_latency_ms: f64,
+    /// Number of failed requests (4xx, 5xx status)
+    pub error_count: u64,
+}
+
+/// Criteria for `MetricsCollector::query`; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsFilter {
+    /// HTTP method (case-insensitive)
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Exact status code
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Substring of the path
+    #[serde(default)]
+    pub path_contains: Option<String>,
+}
+
+impl MetricsFilter {
+    /// Whether `metric` matches every set criterion
+    pub fn matches(&self, metric: &RequestMetric) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|method| metric.method.eq_ignore_ascii_case(method))
+            && self
+                .status
+                .is_none_or(|status| metric.status_code == Some(status))
+            && self
+                .path_contains
+                .as_ref()
+                .is_none_or(|needle| metric.path.contains(needle.as_str()))
+    }
+}
+
+/// A page of metrics matching a filter, newest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsPage {
+    /// Number of stored metrics matching the filter
+    pub total: usize,
+    /// Matching metrics skipped, counting back from the newest
+    pub offset: usize,
+    /// Maximum number of items in the page
+    pub limit: usize,
+    /// The matching metrics, newest first
+    pub items: Vec<RequestMetric>,
+}
+
+/// Exact request counts for one source, kept while metrics are sampled
+#[derive(Debug, Default)]
+struct RequestCounts {
+    total: AtomicU64,
+    successful: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl RequestCounts {
+    fn add(&self, status: Option<u16>) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        match status {
+            Some(200..=299) => self.successful.fetch_add(1, Ordering::Relaxed),
+            Some(400..) => self.failed.fetch_add(1, Ordering::Relaxed),
+            _ => 0,
+        };
+    }
+
+    fn reset(&self) {
+        self.total.store(0, Ordering::Relaxed);
+        self.successful.store(0, Ordering::Relaxed);
+        self.failed.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Exponentially weighted moving averages of latency, overall and per source
+///
+/// Each average starts at the first latency seen rather than ramping up from 0.
+#[derive(Debug, Default)]
+struct LatencyEwma {
+    all: Option<f64>,
+    by_source: [Option<f64>; 3],
+}
+
+impl LatencyEwma {
+    fn update(&mut self, source: MetricSource, latency_ms: f64, alpha: f64) {
+        for average in [&mut self.all, &mut self.by_source[source as usize]] {
+            *average = Some(match *average {
+                Some(previous) => previous + alpha * (latency_ms - previous),
+                None => latency_ms,
+            });
+        }
+    }
+
+    fn get(&self, source: Option<MetricSource>) -> Option<f64> {
+        match source {
+            Some(source) => self.by_source[source as usize],
+            None => self.all,
+        }
+    }
 }
 
+/// Metrics buffered per live-feed subscriber before it starts missing some
+#[cfg(feature = "websocket")]
+pub const FEED_CAPACITY: usize = 1024;
+
 /// Metrics collector
 #[derive(Debug)]
 pub struct MetricsCollector {
     /// All recorded metrics
-    metrics: RwLock<Vec<RequestMetric>>,
+    metrics: RwLock<VecDeque<RequestMetric>>,
     /// Maximum number of metrics to keep in memory
     max_entries: usize,
+    /// How the oldest metrics are evicted once `max_entries` is reached
+    eviction: EvictionMode,
+    /// Fraction of `max_entries` removed per bulk eviction
+    evict_fraction: f64,
+    /// Maximum estimated size of stored metrics in bytes
+    max_bytes: Option<usize>,
+    /// Running estimate of the stored metrics' size in bytes
+    current_bytes: AtomicUsize,
+    /// Latency tolerance for coalescing identical consecutive metrics
+    coalesce_tolerance_ms: Option<f64>,
+    /// Age in seconds after which metrics are pruned, regardless of count
+    retention_secs: Option<i64>,
+    /// Continuous CSV stream every recorded metric is appended to
+    csv_stream: Option<CsvStream>,
+    /// SQLite database the stored metrics are persisted to and `history` reads
+    sqlite: Option<SqliteStore>,
+    /// SLO used for burn-rate calculation
+    slo: Option<SloConfig>,
+    /// Whether recording is temporarily suspended
+    paused: AtomicBool,
+    /// Summaries only cover metrics timestamped at or after this marker
+    summary_baseline: RwLock<Option<DateTime<Utc>>>,
+    /// Fraction of recorded metrics that are kept
+    sample_rate: f64,
+    /// Exact per-source counts since the last clear, indexed by `MetricSource`
+    counts: [RequestCounts; 3],
+    /// Exact per-source counts since the summary baseline was set
+    baseline_counts: [RequestCounts; 3],
+    /// Weight of each new latency in the moving averages
+    ewma_alpha: f64,
+    /// Moving averages of latency since the last clear
+    ewma: Mutex<LatencyEwma>,
+    /// Live feed of recorded metrics for WebSocket subscribers
+    #[cfg(feature = "websocket")]
+    feed: tokio::sync::broadcast::Sender<RequestMetric>,
 }
 
 impl MetricsCollector {
     /// Create a new metrics collector
     pub fn new(max_entries: usize) -> Self {
         Self {
-            metrics: RwLock::new(Vec::with_capacity(max_entries)),
+            metrics: RwLock::new(VecDeque::with_capacity(max_entries)),
             max_entries,
+            eviction: EvictionMode::default(),
+            evict_fraction: 0.1,
+            max_bytes: None,
+            current_bytes: AtomicUsize::new(0),
+            coalesce_tolerance_ms: None,
+            retention_secs: None,
+            csv_stream: None,
+            sqlite: None,
+            slo: None,
+            paused: AtomicBool::new(false),
+            summary_baseline: RwLock::new(None),
+            sample_rate: 1.0,
+            counts: Default::default(),
+            baseline_counts: Default::default(),
+            ewma_alpha: crate::config::default_ewma_alpha(),
+            ewma: Mutex::new(LatencyEwma::default()),
+            #[cfg(feature = "websocket")]
+            feed: tokio::sync::broadcast::Sender::new(FEED_CAPACITY),
+        }
+    }
+
+    /// Create a metrics collector that also prunes metrics older than `retention_secs`
+    ///
+    /// `max_entries` stays a hard cap; expired metrics are pruned on each `record()`.
+    pub fn with_retention(max_entries: usize, retention_secs: Option<i64>) -> Self {
+        let mut collector = Self::new(max_entries);
+        collector.retention_secs = retention_secs;
+        collector
+    }
+
+    /// Create a metrics collector persisted to a SQLite database (requires the `sqlite` feature)
+    ///
+    /// The newest `max_entries` stored metrics are loaded on startup and every
+    /// recorded metric is written back in the background. `history` reads the
+    /// database; other queries serve the in-memory window.
+    pub fn with_sqlite(path: &str, max_entries: usize) -> anyhow::Result<Self> {
+        Self::with_sqlite_rows(path, max_entries, max_entries)
+    }
+
+    /// Like `with_sqlite`, with the database keeping up to `max_rows` metrics
+    pub fn with_sqlite_rows(
+        path: &str,
+        max_entries: usize,
+        max_rows: usize,
+    ) -> anyhow::Result<Self> {
+        let (store, loaded) = SqliteStore::open(path, max_entries, max_rows.max(max_entries))?;
+        let bytes = loaded.iter().map(RequestMetric::estimated_size).sum();
+
+        let mut collector = Self::new(max_entries);
+        collector.metrics = RwLock::new(loaded.into());
+        collector.current_bytes = AtomicUsize::new(bytes);
+        collector.sqlite = Some(store);
+        Ok(collector)
+    }
+
+    /// Create a metrics collector from configuration
+    pub fn from_config(config: &MetricsConfig) -> Self {
+        let csv_stream = config.csv_stream_path.as_ref().and_then(|path| {
+            CsvStream::spawn(path, config.csv_max_bytes)
+                .map_err(|e| tracing::error!(error = %e, "Failed to start CSV metrics stream"))
+                .ok()
+        });
+
+        let mut collector = match &config.sqlite_path {
+            Some(path) => Self::with_sqlite_rows(
+                path,
+                config.max_entries,
+                config.sqlite_max_rows.unwrap_or(config.max_entries),
+            )
+            .unwrap_or_else(|e| {
+                tracing::error!(error = %e, "Failed to open metrics database, keeping metrics in memory only");
+                Self::new(config.max_entries)
+            }),
+            None => Self::new(config.max_entries),
+        };
+        collector.retention_secs = config.retention_secs;
+
+        collector
+            .with_eviction(config.eviction, config.evict_fraction)
+            .with_max_bytes(config.max_bytes)
+            .with_coalescing(
+                config
+                    .coalesce_identical
+                    .then_some(config.coalesce_tolerance_ms),
+            )
+            .with_csv_stream(csv_stream)
+            .with_slo(config.slo)
+            .with_sampling(config.sample_rate)
+            .with_ewma_alpha(config.ewma_alpha)
+    }
+
+    /// Weight (0.0-1.0] each new latency gets in `ewma_latency_ms`
+    ///
+    /// Out-of-range values are clamped; NaN keeps the default.
+    pub fn with_ewma_alpha(mut self, alpha: f64) -> Self {
+        if !alpha.is_nan() {
+            self.ewma_alpha = alpha.clamp(f64::MIN_POSITIVE, 1.0);
         }
+        self
+    }
+
+    /// Keep only a random `rate` (0.0-1.0) fraction of recorded metrics
+    ///
+    /// Summaries are computed over the sample, with exact request counts
+    /// reported separately in the `exact_*` fields.
+    pub fn with_sampling(mut self, rate: f64) -> Self {
+        self.sample_rate = if rate.is_nan() {
+            1.0
+        } else {
+            rate.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    /// Whether only a fraction of metrics is stored
+    fn is_sampling(&self) -> bool {
+        self.sample_rate < 1.0
+    }
+
+    /// Choose how the oldest metrics are evicted once `max_entries` is reached
+    ///
+    /// `fraction` of `max_entries` (at least one entry) is removed per bulk eviction.
+    pub fn with_eviction(mut self, mode: EvictionMode, fraction: f64) -> Self {
+        self.eviction = mode;
+        self.evict_fraction = fraction;
+        self
+    }
+
+    /// Compute SLO burn rates in the summary
+    pub fn with_slo(mut self, slo: Option<SloConfig>) -> Self {
+        self.slo = slo;
+        self
+    }
+
+    /// Append every recorded metric to a CSV stream
+    pub fn with_csv_stream(mut self, stream: Option<CsvStream>) -> Self {
+        self.csv_stream = stream;
+        self
+    }
+
+    /// Coalesce identical consecutive metrics whose latency differs by at most `tolerance_ms`
+    pub fn with_coalescing(mut self, tolerance_ms: Option<f64>) -> Self {
+        self.coalesce_tolerance_ms = tolerance_ms;
+        self
+    }
+
+    /// Limit the estimated memory used by stored metrics
+    pub fn with_max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Suspend or resume recording without discarding stored metrics
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Subscribe to metrics as they are recorded
+    ///
+    /// Receivers more than `FEED_CAPACITY` metrics behind get `Lagged` and
+    /// resume from the oldest metric still buffered.
+    #[cfg(feature = "websocket")]
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<RequestMetric> {
+        self.feed.subscribe()
+    }
+
+    /// Start summaries afresh from now, keeping stored metrics
+    ///
+    /// Returns the new baseline. Raw queries like `get_all` are unaffected.
+    pub fn set_summary_baseline(&self) -> DateTime<Utc> {
+        let now = Utc::now();
+        *self.summary_baseline.write() = Some(now);
+        self.baseline_counts.iter().for_each(RequestCounts::reset);
+        now
+    }
+
+    /// Summarize all stored metrics again
+    pub fn clear_summary_baseline(&self) {
+        *self.summary_baseline.write() = None;
+    }
+
+    /// Current summary baseline, if set
+    pub fn summary_baseline(&self) -> Option<DateTime<Utc>> {
+        *self.summary_baseline.read()
+    }
+
+    /// Check if recording is suspended
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
     }
 
     /// Record a new request metric
     pub fn record(&self, metric: RequestMetric) {
+        if self.is_paused() {
+            return;
+        }
+
+        // Averaged before sampling, so it covers every request
+        self.ewma
+            .lock()
+            .update(metric.source, metric.latency_ms, self.ewma_alpha);
+
+        if self.is_sampling() {
+            let source = metric.source as usize;
+            self.counts[source].add(metric.status_code);
+            self.baseline_counts[source].add(metric.status_code);
+            if !rand::thread_rng().gen_bool(self.sample_rate) {
+                return;
+            }
+        }
+
+        if let Some(stream) = &self.csv_stream {
+            stream.send(&metric);
+        }
+
+        // Sending never blocks; subscribers that fall behind skip what they missed
+        #[cfg(feature = "websocket")]
+        if self.feed.receiver_count() > 0 {
+            let _ = self.feed.send(metric.clone());
+        }
+
         let mut metrics = self.metrics.write();
-        if metrics.len() >= self.max_entries {
+
+        // Prune metrics that have aged out of the retention window
+        if let Some(retention_secs) = self.retention_secs {
+            let cutoff = Utc::now() - chrono::Duration::seconds(retention_secs);
+            let expired = metrics.iter().take_while(|m| m.timestamp < cutoff).count();
+            if expired > 0 {
+                let evicted: usize = metrics.drain(0..expired).map(|m| m.estimated_size()).sum();
+                self.current_bytes.fetch_sub(evicted, Ordering::Relaxed);
+            }
+        }
+
+        // Fold repeats of the previous request into its repeat count
+        if let (Some(tolerance), Some(last)) = (self.coalesce_tolerance_ms, metrics.back_mut()) {
+            if last.is_repeat_of(&metric, tolerance) {
+                last.repeat_count += 1;
+                if let Some(store) = &self.sqlite {
+                    store.upsert(last);
+                }
+                return;
+            }
+        }
+
+        if !metrics.is_empty() && metrics.len() >= self.max_entries {
             // Remove oldest entries when at capacity
-            metrics.drain(0..self.max_entries / 10);
+            let count = match self.eviction {
+                EvictionMode::Ring => 1,
+                EvictionMode::Bulk => {
+                    (self.max_entries as f64 * self.evict_fraction).ceil() as usize
+                }
+            };
+            let count = count.max(1).min(metrics.len());
+            let evicted: usize = metrics.drain(0..count).map(|m| m.estimated_size()).sum();
+            self.current_bytes.fetch_sub(evicted, Ordering::Relaxed);
+        }
+
+        let mut total = self
+            .current_bytes
+            .fetch_add(metric.estimated_size(), Ordering::Relaxed)
+            + metric.estimated_size();
+        if let Some(store) = &self.sqlite {
+            store.upsert(&metric);
+        }
+        metrics.push_back(metric);
+
+        if let Some(max_bytes) = self.max_bytes {
+            // Evict oldest entries until under the byte cap
+            let mut evict = 0;
+            while total > max_bytes && evict < metrics.len() - 1 {
+                total -= metrics[evict].estimated_size();
+                evict += 1;
+            }
+            if evict > 0 {
+                metrics.drain(0..evict);
+                self.current_bytes.store(total, Ordering::Relaxed);
+            }
         }
-        metrics.push(metric);
+    }
+
+    /// Wait up to `timeout` for the CSV stream and SQLite writers to catch up
+    ///
+    /// Blocks the calling thread. Returns `false` if a writer did not confirm in time.
+    pub fn flush(&self, timeout: std::time::Duration) -> bool {
+        let csv = self.csv_stream.as_ref().is_none_or(|s| s.flush(timeout));
+        let sqlite = self.sqlite.as_ref().is_none_or(|s| s.flush(timeout));
+        csv && sqlite
+    }
+
+    /// Get the estimated memory used by stored metrics, in bytes
+    pub fn estimated_bytes(&self) -> usize {
+        self.current_bytes.load(Ordering::Relaxed)
     }
 
     /// Get all metrics
     pub fn get_all(&self) -> Vec<RequestMetric> {
-        self.metrics.read().clone()
+        self.metrics.read().iter().cloned().collect()
+    }
+
+    /// Get up to `limit` of the newest metrics at or after `since`, oldest first
+    ///
+    /// Reads the SQLite database when there is one, so history older than the
+    /// in-memory window is reachable; metrics still in memory replace their
+    /// stored rows, so records the background writer hasn't committed show up.
+    /// Without a database, or if reading it fails, the in-memory metrics are used.
+    pub fn history(&self, since: Option<DateTime<Utc>>, limit: usize) -> Vec<RequestMetric> {
+        let cutoff = self
+            .retention_secs
+            .map(|secs| Utc::now() - chrono::Duration::seconds(secs));
+        let since = since.max(cutoff);
+        let stored = match self
+            .sqlite
+            .as_ref()
+            .map(|store| store.history(since, limit))
+        {
+            Some(Ok(rows)) => rows,
+            Some(Err(e)) => {
+                tracing::warn!(error = %e, "Failed to read metrics database, using in-memory metrics");
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        let metrics = self.metrics.read();
+        let resident: HashSet<&str> = metrics.iter().map(|m| m.id.as_str()).collect();
+        let mut rows: Vec<RequestMetric> = stored
+            .into_iter()
+            .filter(|m| !resident.contains(m.id.as_str()))
+            .collect();
+        rows.extend(
+            metrics
+                .iter()
+                .filter(|m| since.is_none_or(|since| m.timestamp >= since))
+                .cloned(),
+        );
+        let excess = rows.len().saturating_sub(limit);
+        rows.drain(..excess);
+        rows
+    }
+
+    /// Export stored metrics as a HAR 1.2 log, resolving paths against `base_url`
+    pub fn export_har(&self, base_url: &str) -> HarFile {
+        HarFile::from_metrics(&self.get_all(), base_url)
+    }
+
+    /// Get up to `limit` metrics matching `filter`, newest first, skipping the `offset` newest
+    pub fn query(&self, filter: &MetricsFilter, limit: usize, offset: usize) -> MetricsPage {
+        let metrics = self.metrics.read();
+        let mut total = 0;
+        let mut items = Vec::new();
+        for metric in metrics.iter().rev().filter(|m| filter.matches(m)) {
+            if total >= offset && items.len() < limit {
+                items.push(metric.clone());
+            }
+            total += 1;
+        }
+
+        MetricsPage {
+            total,
+            offset,
+            limit,
+            items,
+        }
     }
 
     /// Get metrics from the last N seconds
@@ -129,13 +847,40 @@ impl MetricsCollector {
 
     /// Get aggregated summary
     pub fn get_summary(&self) -> MetricsSummary {
-        let metrics = self.metrics.read();
+        self.get_summary_for(None)
+    }
+
+    /// Get aggregated summary of metrics from one source (all sources if `None`)
+    ///
+    /// Only metrics since the summary baseline, if one is set, are included.
+    pub fn get_summary_for(&self, source: Option<MetricSource>) -> MetricsSummary {
+        let since = self.summary_baseline();
+        let stored = self.metrics.read();
+        let metrics: Vec<&RequestMetric> = stored
+            .iter()
+            .filter(|m| source.is_none_or(|s| m.source == s))
+            .filter(|m| since.is_none_or(|since| m.timestamp >= since))
+            .collect();
+        self.summarize(metrics, source, since)
+    }
 
+    fn summarize(
+        &self,
+        metrics: Vec<&RequestMetric>,
+        source: Option<MetricSource>,
+        since: Option<DateTime<Utc>>,
+    ) -> MetricsSummary {
         if metrics.is_empty() {
-            return MetricsSummary::default();
+            let mut summary = MetricsSummary {
+                since,
+                ewma_latency_ms: self.ewma.lock().get(source).unwrap_or(0.0),
+                ..Default::default()
+            };
+            self.apply_sampling(&mut summary, source);
+            return summary;
         }
 
-        let total_requests = metrics.len() as u64;
+        let total_requests: u64 = metrics.iter().map(|m| m.repeat_count).sum();
         let mut successful_requests = 0u64;
         let mut failed_requests = 0u64;
         let mut total_latency = 0.0;
@@ -143,35 +888,80 @@ impl MetricsCollector {
         let mut max_latency = 0.0f64;
         let mut proxied_requests = 0u64;
         let mut status_distribution = HashMap::new();
+        let mut split_count = 0u64;
+        let mut total_queue_wait = 0.0;
+        let mut total_upstream = 0.0;
+        let mut with_status = 0u64;
+        let mut total_bytes_in = 0u64;
+        let mut total_bytes_out = 0u64;
+        // Weighted Welford: running mean and sum of squared deviations
+        let mut seen = 0u64;
+        let mut mean = 0.0;
+        let mut m2 = 0.0;
 
         for metric in metrics.iter() {
-            total_latency += metric.latency_ms;
+            let weight = metric.repeat_count;
+            if weight > 0 {
+                seen += weight;
+                let delta = metric.latency_ms - mean;
+                mean += delta * weight as f64 / seen as f64;
+                m2 += weight as f64 * delta * (metric.latency_ms - mean);
+            }
+            if let (Some(queue_wait), Some(upstream)) = (metric.queue_wait_ms, metric.upstream_ms) {
+                split_count += weight;
+                total_queue_wait += queue_wait * weight as f64;
+                total_upstream += upstream * weight as f64;
+            }
+            total_latency += metric.latency_ms * weight as f64;
             min_latency = min_latency.min(metric.latency_ms);
             max_latency = max_latency.max(metric.latency_ms);
 
             if metric.proxied {
-                proxied_requests += 1;
+                proxied_requests += weight;
             }
+            total_bytes_in += metric.request_bytes.unwrap_or(0) * weight;
+            total_bytes_out += metric.response_bytes.unwrap_or(0) * weight;
 
             if let Some(status) = metric.status_code {
-                *status_distribution.entry(status).or_insert(0) += 1;
+                with_status += weight;
+                *status_distribution.entry(status).or_insert(0) += weight;
                 if (200..300).contains(&status) {
-                    successful_requests += 1;
+                    successful_requests += weight;
                 } else if status >= 400 {
-                    failed_requests += 1;
+                    failed_requests += weight;
                 }
             }
         }
 
-        // Calculate requests per second over last minute
-        let one_minute_ago = Utc::now() - chrono::Duration::minutes(1);
-        let recent_count = metrics
+        let class_count = |class: u16| {
+            status_distribution
+                .iter()
+                .filter(|(status, _)| *status / 100 == class)
+                .map(|(_, count)| count)
+                .sum::<u64>()
+        };
+        let (count_2xx, count_3xx, count_4xx, count_5xx) = (
+            class_count(2),
+            class_count(3),
+            class_count(4),
+            class_count(5),
+        );
+
+        let requests_per_second = requests_per_second(&metrics, Duration::from_secs(60));
+        let mut latencies: Vec<(f64, u64)> = metrics
             .iter()
-            .filter(|m| m.timestamp > one_minute_ago)
-            .count() as f64;
-        let requests_per_second = recent_count / 60.0;
+            .map(|m| (m.latency_ms, m.repeat_count))
+            .collect();
+        latencies.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let split_avg = |total: f64| {
+            if split_count == 0 {
+                0.0
+            } else {
+                total / split_count as f64
+            }
+        };
 
-        MetricsSummary {
+        let mut summary = MetricsSummary {
             total_requests,
             successful_requests,
             failed_requests,
@@ -182,15 +972,170 @@ impl MetricsCollector {
                 min_latency
             },
             max_latency_ms: max_latency,
+            total_bytes_in,
+            total_bytes_out,
+            stddev_latency_ms: if seen == 0 {
+                0.0
+            } else {
+                (m2 / seen as f64).sqrt()
+            },
+            error_rate: if with_status == 0 {
+                0.0
+            } else {
+                failed_requests as f64 / with_status as f64
+            },
+            p50_latency_ms: nearest_rank(&latencies, total_requests, 50.0),
+            p95_latency_ms: nearest_rank(&latencies, total_requests, 95.0),
+            p99_latency_ms: nearest_rank(&latencies, total_requests, 99.0),
             proxied_requests,
             status_distribution,
+            count_2xx,
+            count_3xx,
+            count_4xx,
+            count_5xx,
+            count_other: total_requests - (count_2xx + count_3xx + count_4xx + count_5xx),
+            success_rate: successful_requests as f64 / total_requests as f64,
             requests_per_second,
+            estimated_bytes: self.estimated_bytes(),
+            avg_queue_wait_ms: split_avg(total_queue_wait),
+            avg_upstream_ms: split_avg(total_upstream),
+            burn_rate_5m: self.burn_rate(&metrics, chrono::Duration::minutes(5)),
+            burn_rate_1h: self.burn_rate(&metrics, chrono::Duration::hours(1)),
+            since,
+            sample_rate: None,
+            exact_total_requests: None,
+            exact_successful_requests: None,
+            exact_failed_requests: None,
+            ewma_latency_ms: self.ewma.lock().get(source).unwrap_or(0.0),
+        };
+        self.apply_sampling(&mut summary, source);
+        summary
+    }
+
+    /// Report the exact request counts alongside the sampled ones and scale the request rate up
+    fn apply_sampling(&self, summary: &mut MetricsSummary, source: Option<MetricSource>) {
+        if !self.is_sampling() {
+            return;
+        }
+        let counts = if summary.since.is_some() {
+            &self.baseline_counts
+        } else {
+            &self.counts
+        };
+        let sources = [
+            MetricSource::ServerInbound,
+            MetricSource::TestClient,
+            MetricSource::Proxy,
+        ];
+        let (mut total, mut successful, mut failed) = (0, 0, 0);
+        for s in sources
+            .into_iter()
+            .filter(|&s| source.is_none_or(|f| f == s))
+        {
+            let c = &counts[s as usize];
+            total += c.total.load(Ordering::Relaxed);
+            successful += c.successful.load(Ordering::Relaxed);
+            failed += c.failed.load(Ordering::Relaxed);
+        }
+
+        summary.exact_total_requests = Some(total);
+        summary.exact_successful_requests = Some(successful);
+        summary.exact_failed_requests = Some(failed);
+        if self.sample_rate > 0.0 {
+            summary.requests_per_second /= self.sample_rate;
+        }
+        summary.sample_rate = Some(self.sample_rate);
+    }
+
+    /// Requests per second over the trailing `window`
+    ///
+    /// When the oldest retained metric is younger than `window` (just after
+    /// startup, or after a clear), the rate is taken over that shorter span.
+    pub fn rps_over(&self, window: Duration) -> f64 {
+        let metrics = self.metrics.read();
+        let rps = requests_per_second(&metrics.iter().collect::<Vec<_>>(), window);
+        if self.is_sampling() && self.sample_rate > 0.0 {
+            rps / self.sample_rate
+        } else {
+            rps
+        }
+    }
+
+    /// Summarize metrics per `(method, path)`
+    pub fn get_summary_by_endpoint(&self) -> HashMap<(String, String), EndpointSummary> {
+        let metrics = self.metrics.read();
+        let mut latencies: HashMap<(String, String), Vec<(f64, u64)>> = HashMap::new();
+        let mut errors: HashMap<(String, String), u64> = HashMap::new();
+
+        for metric in metrics.iter() {
+            let key = (metric.method.clone(), metric.path.clone());
+            if metric.status_code.is_some_and(|status| status >= 400) {
+                *errors.entry(key.clone()).or_insert(0) += metric.repeat_count;
+            }
+            latencies
+                .entry(key)
+                .or_default()
+                .push((metric.latency_ms, metric.repeat_count));
+        }
+
+        latencies
+            .into_iter()
+            .map(|(key, mut latencies)| {
+                latencies.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let count: u64 = latencies.iter().map(|(_, weight)| weight).sum();
+                let total_latency: f64 = latencies.iter().map(|(l, w)| l * *w as f64).sum();
+                let summary = EndpointSummary {
+                    count,
+                    avg_latency_ms: total_latency / count as f64,
+                    p95_latency_ms: nearest_rank(&latencies, count, 95.0),
+                    error_count: errors.get(&key).copied().unwrap_or(0),
+                };
+                (key, summary)
+            })
+            .collect()
+    }
+
+    /// Error budget burn rate over the trailing window
+    ///
+    /// The ratio of the bad-request fraction to the budget allowed by the SLO:
+    /// 1.0 spends the budget exactly over the SLO period, 10.0 ten times faster.
+    /// Windows longer than the stored history use whatever data is available.
+    fn burn_rate(&self, metrics: &[&RequestMetric], window: chrono::Duration) -> Option<f64> {
+        let slo = self.slo?;
+        let budget = 1.0 - slo.target_pct / 100.0;
+        if budget <= 0.0 {
+            return None;
         }
+
+        let since = Utc::now() - window;
+        let (total, bad) =
+            metrics
+                .iter()
+                .filter(|m| m.timestamp > since)
+                .fold((0u64, 0u64), |(total, bad), m| {
+                    let is_bad = m.latency_ms > slo.latency_ms
+                        || m.status_code.is_none_or(|status| status >= 500);
+                    (
+                        total + m.repeat_count,
+                        bad + if is_bad { m.repeat_count } else { 0 },
+                    )
+                });
+
+        (total > 0).then(|| bad as f64 / total as f64 / budget)
     }
 
     /// Clear all metrics
     pub fn clear(&self) {
-        self.metrics.write().clear();
+        let mut metrics = self.metrics.write();
+        metrics.clear();
+        self.current_bytes.store(0, Ordering::Relaxed);
+        self.counts.iter().for_each(RequestCounts::reset);
+        self.baseline_counts.iter().for_each(RequestCounts::reset);
+        *self.ewma.lock() = LatencyEwma::default();
+        if let Some(store) = &self.sqlite {
+            // Queued after any pending writes, so no older row survives it
+            store.clear();
+        }
     }
 
     /// Get the count of requests
@@ -214,20 +1159,20 @@ impl MetricsCollector {
 
         // Handle edge cases: same values, invalid ranges, or insufficient data
         if max_latency == min_latency || buckets == 0 {
-            return vec![(min_latency, metrics.len() as u64)];
+            return vec![(min_latency, metrics.iter().map(|m| m.repeat_count).sum())];
         }
 
         let bucket_size = (max_latency - min_latency) / buckets as f64;
         // Guard against NaN or zero bucket_size
         if !bucket_size.is_finite() || bucket_size == 0.0 {
-            return vec![(min_latency, metrics.len() as u64)];
+            return vec![(min_latency, metrics.iter().map(|m| m.repeat_count).sum())];
         }
         let mut histogram = vec![0u64; buckets];
 
         for metric in metrics.iter() {
             let bucket = ((metric.latency_ms - min_latency) / bucket_size) as usize;
             let bucket = bucket.min(buckets - 1);
-            histogram[bucket] += 1;
+            histogram[bucket] += metric.repeat_count;
         }
 
         histogram
@@ -237,6 +1182,46 @@ impl MetricsCollector {
             .collect()
     }
 
+    /// Get latency distribution in logarithmically spaced buckets
+    ///
+    /// Bucket `k` covers `[base^k, base^(k+1))` ms, so with `base = 2.0` the
+    /// boundaries are ..., 0.5, 1, 2, 4, 8, ... Returns `(lower_bound, count)`
+    /// for every bucket between the fastest and slowest request, at most
+    /// `MAX_HISTOGRAM_BUCKETS` of them (faster requests join the first bucket).
+    /// A `base` outside `LOG_HISTOGRAM_BASES` falls back to 2.
+    pub fn get_log_histogram(&self, base: f64) -> Vec<(f64, u64)> {
+        let metrics = self.metrics.read();
+        if metrics.is_empty() {
+            return vec![];
+        }
+
+        let base = if LOG_HISTOGRAM_BASES.contains(&base) {
+            base
+        } else {
+            2.0
+        };
+        // Latencies of zero fall into the bucket starting at 1µs
+        let exponent = |latency: f64| latency.max(0.001).log(base).floor() as i64;
+
+        let (min_exp, max_exp) = metrics
+            .iter()
+            .map(|m| exponent(m.latency_ms))
+            .fold((i64::MAX, i64::MIN), |(lo, hi), e| (lo.min(e), hi.max(e)));
+        let min_exp = min_exp.max(max_exp - (MAX_HISTOGRAM_BUCKETS as i64 - 1));
+
+        let mut histogram = vec![0u64; (max_exp - min_exp + 1) as usize];
+        for metric in metrics.iter() {
+            let bucket = (exponent(metric.latency_ms) - min_exp).max(0);
+            histogram[bucket as usize] += metric.repeat_count;
+        }
+
+        histogram
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| (base.powf((min_exp + i as i64) as f64), count))
+            .collect()
+    }
+
     /// Get time-series data for realtime charts
     pub fn get_time_series(&self, points: usize) -> Vec<(DateTime<Utc>, f64)> {
         let metrics = self.metrics.read();
@@ -254,6 +1239,12 @@ impl MetricsCollector {
     }
 }
 
+/// Most buckets a latency histogram returns
+pub const MAX_HISTOGRAM_BUCKETS: usize = 10_000;
+
+/// Accepted growth factors for the log-scale histogram
+pub const LOG_HISTOGRAM_BASES: std::ops::RangeInclusive<f64> = 1.1..=10.0;
+
 /// Shared metrics collector for use across threads
 pub type SharedMetrics = Arc<MetricsCollector>;
 
@@ -262,6 +1253,44 @@ pub fn create_shared_metrics(max_entries: usize) -> SharedMetrics {
     Arc::new(MetricsCollector::new(max_entries))
 }
 
+/// Create a new shared metrics collector from configuration
+pub fn create_shared_metrics_from_config(config: &MetricsConfig) -> SharedMetrics {
+    Arc::new(MetricsCollector::from_config(config))
+}
+
+/// Requests per second over the trailing `window`, or since the oldest metric if that is sooner
+///
+/// The span is at least one second so a lone fresh request does not read as a huge rate.
+fn requests_per_second(metrics: &[&RequestMetric], window: Duration) -> f64 {
+    let now = Utc::now();
+    let window = chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX);
+    let cutoff = now - window;
+
+    let Some(oldest) = metrics.iter().map(|m| m.timestamp).min() else {
+        return 0.0;
+    };
+    let count: u64 = metrics
+        .iter()
+        .filter(|m| m.timestamp > cutoff)
+        .map(|m| m.repeat_count)
+        .sum();
+    let span = (now - oldest).min(window);
+    count as f64 / (span.num_milliseconds() as f64 / 1000.0).max(1.0)
+}
+
+/// Nearest-rank percentile over latencies sorted ascending, each weighted by its repeat count
+fn nearest_rank(sorted: &[(f64, u64)], total: u64, pct: f64) -> f64 {
+    let rank = ((pct / 100.0) * total as f64).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for &(latency, weight) in sorted {
+        seen += weight;
+        if seen >= rank {
+            return latency;
+        }
+    }
+    sorted.last().map(|&(latency, _)| latency).unwrap_or(0.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,6 +1324,459 @@ mod tests {
         assert_eq!(summary.max_latency_ms, 90.0);
     }
 
+    #[test]
+    fn test_summary_stddev_and_error_rate() {
+        let empty = MetricsCollector::new(100).get_summary();
+        assert_eq!(empty.stddev_latency_ms, 0.0);
+        assert_eq!(empty.error_rate, 0.0);
+
+        let collector = MetricsCollector::new(100);
+        for (status, latency) in [
+            (Some(200), 10.0),
+            (Some(200), 20.0),
+            (Some(500), 30.0),
+            (None, 40.0),
+        ] {
+            let mut metric =
+                RequestMetric::new("GET".to_string(), "/".to_string()).with_latency(latency);
+            metric.status_code = status;
+            collector.record(metric);
+        }
+
+        let summary = collector.get_summary();
+        assert!((summary.stddev_latency_ms - 125f64.sqrt()).abs() < 1e-9);
+        assert!((summary.error_rate - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_summary_status_classes() {
+        let collector = MetricsCollector::new(100);
+        for status in [
+            Some(200),
+            Some(204),
+            Some(301),
+            Some(404),
+            Some(503),
+            Some(101),
+            None,
+        ] {
+            let mut metric = RequestMetric::new("GET".to_string(), "/".to_string());
+            metric.status_code = status;
+            collector.record(metric);
+        }
+
+        let summary = collector.get_summary();
+        assert_eq!(
+            (
+                summary.count_2xx,
+                summary.count_3xx,
+                summary.count_4xx,
+                summary.count_5xx,
+                summary.count_other
+            ),
+            (2, 1, 1, 1, 2)
+        );
+        assert!((summary.success_rate - 2.0 / 7.0).abs() < 1e-9);
+        assert_eq!(MetricsCollector::new(100).get_summary().success_rate, 0.0);
+    }
+
+    #[test]
+    fn test_ewma_latency() {
+        let collector = MetricsCollector::new(100).with_ewma_alpha(0.5);
+        assert_eq!(collector.get_summary().ewma_latency_ms, 0.0);
+
+        for latency in [100.0, 200.0, 0.0] {
+            collector.record(
+                RequestMetric::new("GET".to_string(), "/".to_string())
+                    .with_latency(latency)
+                    .with_source(MetricSource::Proxy),
+            );
+        }
+        // Starts at the first value: 100, then 150, then 75
+        assert_eq!(collector.get_summary().ewma_latency_ms, 75.0);
+        assert_eq!(
+            collector
+                .get_summary_for(Some(MetricSource::Proxy))
+                .ewma_latency_ms,
+            75.0
+        );
+        assert_eq!(
+            collector
+                .get_summary_for(Some(MetricSource::TestClient))
+                .ewma_latency_ms,
+            0.0
+        );
+
+        collector.clear();
+        collector.record(RequestMetric::new("GET".to_string(), "/".to_string()).with_latency(40.0));
+        assert_eq!(collector.get_summary().ewma_latency_ms, 40.0);
+    }
+
+    #[test]
+    fn test_summary_baseline_keeps_raw_metrics() {
+        let collector = MetricsCollector::new(100);
+        let mut before = RequestMetric::new("GET".to_string(), "/".to_string()).with_status(500);
+        before.timestamp -= chrono::Duration::seconds(1);
+        collector.record(before);
+
+        let since = collector.set_summary_baseline();
+        collector.record(RequestMetric::new("GET".to_string(), "/".to_string()).with_status(200));
+
+        let summary = collector.get_summary();
+        assert_eq!(summary.total_requests, 1);
+        assert_eq!(summary.failed_requests, 0);
+        assert_eq!(summary.since, Some(since));
+        assert_eq!(collector.get_all().len(), 2);
+
+        collector.clear_summary_baseline();
+        assert_eq!(collector.get_summary().total_requests, 2);
+    }
+
+    #[test]
+    fn test_summary_body_bytes() {
+        let collector = MetricsCollector::new(100);
+        collector.record(
+            RequestMetric::new("POST".to_string(), "/".to_string())
+                .with_body_sizes(Some(100), Some(2048)),
+        );
+        collector.record(
+            RequestMetric::new("GET".to_string(), "/".to_string()).with_body_sizes(None, Some(10)),
+        );
+        collector.record(RequestMetric::new("GET".to_string(), "/".to_string()));
+
+        let summary = collector.get_summary();
+        assert_eq!(summary.total_bytes_in, 100);
+        assert_eq!(summary.total_bytes_out, 2058);
+    }
+
+    #[test]
+    fn test_query_filters_and_pages() {
+        let collector = MetricsCollector::new(100);
+        for i in 0..10 {
+            let method = if i % 2 == 0 { "GET" } else { "POST" };
+            collector.record(
+                RequestMetric::new(method.to_string(), format!("/items/{}", i)).with_status(200),
+            );
+        }
+        collector
+            .record(RequestMetric::new("GET".to_string(), "/health".to_string()).with_status(503));
+
+        let filter = MetricsFilter {
+            method: Some("get".to_string()),
+            path_contains: Some("/items".to_string()),
+            ..Default::default()
+        };
+        let page = collector.query(&filter, 2, 1);
+        assert_eq!(page.total, 5);
+        let paths: Vec<&str> = page.items.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["/items/6", "/items/4"]);
+
+        let errors = MetricsFilter {
+            status: Some(503),
+            ..Default::default()
+        };
+        assert_eq!(collector.query(&errors, 10, 0).items[0].path, "/health");
+        assert!(collector
+            .query(&MetricsFilter::default(), 10, 20)
+            .items
+            .is_empty());
+    }
+
+    #[test]
+    fn test_sampling_keeps_exact_counts() {
+        let collector = MetricsCollector::new(1000).with_sampling(0.1);
+        for i in 0..500 {
+            let status = if i % 5 == 0 { 500 } else { 200 };
+            collector
+                .record(RequestMetric::new("GET".to_string(), "/".to_string()).with_status(status));
+        }
+
+        let summary = collector.get_summary();
+        assert_eq!(summary.exact_total_requests, Some(500));
+        assert_eq!(summary.exact_successful_requests, Some(400));
+        assert_eq!(summary.exact_failed_requests, Some(100));
+        assert_eq!(summary.sample_rate, Some(0.1));
+        assert!(collector.count() < 150);
+
+        // The sampled counts agree with each other
+        assert_eq!(summary.total_requests, collector.count() as u64);
+        assert_eq!(
+            summary.status_distribution.values().sum::<u64>(),
+            summary.total_requests
+        );
+        assert_eq!(
+            summary.count_2xx + summary.count_5xx,
+            summary.total_requests
+        );
+        assert_eq!(summary.successful_requests, summary.count_2xx);
+        assert_eq!(
+            collector
+                .get_summary_for(Some(MetricSource::Proxy))
+                .exact_total_requests,
+            Some(0)
+        );
+
+        collector.set_summary_baseline();
+        collector.record(RequestMetric::new("GET".to_string(), "/".to_string()).with_status(200));
+        assert_eq!(collector.get_summary().exact_total_requests, Some(1));
+
+        collector.clear();
+        collector.clear_summary_baseline();
+        assert_eq!(collector.get_summary().exact_total_requests, Some(0));
+        assert_eq!(
+            MetricsCollector::new(10).get_summary().exact_total_requests,
+            None
+        );
+    }
+
+    #[test]
+    fn test_summary_percentiles() {
+        let collector = MetricsCollector::new(1000);
+        assert_eq!(collector.get_summary().p99_latency_ms, 0.0);
+
+        collector.record(RequestMetric::new("GET".to_string(), "/".to_string()).with_latency(7.0));
+        let summary = collector.get_summary();
+        assert_eq!(summary.p50_latency_ms, 7.0);
+        assert_eq!(summary.p99_latency_ms, 7.0);
+
+        collector.clear();
+        for i in (1..=100).rev() {
+            collector.record(
+                RequestMetric::new("GET".to_string(), format!("/{}", i)).with_latency(i as f64),
+            );
+        }
+        let summary = collector.get_summary();
+        assert_eq!(summary.p50_latency_ms, 50.0);
+        assert_eq!(summary.p95_latency_ms, 95.0);
+        assert_eq!(summary.p99_latency_ms, 99.0);
+    }
+
+    #[test]
+    fn test_summary_by_source() {
+        let collector = MetricsCollector::new(100);
+        collector.record(RequestMetric::new("GET".to_string(), "/".to_string()).with_status(200));
+        collector.record(
+            RequestMetric::new("GET".to_string(), "/".to_string())
+                .with_status(500)
+                .with_source(MetricSource::TestClient),
+        );
+
+        assert_eq!(collector.get_summary().total_requests, 2);
+        let server = collector.get_summary_for(Some(MetricSource::ServerInbound));
+        assert_eq!(server.total_requests, 1);
+        assert_eq!(server.failed_requests, 0);
+        let proxy = collector.get_summary_for(Some(MetricSource::Proxy));
+        assert_eq!(proxy.total_requests, 0);
+    }
+
+    #[test]
+    fn test_summary_by_endpoint() {
+        let collector = MetricsCollector::new(100);
+        let record = |method: &str, path: &str, status: u16, latency: f64| {
+            collector.record(
+                RequestMetric::new(method.to_string(), path.to_string())
+                    .with_status(status)
+                    .with_latency(latency),
+            );
+        };
+        record("GET", "/fast", 200, 10.0);
+        record("GET", "/fast", 200, 20.0);
+        record("GET", "/slow", 500, 400.0);
+        record("POST", "/fast", 201, 30.0);
+
+        let by_endpoint = collector.get_summary_by_endpoint();
+        assert_eq!(by_endpoint.len(), 3);
+
+        let fast = &by_endpoint[&("GET".to_string(), "/fast".to_string())];
+        assert_eq!(fast.count, 2);
+        assert_eq!(fast.avg_latency_ms, 15.0);
+        assert_eq!(fast.p95_latency_ms, 20.0);
+        assert_eq!(fast.error_count, 0);
+
+        let slow = &by_endpoint[&("GET".to_string(), "/slow".to_string())];
+        assert_eq!(slow.error_count, 1);
+    }
+
+    #[test]
+    fn test_retention_prunes_old_metrics() {
+        let collector = MetricsCollector::with_retention(100, Some(60));
+        let aged = |path: &str, age_secs: i64| {
+            let mut metric = RequestMetric::new("GET".to_string(), path.to_string());
+            metric.timestamp = Utc::now() - chrono::Duration::seconds(age_secs);
+            metric
+        };
+
+        collector.record(aged("/old", 300));
+        collector.record(aged("/older-than-window", 61));
+        collector.record(aged("/recent", 30));
+        collector.record(aged("/new", 0));
+
+        let paths: Vec<String> = collector.get_all().into_iter().map(|m| m.path).collect();
+        assert_eq!(paths, vec!["/recent", "/new"]);
+        assert_eq!(
+            collector.estimated_bytes(),
+            collector
+                .get_all()
+                .iter()
+                .map(RequestMetric::estimated_size)
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_rps_over_short_history() {
+        let collector = MetricsCollector::new(100);
+        for i in 0..30 {
+            let mut metric = RequestMetric::new("GET".to_string(), "/".to_string());
+            metric.timestamp = Utc::now() - chrono::Duration::milliseconds(5000 - i * 170);
+            collector.record(metric);
+        }
+
+        let rps = collector.rps_over(Duration::from_secs(60));
+        assert!((rps - 6.0).abs() < 0.5, "rps was {}", rps);
+        assert!((collector.get_summary().requests_per_second - 6.0).abs() < 0.5);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn test_feed_drops_lagged_metrics() {
+        let collector = MetricsCollector::new(10);
+        let mut feed = collector.subscribe();
+
+        // Recording never blocks on a subscriber that is not reading
+        for i in 0..FEED_CAPACITY + 5 {
+            collector.record(RequestMetric::new("GET".to_string(), format!("/{}", i)));
+        }
+
+        assert!(matches!(
+            feed.try_recv(),
+            Err(tokio::sync::broadcast::error::TryRecvError::Lagged(5))
+        ));
+        assert_eq!(feed.try_recv().unwrap().path, "/5");
+    }
+
+    #[test]
+    fn test_eviction_modes() {
+        let record_n = |collector: &MetricsCollector, n: usize| {
+            for i in 0..n {
+                collector.record(RequestMetric::new("GET".to_string(), format!("/{}", i)));
+            }
+        };
+
+        // Ring mode keeps a full window
+        let ring = MetricsCollector::new(20).with_eviction(EvictionMode::Ring, 0.1);
+        record_n(&ring, 25);
+        assert_eq!(ring.count(), 20);
+        assert_eq!(ring.get_all()[0].path, "/5");
+
+        // Bulk mode drops a fraction at once
+        let bulk = MetricsCollector::new(20).with_eviction(EvictionMode::Bulk, 0.5);
+        record_n(&bulk, 21);
+        assert_eq!(bulk.count(), 11);
+
+        // Tiny buffers still evict at least one entry
+        let tiny = MetricsCollector::new(5);
+        record_n(&tiny, 8);
+        assert_eq!(tiny.count(), 5);
+    }
+
+    #[test]
+    fn test_pause_skips_recording() {
+        let collector = MetricsCollector::new(100);
+        collector.record(RequestMetric::new("GET".to_string(), "/a".to_string()));
+
+        collector.set_paused(true);
+        collector.record(RequestMetric::new("GET".to_string(), "/b".to_string()));
+        assert_eq!(collector.count(), 1);
+
+        collector.set_paused(false);
+        collector.record(RequestMetric::new("GET".to_string(), "/c".to_string()));
+        assert_eq!(collector.count(), 2);
+    }
+
+    #[test]
+    fn test_burn_rate() {
+        let collector = MetricsCollector::new(100).with_slo(Some(SloConfig {
+            target_pct: 99.0,
+            latency_ms: 200.0,
+        }));
+        assert_eq!(collector.get_summary().burn_rate_5m, None);
+
+        for i in 0..100 {
+            let status = if i < 2 { 500 } else { 200 };
+            let latency = if i == 2 { 500.0 } else { 50.0 };
+            collector.record(
+                RequestMetric::new("GET".to_string(), "/test".to_string())
+                    .with_status(status)
+                    .with_latency(latency),
+            );
+        }
+
+        // 3% bad against a 1% budget burns three times too fast
+        let summary = collector.get_summary();
+        let burn = summary.burn_rate_5m.unwrap();
+        assert!((burn - 3.0).abs() < 1e-9, "burn rate {}", burn);
+        assert_eq!(summary.burn_rate_1h, summary.burn_rate_5m);
+    }
+
+    #[test]
+    fn test_log_histogram() {
+        let collector = MetricsCollector::new(100);
+        for latency in [0.6, 1.0, 3.0, 3.5, 20.0] {
+            collector.record(
+                RequestMetric::new("GET".to_string(), "/test".to_string()).with_latency(latency),
+            );
+        }
+
+        let histogram = collector.get_log_histogram(2.0);
+        assert_eq!(
+            histogram,
+            vec![(0.5, 1), (1.0, 1), (2.0, 2), (4.0, 0), (8.0, 0), (16.0, 1)]
+        );
+
+        // Degenerate bases fall back to 2 rather than allocating huge spans
+        assert_eq!(collector.get_log_histogram(1.000001), histogram);
+        assert_eq!(collector.get_log_histogram(1.0 + 1e-12), histogram);
+    }
+
+    #[test]
+    fn test_summary_queue_split() {
+        let collector = MetricsCollector::new(100);
+        collector.record(
+            RequestMetric::new("GET".to_string(), "/a".to_string())
+                .with_latency(30.0)
+                .with_queue_split(20.0, 10.0),
+        );
+        collector.record(
+            RequestMetric::new("GET".to_string(), "/b".to_string())
+                .with_latency(30.0)
+                .with_queue_split(0.0, 30.0),
+        );
+        // Unproxied requests don't dilute the averages
+        collector.record(RequestMetric::new("GET".to_string(), "/c".to_string()));
+
+        let summary = collector.get_summary();
+        assert_eq!(summary.avg_queue_wait_ms, 10.0);
+        assert_eq!(summary.avg_upstream_ms, 20.0);
+    }
+
+    #[test]
+    fn test_ring_buffer_stays_bounded() {
+        let collector = MetricsCollector::new(10_000);
+        for _ in 0..1_000_000 {
+            collector.record(RequestMetric::new("GET".to_string(), "/".to_string()));
+        }
+
+        assert!(collector.count() <= 10_000);
+        // Evicting from the front of the deque never grows or reallocates it
+        assert!(collector.metrics.read().capacity() < 20_000);
+        assert_eq!(collector.get_recent(60).len(), collector.count());
+        assert_eq!(
+            collector.get_summary().total_requests as usize,
+            collector.count()
+        );
+    }
+
     #[test]
     fn test_max_entries() {
         let collector = MetricsCollector::new(20);
@@ -307,4 +1789,60 @@ mod tests {
         // Should have removed some entries
         assert!(collector.count() < 30);
     }
+
+    #[test]
+    fn test_zero_max_entries_keeps_latest() {
+        for mode in [EvictionMode::Ring, EvictionMode::Bulk] {
+            let collector = MetricsCollector::new(0).with_eviction(mode, 0.1);
+            for i in 0..3 {
+                collector.record(RequestMetric::new(
+                    "GET".to_string(),
+                    format!("/test/{}", i),
+                ));
+            }
+            assert_eq!(collector.count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_coalesce_identical() {
+        let collector = MetricsCollector::new(100).with_coalescing(Some(5.0));
+
+        for latency in [10.0, 12.0, 11.0] {
+            let metric = RequestMetric::new("GET".to_string(), "/health".to_string())
+                .with_status(200)
+                .with_latency(latency);
+            collector.record(metric);
+        }
+        let metric = RequestMetric::new("GET".to_string(), "/health".to_string())
+            .with_status(200)
+            .with_latency(100.0);
+        collector.record(metric);
+
+        assert_eq!(collector.count(), 2);
+        assert_eq!(collector.get_all()[0].repeat_count, 3);
+
+        let summary = collector.get_summary();
+        assert_eq!(summary.total_requests, 4);
+        assert_eq!(summary.successful_requests, 4);
+        assert_eq!(summary.avg_latency_ms, 32.5);
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let metric_size =
+            RequestMetric::new("GET".to_string(), "/test/0".to_string()).estimated_size();
+        let collector = MetricsCollector::new(1000).with_max_bytes(Some(metric_size * 5));
+
+        for i in 0..20 {
+            let metric = RequestMetric::new("GET".to_string(), format!("/test/{}", i % 10));
+            collector.record(metric);
+        }
+
+        assert_eq!(collector.count(), 5);
+        assert!(collector.estimated_bytes() <= metric_size * 5);
+
+        collector.clear();
+        assert_eq!(collector.estimated_bytes(), 0);
+    }
 }
@@ -0,0 +1,107 @@
+//! Prometheus text exposition format
+//!
+//! Renders a [`MetricsSummary`] for scraping. Values describe the stored
+//! metrics, so counters drop when metrics are cleared or evicted; Prometheus
+//! treats that as a counter reset.
+
+use super::MetricsSummary;
+use std::fmt::Write;
+
+/// Content type of the text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Render a summary in the Prometheus text format
+pub fn render(summary: &MetricsSummary) -> String {
+    let mut out = String::new();
+
+    counter(
+        &mut out,
+        "api_check_requests_total",
+        "Total number of recorded requests",
+        summary.total_requests,
+    );
+    counter(
+        &mut out,
+        "api_check_requests_failed_total",
+        "Requests that completed with a 4xx or 5xx status",
+        summary.failed_requests,
+    );
+    counter(
+        &mut out,
+        "api_check_requests_proxied_total",
+        "Requests forwarded to an upstream",
+        summary.proxied_requests,
+    );
+
+    out.push_str("# HELP api_check_responses_total Requests by response status code\n");
+    out.push_str("# TYPE api_check_responses_total counter\n");
+    let mut statuses: Vec<_> = summary.status_distribution.iter().collect();
+    statuses.sort();
+    for (status, count) in statuses {
+        let _ = writeln!(
+            out,
+            "api_check_responses_total{{status=\"{}\"}} {}",
+            status, count
+        );
+    }
+
+    out.push_str("# HELP api_check_request_latency_ms Request latency in milliseconds\n");
+    out.push_str("# TYPE api_check_request_latency_ms summary\n");
+    for (quantile, value) in [
+        ("0.5", summary.p50_latency_ms),
+        ("0.95", summary.p95_latency_ms),
+        ("0.99", summary.p99_latency_ms),
+    ] {
+        let _ = writeln!(
+            out,
+            "api_check_request_latency_ms{{quantile=\"{}\"}} {}",
+            quantile, value
+        );
+    }
+    let _ = writeln!(
+        out,
+        "api_check_request_latency_ms_sum {}",
+        summary.avg_latency_ms * summary.total_requests as f64
+    );
+    let _ = writeln!(
+        out,
+        "api_check_request_latency_ms_count {}",
+        summary.total_requests
+    );
+
+    out
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_exposition_format() {
+        let mut summary = MetricsSummary {
+            total_requests: 3,
+            failed_requests: 1,
+            avg_latency_ms: 10.0,
+            p99_latency_ms: 20.0,
+            ..Default::default()
+        };
+        summary.status_distribution.insert(500, 1);
+        summary.status_distribution.insert(200, 2);
+
+        let text = render(&summary);
+        assert!(
+            text.contains("# TYPE api_check_requests_total counter\napi_check_requests_total 3\n")
+        );
+        assert!(text.contains(
+            "api_check_responses_total{status=\"200\"} 2\napi_check_responses_total{status=\"500\"} 1\n"
+        ));
+        assert!(text.contains("api_check_request_latency_ms{quantile=\"0.99\"} 20\n"));
+        assert!(text.contains("api_check_request_latency_ms_sum 30\n"));
+    }
+}
@@ -0,0 +1,379 @@
+//! SQLite metrics persistence
+//!
+//! With the `sqlite` feature, recorded metrics are written to a SQLite
+//! database so history survives restarts. Writes go over a channel to a
+//! background thread that commits everything queued in one transaction,
+//! keeping file I/O off the request path. On startup the newest
+//! `max_entries` rows are loaded back into memory; the database keeps up to
+//! `max_rows`, pruning older rows, and serves history reads over a second
+//! connection.
+
+#[cfg(feature = "sqlite")]
+mod store {
+    use crate::metrics::{id, RequestMetric};
+    use anyhow::{Context, Result};
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use parking_lot::Mutex;
+    use rusqlite::{params, Connection};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS metrics (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        timestamp TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS metrics_timestamp ON metrics (timestamp)";
+
+    #[derive(Debug)]
+    enum Command {
+        /// Insert a metric, replacing an earlier row with the same ID
+        Upsert(Box<RequestMetric>),
+        /// Delete every row
+        Clear,
+        /// Acknowledge once everything queued before it is committed
+        Flush(Sender<()>),
+    }
+
+    /// Handle feeding metrics to the background SQLite writer
+    #[derive(Debug)]
+    pub struct SqliteStore {
+        sender: Option<Sender<Command>>,
+        writer: Option<JoinHandle<()>>,
+        /// Connection for queries, separate from the writer's
+        reader: Mutex<Connection>,
+    }
+
+    impl SqliteStore {
+        /// Open (or create) the database, returning the store and its newest `max_entries` metrics
+        ///
+        /// The database itself keeps the newest `max_rows` metrics.
+        pub fn open(
+            path: &str,
+            max_entries: usize,
+            max_rows: usize,
+        ) -> Result<(Self, Vec<RequestMetric>)> {
+            let conn = Connection::open(path)
+                .with_context(|| format!("Failed to open metrics database {}", path))?;
+            // WAL lets reads proceed while the writer commits
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            conn.execute_batch(SCHEMA)?;
+            let loaded = load_recent(&conn, max_entries)?;
+
+            // Sequential IDs restart at 1 in every process; continue after the
+            // stored ones so new rows don't replace history
+            let max_sequential: Option<i64> = conn.query_row(
+                "SELECT MAX(CAST(id AS INTEGER)) FROM metrics
+                 WHERE id <> '' AND id NOT GLOB '*[^0-9]*'",
+                [],
+                |row| row.get(0),
+            )?;
+            if let Some(max) = max_sequential {
+                id::seed_sequence(max as u64 + 1);
+            }
+
+            let reader = Connection::open(path)
+                .with_context(|| format!("Failed to open metrics database {}", path))?;
+            reader.busy_timeout(Duration::from_secs(1))?;
+
+            let (sender, receiver) = mpsc::channel();
+            let writer = std::thread::Builder::new()
+                .name("metrics-sqlite".to_string())
+                .spawn(move || run(conn, receiver, max_rows))
+                .context("Failed to spawn SQLite writer thread")?;
+
+            tracing::info!(path = %path, loaded = %loaded.len(), "Opened metrics database");
+            Ok((
+                Self {
+                    sender: Some(sender),
+                    writer: Some(writer),
+                    reader: Mutex::new(reader),
+                },
+                loaded,
+            ))
+        }
+
+        /// Up to `limit` of the newest stored metrics at or after `since`, oldest first
+        ///
+        /// Only sees what the writer has committed.
+        pub fn history(
+            &self,
+            since: Option<DateTime<Utc>>,
+            limit: usize,
+        ) -> Result<Vec<RequestMetric>> {
+            let conn = self.reader.lock();
+            let limit = limit.min(i64::MAX as usize) as i64;
+            let data = |row: &rusqlite::Row| row.get::<_, String>(0);
+            match since {
+                Some(since) => {
+                    let mut stmt = conn.prepare_cached(
+                        "SELECT data FROM (
+                             SELECT seq, data FROM metrics WHERE timestamp >= ?1
+                             ORDER BY seq DESC LIMIT ?2
+                         ) ORDER BY seq",
+                    )?;
+                    let rows = stmt.query_map(params![stored_timestamp(&since), limit], data)?;
+                    parse_rows(rows)
+                }
+                None => {
+                    let mut stmt = conn.prepare_cached(
+                        "SELECT data FROM (
+                             SELECT seq, data FROM metrics ORDER BY seq DESC LIMIT ?1
+                         ) ORDER BY seq",
+                    )?;
+                    let rows = stmt.query_map([limit], data)?;
+                    parse_rows(rows)
+                }
+            }
+        }
+
+        /// Queue a metric to be written (or rewritten, for coalesced repeats)
+        pub fn upsert(&self, metric: &RequestMetric) {
+            self.send(Command::Upsert(Box::new(metric.clone())));
+        }
+
+        /// Queue deletion of every stored metric
+        pub fn clear(&self) {
+            self.send(Command::Clear);
+        }
+
+        /// Wait up to `timeout` for queued writes to be committed
+        ///
+        /// Returns `false` if the writer did not confirm in time.
+        pub fn flush(&self, timeout: Duration) -> bool {
+            let (ack, done) = mpsc::channel();
+            self.send(Command::Flush(ack));
+            done.recv_timeout(timeout).is_ok()
+        }
+
+        fn send(&self, command: Command) {
+            // The writer only stops after an error, which it has already logged
+            if let Some(sender) = &self.sender {
+                let _ = sender.send(command);
+            }
+        }
+    }
+
+    impl Drop for SqliteStore {
+        fn drop(&mut self) {
+            // Closing the channel lets the writer commit what is queued and exit
+            self.sender.take();
+            if let Some(writer) = self.writer.take() {
+                let _ = writer.join();
+            }
+        }
+    }
+
+    /// Fixed-width UTC timestamp, so stored timestamps compare as strings
+    fn stored_timestamp(timestamp: &DateTime<Utc>) -> String {
+        timestamp.to_rfc3339_opts(SecondsFormat::Micros, true)
+    }
+
+    fn load_recent(conn: &Connection, max_entries: usize) -> Result<Vec<RequestMetric>> {
+        let mut stmt = conn.prepare(
+            "SELECT data FROM (SELECT seq, data FROM metrics ORDER BY seq DESC LIMIT ?1)
+             ORDER BY seq",
+        )?;
+        let rows = stmt.query_map([max_entries as i64], |row| row.get::<_, String>(0))?;
+        parse_rows(rows)
+    }
+
+    fn parse_rows(
+        rows: impl Iterator<Item = rusqlite::Result<String>>,
+    ) -> Result<Vec<RequestMetric>> {
+        let mut metrics = Vec::new();
+        for data in rows {
+            match serde_json::from_str(&data?) {
+                Ok(metric) => metrics.push(metric),
+                Err(e) => tracing::warn!(error = %e, "Skipping unreadable stored metric"),
+            }
+        }
+        Ok(metrics)
+    }
+
+    fn run(mut conn: Connection, receiver: Receiver<Command>, max_rows: usize) {
+        while let Ok(command) = receiver.recv() {
+            // Commit everything already queued in one transaction
+            let (acks, batch): (Vec<Command>, Vec<Command>) = std::iter::once(command)
+                .chain(receiver.try_iter())
+                .partition(|command| matches!(command, Command::Flush(_)));
+            if let Err(e) = write_batch(&mut conn, batch, max_rows) {
+                tracing::error!(error = %e, "SQLite metrics writer stopped");
+                return;
+            }
+            for ack in acks {
+                if let Command::Flush(ack) = ack {
+                    let _ = ack.send(());
+                }
+            }
+        }
+    }
+
+    fn write_batch(conn: &mut Connection, batch: Vec<Command>, max_rows: usize) -> Result<()> {
+        let tx = conn.transaction()?;
+        {
+            let mut upsert = tx.prepare_cached(
+                "INSERT OR REPLACE INTO metrics (id, timestamp, data) VALUES (?1, ?2, ?3)",
+            )?;
+            for command in batch {
+                match command {
+                    Command::Upsert(metric) => {
+                        upsert.execute(params![
+                            metric.id,
+                            stored_timestamp(&metric.timestamp),
+                            serde_json::to_string(&metric)?
+                        ])?;
+                    }
+                    Command::Clear => {
+                        tx.execute("DELETE FROM metrics", [])?;
+                    }
+                    Command::Flush(_) => {}
+                }
+            }
+        }
+        tx.execute(
+            "DELETE FROM metrics WHERE seq <= (SELECT MAX(seq) FROM metrics) - ?1",
+            [max_rows as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+
+/// Placeholder used when built without the `sqlite` feature
+#[cfg(not(feature = "sqlite"))]
+#[derive(Debug)]
+pub struct SqliteStore {
+    _private: (),
+}
+
+#[cfg(not(feature = "sqlite"))]
+impl SqliteStore {
+    /// Always fails: SQLite support is not compiled in
+    pub fn open(
+        path: &str,
+        _max_entries: usize,
+        _max_rows: usize,
+    ) -> anyhow::Result<(Self, Vec<super::RequestMetric>)> {
+        anyhow::bail!(
+            "Cannot open metrics database {}: api-check was built without the `sqlite` feature",
+            path
+        )
+    }
+
+    /// Empty placeholder
+    pub fn history(
+        &self,
+        _since: Option<chrono::DateTime<chrono::Utc>>,
+        _limit: usize,
+    ) -> anyhow::Result<Vec<super::RequestMetric>> {
+        Ok(Vec::new())
+    }
+
+    /// No-op placeholder
+    pub fn upsert(&self, _metric: &super::RequestMetric) {}
+
+    /// No-op placeholder
+    pub fn clear(&self) {}
+
+    /// No-op placeholder
+    pub fn flush(&self, _timeout: std::time::Duration) -> bool {
+        true
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use crate::config::IdFormat;
+    use crate::metrics::id::generate_id;
+    use crate::metrics::{MetricsCollector, RequestMetric};
+
+    #[test]
+    fn test_history_survives_reopen() {
+        let path = std::env::temp_dir().join(format!("api-check-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+
+        {
+            let collector = MetricsCollector::with_sqlite(path, 3).unwrap();
+            for i in 0..5 {
+                collector.record(RequestMetric::new("GET".to_string(), format!("/{}", i)));
+            }
+        }
+
+        let reopened = MetricsCollector::with_sqlite(path, 3).unwrap();
+        let paths: Vec<String> = reopened.get_all().into_iter().map(|m| m.path).collect();
+        assert_eq!(paths, vec!["/2", "/3", "/4"]);
+
+        drop(reopened);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[test]
+    fn test_sequential_ids_continue_after_stored() {
+        let path = std::env::temp_dir().join(format!("api-check-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+
+        {
+            let collector = MetricsCollector::with_sqlite(path, 10).unwrap();
+            let mut metric = RequestMetric::new("GET".to_string(), "/".to_string());
+            metric.id = format!("{:020}", 1_000_000_000_000u64);
+            collector.record(metric);
+        }
+
+        // Reopening seeds the sequence so new rows don't replace stored ones
+        let reopened = MetricsCollector::with_sqlite(path, 10).unwrap();
+        let next: u64 = generate_id(IdFormat::Sequential).parse().unwrap();
+        assert!(next > 1_000_000_000_000);
+
+        drop(reopened);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[test]
+    fn test_history_reads_database() {
+        let path = std::env::temp_dir().join(format!("api-check-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+
+        // Only two metrics fit in memory, but the database keeps ten
+        let collector = MetricsCollector::with_sqlite_rows(path, 2, 10).unwrap();
+        for i in 0..5 {
+            collector
+                .record(RequestMetric::new("GET".to_string(), format!("/{}", i)).with_status(200));
+        }
+        assert!(collector.flush(std::time::Duration::from_secs(5)));
+        assert_eq!(collector.count(), 2);
+
+        let paths = |metrics: Vec<RequestMetric>| -> Vec<String> {
+            metrics.into_iter().map(|m| m.path).collect()
+        };
+        assert_eq!(
+            paths(collector.history(None, 100)),
+            vec!["/0", "/1", "/2", "/3", "/4"]
+        );
+        assert_eq!(paths(collector.history(None, 3)), vec!["/2", "/3", "/4"]);
+        let since = chrono::Utc::now() - chrono::Duration::seconds(60);
+        assert_eq!(collector.history(Some(since), 100).len(), 5);
+        assert!(collector.history(Some(chrono::Utc::now()), 100).is_empty());
+
+        // Summaries serve the in-memory window
+        assert_eq!(collector.get_summary().total_requests, 2);
+
+        collector.clear();
+        assert!(collector.flush(std::time::Duration::from_secs(5)));
+        assert!(collector.history(None, 100).is_empty());
+
+        drop(collector);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+}
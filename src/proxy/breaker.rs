@@ -0,0 +1,144 @@
+//! Per-upstream circuit breaker
+//!
+//! After `failure_threshold` consecutive transport failures within
+//! `window_secs`, an upstream's circuit opens and requests to it are answered
+//! with 503 straight away. Once `cooldown_secs` pass, the circuit is
+//! half-open: one probe request goes through, closing the circuit if it
+//! succeeds and reopening it for another cooldown if it fails.
+
+use crate::config::CircuitBreakerConfig;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Failure tracking for one upstream
+#[derive(Debug, Default)]
+struct Circuit {
+    /// Consecutive failures since `first_failure`
+    failures: u32,
+    first_failure: Option<Instant>,
+    /// Set while the circuit is open (or half-open, once this has passed)
+    open_until: Option<Instant>,
+    /// When the half-open probe was let through
+    probe_started: Option<Instant>,
+}
+
+/// Circuit breakers for every upstream, keyed by upstream base URL
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+impl CircuitBreaker {
+    /// Create a breaker with every circuit closed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a request may be sent to `upstream`
+    ///
+    /// While half-open only one probe is allowed at a time; a probe that never
+    /// reports back (say, the client went away) is replaced after a cooldown.
+    pub fn allow(&self, upstream: &str, config: &CircuitBreakerConfig) -> bool {
+        let mut circuits = self.circuits.lock();
+        let Some(circuit) = circuits.get_mut(upstream) else {
+            return true;
+        };
+        let Some(open_until) = circuit.open_until else {
+            return true;
+        };
+
+        let now = Instant::now();
+        let cooldown = Duration::from_secs(config.cooldown_secs);
+        if now < open_until || circuit.probe_started.is_some_and(|at| now < at + cooldown) {
+            return false;
+        }
+        circuit.probe_started = Some(now);
+        true
+    }
+
+    /// Record the outcome of a request sent to `upstream`
+    pub fn record(&self, upstream: &str, config: &CircuitBreakerConfig, success: bool) {
+        let mut circuits = self.circuits.lock();
+        if success {
+            if circuits
+                .remove(upstream)
+                .is_some_and(|c| c.open_until.is_some())
+            {
+                tracing::info!(upstream = %upstream, "Circuit closed");
+            }
+            return;
+        }
+
+        let now = Instant::now();
+        let circuit = circuits.entry(upstream.to_string()).or_default();
+        if circuit.open_until.is_some() {
+            // A failed probe (or a request that was already in flight) reopens it
+            circuit.open_until = Some(now + Duration::from_secs(config.cooldown_secs));
+            circuit.probe_started = None;
+            return;
+        }
+
+        let window = Duration::from_secs(config.window_secs);
+        if circuit
+            .first_failure
+            .is_none_or(|first| now - first > window)
+        {
+            circuit.failures = 0;
+            circuit.first_failure = Some(now);
+        }
+        circuit.failures += 1;
+        if circuit.failures >= config.failure_threshold.max(1) {
+            circuit.open_until = Some(now + Duration::from_secs(config.cooldown_secs));
+            tracing::warn!(
+                upstream = %upstream,
+                failures = %circuit.failures,
+                cooldown_secs = %config.cooldown_secs,
+                "Circuit opened"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_and_recovers() {
+        let breaker = CircuitBreaker::new();
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            window_secs: 60,
+            cooldown_secs: 60,
+        };
+        let upstream = "http://down";
+        let end_cooldown = || {
+            breaker
+                .circuits
+                .lock()
+                .get_mut(upstream)
+                .unwrap()
+                .open_until = Some(Instant::now());
+        };
+
+        breaker.record(upstream, &config, false);
+        assert!(breaker.allow(upstream, &config));
+        breaker.record(upstream, &config, false);
+        assert!(!breaker.allow(upstream, &config));
+        assert!(breaker.allow("http://other", &config));
+
+        // Half-open: a single probe, and a failed probe reopens the circuit
+        end_cooldown();
+        assert!(breaker.allow(upstream, &config));
+        assert!(!breaker.allow(upstream, &config));
+        breaker.record(upstream, &config, false);
+        assert!(!breaker.allow(upstream, &config));
+
+        end_cooldown();
+        assert!(breaker.allow(upstream, &config));
+        breaker.record(upstream, &config, true);
+        assert!(breaker.allow(upstream, &config));
+        assert!(breaker.allow(upstream, &config));
+    }
+}
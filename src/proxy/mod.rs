@@ -2,18 +2,64 @@
 //!
 //! Forwards requests to a target server and records response status codes.
 
-use crate::config::SharedConfig;
-use crate::metrics::{RequestMetric, SharedMetrics};
+use crate::config::{
+    apply_dns_overrides, AppConfig, HttpVersion, ProxyConfig, ProxyRoute, SharedConfig,
+};
+use crate::metrics::{MetricSource, RequestMetric, SharedMetrics};
+use crate::server::is_length_limit_error;
+use crate::telemetry;
 use anyhow::Result;
 use axum::{
-    body::Body,
-    extract::State,
-    http::{Request, Response, StatusCode},
+    body::{Body, BodyDataStream, Bytes, HttpBody},
+    extract::{ConnectInfo, State},
+    http::{
+        header::{CONTENT_LENGTH, HOST},
+        HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode, Uri,
+    },
     response::IntoResponse,
 };
+use futures::{Stream, StreamExt};
 use reqwest::Client;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{ready, Context, Poll};
 use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::Instrument;
+
+pub mod breaker;
+pub mod plugin;
+pub mod throttle;
+pub mod upstream;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+pub use breaker::CircuitBreaker;
+pub use plugin::WasmPlugin;
+pub use upstream::{SharedUpstreamPool, UpstreamPool};
+
+use plugin::{PluginContext, PluginOutcome};
+use throttle::{paced_stream, throttled_stream, Throttle};
+
+/// Header used to override the proxy target for a single request
+pub const PROXY_TARGET_HEADER: &str = "x-proxy-target";
+
+/// Standard headers describing the original request to the upstream
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// Connection-level headers that are never forwarded in either direction
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
 
 /// Proxy state containing shared configuration and HTTP client
 #[derive(Clone)]
@@ -21,22 +67,69 @@ pub struct ProxyState {
     pub config: SharedConfig,
     pub metrics: SharedMetrics,
     pub client: Client,
+    pub upstreams: SharedUpstreamPool,
+    pub plugin: Option<Arc<WasmPlugin>>,
+    /// Permits bounding in-flight upstream requests
+    pub upstream_permits: Option<Arc<Semaphore>>,
+    /// Per-upstream circuits, used when `proxy.circuit_breaker` is set
+    pub breaker: Arc<CircuitBreaker>,
 }
 
 impl ProxyState {
     /// Create a new proxy state
     pub fn new(config: SharedConfig, metrics: SharedMetrics) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
+        let client = build_client(&config.get()).expect("Failed to create HTTP client");
+
+        // Plugins are loaded once; changing plugin_path requires a restart
+        let proxy_config = config.get().proxy;
+        let plugin = proxy_config.plugin_path.and_then(|path| {
+            match WasmPlugin::load(&path, proxy_config.plugin_fuel) {
+                Ok(plugin) => {
+                    tracing::info!(path = %path, "Loaded proxy plugin");
+                    Some(Arc::new(plugin))
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, path = %path, "Failed to load proxy plugin");
+                    None
+                }
+            }
+        });
+
+        // Like plugins, the concurrency limit is fixed at startup
+        let upstream_permits = config
+            .get()
+            .proxy
+            .max_concurrent_upstream
+            .map(|limit| Arc::new(Semaphore::new(limit.max(1))));
 
         Self {
             config,
             metrics,
             client,
+            upstreams: Arc::new(UpstreamPool::new()),
+            plugin,
+            upstream_permits,
+            breaker: Arc::new(CircuitBreaker::new()),
         }
     }
+
+    /// Share an existing upstream pool
+    pub fn with_upstreams(mut self, upstreams: SharedUpstreamPool) -> Self {
+        self.upstreams = upstreams;
+        self
+    }
+}
+
+/// Build the upstream client, speaking the configured `proxy.http_version`
+pub fn build_client(config: &AppConfig) -> reqwest::Result<Client> {
+    let builder =
+        apply_dns_overrides(Client::builder(), config).timeout(std::time::Duration::from_secs(30));
+    let builder = match config.proxy.http_version {
+        HttpVersion::Auto => builder,
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::Http2 => builder.http2_prior_knowledge(),
+    };
+    builder.build()
 }
 
 /// Proxy handler that forwards requests to the target server
@@ -55,127 +148,726 @@ pub async fn proxy_handler(
         let metric = RequestMetric::new(method, path)
             .with_status(200)
             .with_latency(start.elapsed().as_secs_f64() * 1000.0)
+            .with_source(MetricSource::Proxy)
             .with_proxied(false);
         state.metrics.record(metric);
 
         return (StatusCode::OK, "Proxy mode disabled").into_response();
     }
 
-    // Get target URL
-    let target = match &config.proxy.target {
-        Some(t) => t.clone(),
-        None => {
-            let metric = RequestMetric::new(method, path)
-                .with_status(502)
-                .with_latency(start.elapsed().as_secs_f64() * 1000.0)
-                .with_proxied(false);
-            state.metrics.record(metric);
+    let (target, proxied_url) =
+        match select_upstream(&config.proxy, req.uri(), req.headers(), &state.upstreams) {
+            Ok(Some(selected)) => selected,
+            Ok(None) => {
+                let metric = RequestMetric::new(method, path)
+                    .with_status(502)
+                    .with_latency(start.elapsed().as_secs_f64() * 1000.0)
+                    .with_source(MetricSource::Proxy)
+                    .with_proxied(false);
+                state.metrics.record(metric);
 
-            return (StatusCode::BAD_GATEWAY, "No proxy target configured").into_response();
-        }
-    };
+                return (StatusCode::BAD_GATEWAY, "No proxy target configured").into_response();
+            }
+            Err((status, message)) => {
+                let metric = RequestMetric::new(method, path)
+                    .with_status(status.as_u16())
+                    .with_latency(start.elapsed().as_secs_f64() * 1000.0)
+                    .with_source(MetricSource::Proxy)
+                    .with_proxied(false);
+                state.metrics.record(metric);
 
-    // Build the proxied URL
-    let uri = req.uri();
-    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+                tracing::warn!(error = %message, "Could not resolve proxy target");
+                return (status, message).into_response();
+            }
+        };
 
-    let proxied_url = format!("{}{}", target.trim_end_matches('/'), path_and_query);
+    // Fail fast while the upstream's circuit is open
+    let breaker = config.proxy.circuit_breaker;
+    if breaker.is_some_and(|breaker| !state.breaker.allow(&target, &breaker)) {
+        let metric = RequestMetric::new(method, path)
+            .with_status(503)
+            .with_latency(start.elapsed().as_secs_f64() * 1000.0)
+            .with_source(MetricSource::Proxy)
+            .with_proxied(false)
+            .with_upstream(target)
+            .with_circuit_open(true);
+        state.metrics.record(metric);
+
+        return (StatusCode::SERVICE_UNAVAILABLE, "Upstream circuit open").into_response();
+    }
 
     // Forward the request
-    let result = forward_request(&state.client, req, &proxied_url).await;
+    let span = tracing::info_span!(
+        "proxy_upstream",
+        http.method = %method,
+        http.path = %path,
+        http.url = %proxied_url,
+        upstream = %target,
+        http.status_code = tracing::field::Empty,
+        latency_ms = tracing::field::Empty,
+    );
+    // If the client disconnects, axum drops this future, which drops the
+    // upstream request future and cancels the upstream connection. The guard
+    // records the abandoned request when that happens.
+    let mut cancel_guard = CancelGuard {
+        metrics: state.metrics.clone(),
+        method: method.clone(),
+        path: path.clone(),
+        upstream: target.clone(),
+        start,
+        permit: None,
+        armed: true,
+    };
+
+    // Wait for an upstream permit when concurrency is limited; the guard holds
+    // it until the response body has been sent on
+    if let Some(permits) = &state.upstream_permits {
+        cancel_guard.permit = permits.clone().acquire_owned().await.ok();
+    }
+    let queue_wait_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let upstream_start = Instant::now();
+
+    // Present when served with `into_make_service_with_connect_info`
+    let client_addr = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
 
-    let latency = start.elapsed().as_secs_f64() * 1000.0;
+    let result = forward_request(
+        &state.client,
+        req,
+        client_addr,
+        &proxied_url,
+        &config.proxy,
+        state.plugin.as_ref(),
+    )
+    .instrument(span.clone())
+    .await;
+
+    // An oversized request says nothing about the upstream's health
+    let request_too_large = result.as_ref().err().is_some_and(|e| {
+        matches!(
+            e.downcast_ref::<BodyTooLarge>(),
+            Some(BodyTooLarge::Request(_))
+        )
+    });
+    if let (Some(breaker), false) = (&breaker, request_too_large) {
+        state.breaker.record(&target, breaker, result.is_ok());
+    }
 
     match result {
-        Ok(response) => {
-            let status = response.status().as_u16();
+        Ok(forwarded) => {
+            let (mut parts, body) = forwarded.response.into_parts();
+            span.record("http.status_code", parts.status.as_u16());
+
+            // The request is recorded once the body has been sent on, so the
+            // metric covers the whole transfer and disconnects mid-body count
+            let mut metered = MeteredBody {
+                inner: None,
+                guard: Some(cancel_guard),
+                status: parts.status.as_u16(),
+                queue_wait_ms,
+                upstream_start,
+                request_bytes: forwarded.request_bytes,
+                response_bytes: 0,
+                span,
+                target_url: proxied_url,
+            };
+
+            // Hyper never polls the body of HEAD, 204 and 304 responses
+            let bodyless = method == "HEAD" || matches!(parts.status.as_u16(), 204 | 304);
+            let body = if bodyless || HttpBody::is_end_stream(&body) {
+                metered.finish();
+                body
+            } else {
+                // A wrapped buffered body no longer reports its size, so keep the framing
+                if let Some(len) = HttpBody::size_hint(&body).exact() {
+                    parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+                }
+                metered.inner = Some(body.into_data_stream());
+                Body::from_stream(metered)
+            };
+
+            let mut response = Response::from_parts(parts, body);
+            response.extensions_mut().insert(Proxied);
+            response
+        }
+        Err(e) if request_too_large => {
+            cancel_guard.disarm();
+            let latency = start.elapsed().as_secs_f64() * 1000.0;
+            span.record("latency_ms", latency);
+            tracing::warn!(error = %e, target = %proxied_url, "Rejected proxy request");
+
             let metric = RequestMetric::new(method, path)
-                .with_status(status)
+                .with_status(413)
                 .with_latency(latency)
-                .with_proxied(true);
+                .with_source(MetricSource::Proxy)
+                .with_proxied(false)
+                .with_upstream(target);
             state.metrics.record(metric);
 
-            tracing::info!(
-                target = %proxied_url,
-                status = %status,
-                latency_ms = %latency,
-                "Proxied request"
-            );
-
-            response.into_response()
+            (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()).into_response()
         }
         Err(e) => {
+            cancel_guard.disarm();
+            let upstream_ms = upstream_start.elapsed().as_secs_f64() * 1000.0;
+            let latency = start.elapsed().as_secs_f64() * 1000.0;
+            span.record("latency_ms", latency);
             tracing::error!(error = %e, target = %proxied_url, "Proxy error");
 
             let metric = RequestMetric::new(method, path)
                 .with_status(502)
                 .with_latency(latency)
-                .with_proxied(true);
+                .with_source(MetricSource::Proxy)
+                .with_proxied(true)
+                .with_upstream(target)
+                .with_queue_split(queue_wait_ms, upstream_ms);
             state.metrics.record(metric);
 
-            (StatusCode::BAD_GATEWAY, format!("Proxy error: {}", e)).into_response()
+            let mut response =
+                (StatusCode::BAD_GATEWAY, format!("Proxy error: {}", e)).into_response();
+            response.extensions_mut().insert(Proxied);
+            response
+        }
+    }
+}
+
+/// Response extension marking a request that was forwarded to an upstream
+#[derive(Debug, Clone, Copy)]
+pub struct Proxied;
+
+/// Pick the upstream for a request, returning its base URL and the full URL to forward to
+///
+/// Path-prefix routes apply unless the target is overridden per request with
+/// `X-Proxy-Target` (when allowed); otherwise `target`/`targets` are used.
+pub fn select_upstream(
+    config: &ProxyConfig,
+    uri: &Uri,
+    headers: &HeaderMap,
+    upstreams: &UpstreamPool,
+) -> std::result::Result<Option<(String, String)>, (StatusCode, String)> {
+    let overridden = config.allow_target_header && headers.contains_key(PROXY_TARGET_HEADER);
+    let route = match_route(&config.routes, uri.path()).filter(|_| !overridden);
+
+    let target = match route {
+        Some(route) => route.target.clone(),
+        None => match resolve_target(config, headers, upstreams)? {
+            Some(target) => target,
+            None => return Ok(None),
+        },
+    };
+
+    let forwarded_path = match route {
+        Some(route) if route.strip_prefix => strip_route_prefix(&route.prefix, uri.path()),
+        _ => uri.path(),
+    };
+    let query = uri.query().map(|q| format!("?{}", q)).unwrap_or_default();
+    let proxied_url = format!(
+        "{}{}{}",
+        target.trim_end_matches('/'),
+        forwarded_path,
+        query
+    );
+    tracing::debug!(
+        upstream = %target,
+        route = ?route.map(|r| &r.prefix),
+        url = %proxied_url,
+        "Selected upstream"
+    );
+
+    Ok(Some((target, proxied_url)))
+}
+
+/// Status recorded for requests abandoned by the client (nginx convention)
+pub const CLIENT_CLOSED_REQUEST: u16 = 499;
+
+/// Records a cancelled metric if dropped before the response has been sent on
+///
+/// Also holds the upstream permit, so `max_concurrent_upstream` bounds body
+/// transfers as well as waits for response headers.
+struct CancelGuard {
+    metrics: SharedMetrics,
+    method: String,
+    path: String,
+    upstream: String,
+    start: Instant,
+    permit: Option<OwnedSemaphorePermit>,
+    armed: bool,
+}
+
+impl CancelGuard {
+    /// The request completed; release the permit without recording a cancellation
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        let latency = self.start.elapsed().as_secs_f64() * 1000.0;
+        let metric = RequestMetric::new(
+            std::mem::take(&mut self.method),
+            std::mem::take(&mut self.path),
+        )
+        .with_status(CLIENT_CLOSED_REQUEST)
+        .with_latency(latency)
+        .with_source(MetricSource::Proxy)
+        .with_proxied(true)
+        .with_upstream(std::mem::take(&mut self.upstream))
+        .with_client_cancelled(true);
+        self.metrics.record(metric);
+
+        tracing::info!(latency_ms = %latency, "Client disconnected, upstream request cancelled");
+    }
+}
+
+/// Response body that records the proxied request once it has been sent on
+///
+/// Timings and byte counts cover the whole transfer, including downstream
+/// throttling. If the client disconnects mid-body, hyper drops the body and the
+/// guard records the cancellation.
+struct MeteredBody {
+    inner: Option<BodyDataStream>,
+    guard: Option<CancelGuard>,
+    status: u16,
+    queue_wait_ms: f64,
+    upstream_start: Instant,
+    request_bytes: usize,
+    response_bytes: usize,
+    span: tracing::Span,
+    target_url: String,
+}
+
+impl MeteredBody {
+    /// Record the completed request and release the upstream permit
+    fn finish(&mut self) {
+        let Some(mut guard) = self.guard.take() else {
+            return;
+        };
+
+        let latency = guard.start.elapsed().as_secs_f64() * 1000.0;
+        let upstream_ms = self.upstream_start.elapsed().as_secs_f64() * 1000.0;
+        let bytes_transferred = self.request_bytes + self.response_bytes;
+        let mut metric = RequestMetric::new(
+            std::mem::take(&mut guard.method),
+            std::mem::take(&mut guard.path),
+        )
+        .with_status(self.status)
+        .with_latency(latency)
+        .with_source(MetricSource::Proxy)
+        .with_proxied(true)
+        .with_upstream(std::mem::take(&mut guard.upstream))
+        .with_queue_split(self.queue_wait_ms, upstream_ms)
+        .with_bytes(bytes_transferred as u64)
+        .with_body_sizes(
+            Some(self.request_bytes as u64),
+            Some(self.response_bytes as u64),
+        );
+        if latency > 0.0 && bytes_transferred > 0 {
+            metric = metric.with_throughput(bytes_transferred as f64 / (latency / 1000.0));
+        }
+        guard.metrics.record(metric);
+        guard.disarm();
+
+        self.span.record("latency_ms", latency);
+        tracing::info!(
+            target = %self.target_url,
+            status = %self.status,
+            latency_ms = %latency,
+            "Proxied request"
+        );
+    }
+}
+
+impl Stream for MeteredBody {
+    type Item = std::result::Result<Bytes, axum::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let item = ready!(inner.poll_next_unpin(cx));
+        match &item {
+            Some(Ok(chunk)) => self.response_bytes += chunk.len(),
+            // The body ended, or failed upstream and the response is cut short
+            Some(Err(_)) | None => self.finish(),
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Whether `path` falls under `prefix`, on a path-segment boundary
+fn route_matches(prefix: &str, path: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Find the route with the longest prefix matching `path`
+pub fn match_route<'a>(routes: &'a [ProxyRoute], path: &str) -> Option<&'a ProxyRoute> {
+    routes
+        .iter()
+        .filter(|route| route_matches(&route.prefix, path))
+        .max_by_key(|route| route.prefix.trim_end_matches('/').len())
+}
+
+/// Remove a matched route prefix from `path`, keeping it absolute
+fn strip_route_prefix<'a>(prefix: &str, path: &'a str) -> &'a str {
+    match path.strip_prefix(prefix.trim_end_matches('/')) {
+        Some("") | None => "/",
+        Some(rest) => rest,
+    }
+}
+
+/// Determine the upstream for a request
+///
+/// Returns the `X-Proxy-Target` header value when overrides are enabled and
+/// the target passes validation, otherwise the next healthy pooled upstream,
+/// otherwise the configured target.
+pub fn resolve_target(
+    config: &ProxyConfig,
+    headers: &axum::http::HeaderMap,
+    upstreams: &UpstreamPool,
+) -> std::result::Result<Option<String>, (StatusCode, String)> {
+    let header = headers
+        .get(PROXY_TARGET_HEADER)
+        .filter(|_| config.allow_target_header);
+
+    let Some(value) = header else {
+        if config.targets.is_empty() {
+            return Ok(config.target.clone());
+        }
+        return upstreams.next_healthy(&config.targets).map(Some).ok_or((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No healthy upstreams".to_string(),
+        ));
+    };
+
+    let value = value.to_str().map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Invalid X-Proxy-Target header".to_string(),
+        )
+    })?;
+
+    let url = reqwest::Url::parse(value).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid X-Proxy-Target URL: {}", e),
+        )
+    })?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unsupported X-Proxy-Target scheme: {}", url.scheme()),
+        ));
+    }
+
+    let host = url.host_str().unwrap_or_default();
+    let host_port = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+
+    let allowed = config
+        .target_allowlist
+        .iter()
+        .any(|entry| entry.eq_ignore_ascii_case(host) || entry.eq_ignore_ascii_case(&host_port));
+
+    if !allowed {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("X-Proxy-Target host not allowed: {}", host_port),
+        ));
+    }
+
+    Ok(Some(value.to_string()))
+}
+
+/// Drop hop-by-hop headers, including any named in `Connection`
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    let listed: Vec<HeaderName> = headers
+        .get_all(axum::http::header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in listed {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(*name);
+    }
+}
+
+/// Remove `remove` headers (case-insensitive), then set each `add` header,
+/// replacing any existing value
+///
+/// Invalid names or values are skipped; `AppConfig::validate` reports them at load.
+fn apply_header_rules(headers: &mut HeaderMap, remove: &[String], add: &[(String, String)]) {
+    for name in remove {
+        if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+            headers.remove(name);
+        }
+    }
+    for (name, value) in add {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// Append the client IP to `X-Forwarded-For` (keeping any existing chain) and
+/// set `X-Forwarded-Proto` and `X-Forwarded-Host` from the incoming request
+fn add_forwarded_headers(headers: &mut HeaderMap, client_ip: Option<IpAddr>, proto: &str) {
+    if let Some(ip) = client_ip {
+        let mut chain: Vec<String> = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .map(str::to_string)
+            .collect();
+        chain.push(ip.to_string());
+        if let Ok(value) = HeaderValue::from_str(&chain.join(", ")) {
+            headers.insert(X_FORWARDED_FOR, value);
         }
     }
+    if let Ok(value) = HeaderValue::from_str(proto) {
+        headers.insert(X_FORWARDED_PROTO, value);
+    }
+    if let Some(host) = headers.get(HOST).cloned() {
+        headers.insert(X_FORWARDED_HOST, host);
+    }
+}
+
+/// A body went over `proxy.max_body_bytes`
+#[derive(Debug, thiserror::Error)]
+enum BodyTooLarge {
+    #[error("Request body exceeds {0} bytes")]
+    Request(usize),
+    #[error("Upstream response body exceeds {0} bytes")]
+    Response(usize),
+}
+
+/// Response from the upstream along with the request body bytes sent to it
+struct Forwarded {
+    response: Response<Body>,
+    /// Request body bytes sent upstream
+    request_bytes: usize,
 }
 
 /// Forward a request to the target URL
 async fn forward_request(
     client: &Client,
     req: Request<Body>,
+    client_addr: Option<SocketAddr>,
     target_url: &str,
-) -> Result<Response<Body>> {
+    config: &ProxyConfig,
+    plugin: Option<&Arc<WasmPlugin>>,
+) -> Result<Forwarded> {
     let method = req.method().clone();
-    let headers = req.headers().clone();
+    let mut headers = req.headers().clone();
+    strip_hop_by_hop(&mut headers);
+    if config.add_forwarded_headers {
+        let proto = req.uri().scheme_str().unwrap_or("http");
+        add_forwarded_headers(&mut headers, client_addr.map(|addr| addr.ip()), proto);
+    }
 
-    // Read the request body with a reasonable size limit (10MB max)
-    const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
-    let body_bytes = axum::body::to_bytes(req.into_body(), MAX_BODY_SIZE).await?;
+    let limit = config.max_body_bytes;
+    let mut body_bytes = match axum::body::to_bytes(req.into_body(), limit).await {
+        Ok(body) => body,
+        Err(e) if is_length_limit_error(&e) => return Err(BodyTooLarge::Request(limit).into()),
+        Err(e) => return Err(e.into()),
+    };
+
+    // Let the plugin rewrite or short-circuit the request
+    if let Some(plugin) = plugin {
+        let ctx = PluginContext {
+            headers,
+            body: body_bytes.to_vec(),
+            status: 0,
+        };
+        let (outcome, ctx) = plugin::run_blocking(plugin, ctx, WasmPlugin::on_request).await?;
+        if let PluginOutcome::Respond(status) = outcome {
+            return Ok(Forwarded {
+                response: build_response(status, &ctx.headers, ctx.body.into(), target_url),
+                request_bytes: 0,
+            });
+        }
+        headers = ctx.headers;
+        body_bytes = ctx.body.into();
+    }
 
     // Build the forwarded request
-    let mut builder = client.request(
-        method.to_string().parse().unwrap_or(reqwest::Method::GET),
-        target_url,
-    );
+    let mut builder = client
+        .request(
+            method.to_string().parse().unwrap_or(reqwest::Method::GET),
+            target_url,
+        )
+        .timeout(std::time::Duration::from_secs(config.timeout_secs));
 
-    // Copy headers (excluding host)
+    // Copy headers (excluding host and the target override), applying the configured rules
+    apply_header_rules(
+        &mut headers,
+        &config.request_headers_remove,
+        &config.request_headers_add,
+    );
+    // Values are copied as raw bytes so non-ASCII headers survive
     for (key, value) in headers.iter() {
-        if key != "host" {
-            if let Ok(v) = value.to_str() {
-                builder = builder.header(key.as_str(), v);
-            }
+        if key != "host" && key != PROXY_TARGET_HEADER {
+            builder = builder.header(key, value.clone());
         }
     }
 
-    // Set body if present
+    // Propagate our trace context to the upstream, replacing any incoming traceparent
+    let mut trace_headers = axum::http::HeaderMap::new();
+    telemetry::inject_context(&mut trace_headers);
+    builder = builder.headers(trace_headers);
+
+    // Set body if present, paced when an upstream bandwidth limit is set
+    let request_bytes = body_bytes.len();
     if !body_bytes.is_empty() {
-        builder = builder.body(body_bytes.to_vec());
+        builder = match config.max_upstream_bytes_per_sec {
+            Some(limit) => builder.body(reqwest::Body::wrap_stream(paced_stream(
+                body_bytes,
+                Throttle::new(limit),
+            ))),
+            None => builder.body(body_bytes.to_vec()),
+        };
     }
 
     // Send the request
-    let response = builder.send().await?;
-
-    // Convert response
+    let mut response = builder.send().await?;
     let status = response.status();
-    let headers = response.headers().clone();
-    let body_bytes = response.bytes().await?;
+    let mut headers = response.headers().clone();
+    strip_hop_by_hop(&mut headers);
+    apply_header_rules(
+        &mut headers,
+        &config.response_headers_remove,
+        &config.response_headers_add,
+    );
+
+    if response
+        .content_length()
+        .is_some_and(|len| len > limit as u64)
+    {
+        return Err(BodyTooLarge::Response(limit).into());
+    }
 
-    let mut response_builder = Response::builder().status(status.as_u16());
+    // Plugins rewrite whole bodies, so only then is the response buffered
+    if let Some(plugin) = plugin {
+        let mut throttle = config.max_downstream_bytes_per_sec.map(Throttle::new);
+        let mut body_bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body_bytes.len() + chunk.len() > limit {
+                return Err(BodyTooLarge::Response(limit).into());
+            }
+            if let Some(throttle) = &mut throttle {
+                throttle.consume(chunk.len()).await;
+            }
+            body_bytes.extend_from_slice(&chunk);
+        }
+        let ctx = PluginContext {
+            headers,
+            body: body_bytes,
+            status: status.as_u16(),
+        };
+        let ((), ctx) = plugin::run_blocking(plugin, ctx, WasmPlugin::on_response).await?;
+        return Ok(Forwarded {
+            response: build_response(ctx.status, &ctx.headers, ctx.body.into(), target_url),
+            request_bytes,
+        });
+    }
 
+    // Stream the body through, paced when a downstream bandwidth limit is set.
+    // Bodies of unknown length are cut off once they pass the limit.
+    let stream = limited_stream(response.bytes_stream(), limit);
+    let body = match config.max_downstream_bytes_per_sec {
+        Some(rate) => Body::from_stream(throttled_stream(stream, Throttle::new(rate))),
+        None => Body::from_stream(stream),
+    };
+
+    Ok(Forwarded {
+        response: build_response(status.as_u16(), &headers, body, target_url),
+        request_bytes,
+    })
+}
+
+/// Pass a response body through, failing the stream once it exceeds `limit` bytes
+fn limited_stream(
+    stream: impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static,
+    limit: usize,
+) -> impl Stream<Item = Result<Bytes>> + Send + 'static {
+    let mut seen = 0usize;
+    stream.map(move |chunk| {
+        let chunk = chunk?;
+        seen += chunk.len();
+        if seen > limit {
+            tracing::warn!(limit = %limit, "Upstream response body too large, cutting it off");
+            return Err(BodyTooLarge::Response(limit).into());
+        }
+        Ok(chunk)
+    })
+}
+
+/// Build the downstream response, skipping headers that hyper would reject
+///
+/// `headers` must already have been through `strip_hop_by_hop`, so hyper frames
+/// the body itself. The upstream `Content-Length` is kept for streamed bodies,
+/// but not for buffered ones, which a plugin may have resized and hyper
+/// measures anyway.
+fn build_response(
+    status: u16,
+    headers: &axum::http::HeaderMap,
+    body: Body,
+    target_url: &str,
+) -> Response<Body> {
+    let buffered = HttpBody::size_hint(&body).exact().is_some();
+    let mut response = Response::new(body);
+    *response.status_mut() = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+
+    let mut skipped_headers = 0usize;
     for (key, value) in headers.iter() {
-        response_builder = response_builder.header(key, value);
+        if buffered && key == CONTENT_LENGTH {
+            continue;
+        }
+        let name = HeaderName::from_bytes(key.as_str().as_bytes());
+        let value = HeaderValue::from_bytes(value.as_bytes());
+        match (name, value) {
+            (Ok(name), Ok(value)) => {
+                response.headers_mut().append(name, value);
+            }
+            _ => {
+                skipped_headers += 1;
+                tracing::debug!(header = %key, target = %target_url, "Skipping invalid upstream header");
+            }
+        }
     }
 
-    let response = response_builder
-        .body(Body::from(body_bytes.to_vec()))
-        .unwrap();
+    if skipped_headers > 0 {
+        tracing::warn!(
+            skipped = %skipped_headers,
+            target = %target_url,
+            "Skipped invalid upstream response headers"
+        );
+    }
 
-    Ok(response)
+    response
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::AppConfig;
+    use crate::config::{AppConfig, CircuitBreakerConfig};
     use crate::metrics::create_shared_metrics;
 
     #[test]
@@ -187,4 +879,368 @@ mod tests {
         // Just verify it can be created
         assert!(!state.config.get().proxy.enabled);
     }
+
+    #[tokio::test]
+    async fn test_client_disconnect_records_cancellation() {
+        // Upstream that accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(format!("http://{}", addr)),
+            ..Default::default()
+        });
+        let metrics = create_shared_metrics(1000);
+        let state = Arc::new(ProxyState::new(config, metrics.clone()));
+
+        let req = Request::builder().uri("/slow").body(Body::empty()).unwrap();
+        let handler = proxy_handler(State(state), req);
+
+        // Simulate the client giving up by dropping the handler future
+        let timed_out = tokio::time::timeout(std::time::Duration::from_millis(100), handler).await;
+        assert!(timed_out.is_err());
+
+        let recorded = metrics.get_all();
+        assert_eq!(recorded.len(), 1);
+        assert!(recorded[0].client_cancelled);
+        assert_eq!(recorded[0].status_code, Some(CLIENT_CLOSED_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_cover_the_whole_body() {
+        // One chunked body that completes, one that stalls after its first chunk
+        let app = axum::Router::new()
+            .route(
+                "/chunked",
+                axum::routing::get(|| async {
+                    Body::from_stream(futures::stream::iter(
+                        ["ab", "cd", "ef"].map(Ok::<_, std::io::Error>),
+                    ))
+                }),
+            )
+            .route(
+                "/stall",
+                axum::routing::get(|| async {
+                    Body::from_stream(
+                        futures::stream::iter([Ok::<_, std::io::Error>("start")])
+                            .chain(futures::stream::pending()),
+                    )
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(format!("http://{}", addr)),
+            max_concurrent_upstream: Some(1),
+            ..Default::default()
+        });
+        let metrics = create_shared_metrics(1000);
+        let state = Arc::new(ProxyState::new(config, metrics.clone()));
+        let permits = state.upstream_permits.clone().unwrap();
+        let get = |uri: &str| {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            proxy_handler(State(state.clone()), req)
+        };
+
+        let response = get("/chunked").await.into_response();
+        assert_eq!(metrics.count(), 0);
+        assert_eq!(permits.available_permits(), 0);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), b"abcdef");
+        assert_eq!(metrics.get_all()[0].response_bytes, Some(6));
+        assert_eq!(permits.available_permits(), 1);
+
+        // The client reads the first chunk and then disconnects
+        let response = get("/stall").await.into_response();
+        let mut body = response.into_body().into_data_stream();
+        assert_eq!(body.next().await.unwrap().unwrap().as_ref(), b"start");
+        assert_eq!(metrics.count(), 1);
+        assert_eq!(permits.available_permits(), 0);
+        drop(body);
+
+        let recorded = metrics.get_all();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded[1].client_cancelled);
+        assert_eq!(recorded[1].status_code, Some(CLIENT_CLOSED_REQUEST));
+        assert_eq!(permits.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_streams_large_body_intact() {
+        let payload: Vec<u8> = (0..5 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let served = payload.clone();
+        let app = axum::Router::new().route(
+            "/large",
+            axum::routing::get(move || async move { served.clone() }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(format!("http://{}", addr)),
+            ..Default::default()
+        });
+        let metrics = create_shared_metrics(1000);
+        let state = Arc::new(ProxyState::new(config, metrics.clone()));
+
+        let req = Request::builder()
+            .uri("/large")
+            .body(Body::empty())
+            .unwrap();
+        let response = proxy_handler(State(state), req).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[CONTENT_LENGTH],
+            payload.len().to_string().as_str()
+        );
+        assert!(!response.headers().contains_key("transfer-encoding"));
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.len(), payload.len());
+        assert!(body == payload);
+        assert_eq!(metrics.get_all()[0].bytes, Some(payload.len() as u64));
+        assert_eq!(metrics.get_all()[0].request_bytes, Some(0));
+        assert_eq!(
+            metrics.get_all()[0].response_bytes,
+            Some(payload.len() as u64)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_fails_fast() {
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(dead),
+            circuit_breaker: Some(CircuitBreakerConfig {
+                failure_threshold: 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let metrics = create_shared_metrics(1000);
+        let state = Arc::new(ProxyState::new(config, metrics.clone()));
+
+        let mut statuses = Vec::new();
+        for _ in 0..3 {
+            let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let response = proxy_handler(State(state.clone()), req)
+                .await
+                .into_response();
+            statuses.push(response.status());
+        }
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::BAD_GATEWAY,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+
+        let recorded = metrics.get_all();
+        assert!(!recorded[1].circuit_open);
+        assert!(recorded[2].circuit_open);
+        assert_eq!(recorded[2].status_code, Some(503));
+    }
+
+    #[tokio::test]
+    async fn test_binary_header_and_body_round_trip() {
+        // The upstream answers with the header value and body it received
+        let app = axum::Router::new().fallback(
+            |headers: HeaderMap, body: axum::body::Bytes| async move {
+                ([("x-raw", headers["x-raw"].clone())], body)
+            },
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(format!("http://{}", addr)),
+            ..Default::default()
+        });
+        let state = Arc::new(ProxyState::new(config, create_shared_metrics(1000)));
+
+        let raw_header = HeaderValue::from_bytes(b"caf\xe9 \xff").unwrap();
+        let payload: Vec<u8> = (0..=255).collect();
+        let req = Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header("x-raw", raw_header.clone())
+            .body(Body::from(payload.clone()))
+            .unwrap();
+        let response = proxy_handler(State(state), req).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-raw"], raw_header);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), payload.as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_body_size_limit() {
+        // The upstream echoes request bodies and answers GETs with 32 bytes
+        let app = axum::Router::new()
+            .route("/big", axum::routing::get(|| async { "x".repeat(32) }))
+            .fallback(|body: axum::body::Bytes| async move { body });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(format!("http://{}", addr)),
+            max_body_bytes: 16,
+            ..Default::default()
+        });
+        let metrics = create_shared_metrics(1000);
+        let state = Arc::new(ProxyState::new(config, metrics.clone()));
+        let send = |method: &str, uri: &str, body: Vec<u8>| {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::from(body))
+                .unwrap();
+            proxy_handler(State(state.clone()), req)
+        };
+
+        let response = send("POST", "/upload", vec![0; 16]).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send("POST", "/upload", vec![0; 17]).await.into_response();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let metric = metrics.get_all().pop().unwrap();
+        assert_eq!(metric.status_code, Some(413));
+        assert!(!metric.proxied);
+
+        let response = send("GET", "/big", Vec::new()).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_client_builds_for_each_http_version() {
+        for http_version in [HttpVersion::Auto, HttpVersion::Http1, HttpVersion::Http2] {
+            let mut config = AppConfig::default();
+            config.proxy.http_version = http_version;
+            assert!(build_client(&config).is_ok(), "{:?}", http_version);
+        }
+    }
+
+    #[test]
+    fn test_header_rules() {
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", "session=1".parse().unwrap());
+        headers.insert("connection", "keep-alive, X-Hop".parse().unwrap());
+        headers.insert("x-hop", "1".parse().unwrap());
+        headers.insert("transfer-encoding", "chunked".parse().unwrap());
+        headers.insert("accept", "*/*".parse().unwrap());
+
+        strip_hop_by_hop(&mut headers);
+        apply_header_rules(
+            &mut headers,
+            &["Cookie".to_string()],
+            &[("X-Env".to_string(), "staging".to_string())],
+        );
+
+        let mut names: Vec<&str> = headers.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["accept", "x-env"]);
+        assert_eq!(headers["x-env"], "staging");
+    }
+
+    #[test]
+    fn test_forwarded_headers_extend_chain() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, "api.example.com".parse().unwrap());
+        headers.insert(X_FORWARDED_FOR, "203.0.113.7".parse().unwrap());
+
+        add_forwarded_headers(&mut headers, Some([10, 0, 0, 2].into()), "http");
+        assert_eq!(headers[X_FORWARDED_FOR], "203.0.113.7, 10.0.0.2");
+        assert_eq!(headers[X_FORWARDED_PROTO], "http");
+        assert_eq!(headers[X_FORWARDED_HOST], "api.example.com");
+    }
+
+    #[test]
+    fn test_match_route_longest_prefix() {
+        let route = |prefix: &str, target: &str| ProxyRoute {
+            prefix: prefix.to_string(),
+            target: target.to_string(),
+            strip_prefix: true,
+        };
+        let routes = vec![
+            route("/api", "http://api.local"),
+            route("/api/v2/", "http://v2.local"),
+            route("/static", "http://cdn.local"),
+        ];
+
+        let target = |path| match_route(&routes, path).map(|r| r.target.as_str());
+        assert_eq!(target("/api/users"), Some("http://api.local"));
+        assert_eq!(target("/api/v2/users"), Some("http://v2.local"));
+        assert_eq!(target("/api/v2"), Some("http://v2.local"));
+        assert_eq!(target("/apix"), None);
+        assert_eq!(target("/other"), None);
+
+        assert_eq!(strip_route_prefix("/api/v2/", "/api/v2/users"), "/users");
+        assert_eq!(strip_route_prefix("/static", "/static"), "/");
+    }
+
+    #[test]
+    fn test_resolve_target_override() {
+        let config = ProxyConfig {
+            enabled: true,
+            target: Some("http://default.local".to_string()),
+            allow_target_header: true,
+            target_allowlist: vec!["allowed.local:8080".to_string()],
+            ..Default::default()
+        };
+
+        let pool = UpstreamPool::new();
+        let mut headers = axum::http::HeaderMap::new();
+        assert_eq!(
+            resolve_target(&config, &headers, &pool).unwrap(),
+            Some("http://default.local".to_string())
+        );
+
+        headers.insert(
+            PROXY_TARGET_HEADER,
+            "http://allowed.local:8080".parse().unwrap(),
+        );
+        assert_eq!(
+            resolve_target(&config, &headers, &pool).unwrap(),
+            Some("http://allowed.local:8080".to_string())
+        );
+
+        headers.insert(PROXY_TARGET_HEADER, "http://evil.local".parse().unwrap());
+        let err = resolve_target(&config, &headers, &pool).unwrap_err();
+        assert_eq!(err.0, StatusCode::FORBIDDEN);
+    }
 }
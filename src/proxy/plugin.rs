@@ -0,0 +1,337 @@
+//! WASM proxy plugins
+//!
+//! A plugin is a WebAssembly module (binary `.wasm` or text `.wat`) loaded
+//! from `proxy.plugin_path` that can inspect and modify each proxied request
+//! before it is forwarded and each response before it is returned. Requires
+//! the `wasm-plugins` feature.
+//!
+//! # ABI
+//!
+//! The module must export its linear `memory` and may export either hook:
+//!
+//! - `on_request() -> i32`: called before forwarding. Return `0` to continue,
+//!   or an HTTP status code (100-599) to respond immediately with that status
+//!   and the current body/headers without contacting the upstream.
+//! - `on_response() -> i32`: called before returning the upstream response.
+//!   The return value is ignored.
+//!
+//! Host functions are imported from the `api_check` module. Strings are
+//! passed as `(ptr, len)` pairs in guest memory. Getters copy into an
+//! `(out_ptr, out_cap)` buffer and return the full length (writing nothing if
+//! it exceeds `out_cap`), or `-1` when the value is absent:
+//!
+//! - `get_header(name_ptr, name_len, out_ptr, out_cap) -> i32`
+//! - `set_header(name_ptr, name_len, value_ptr, value_len)`
+//! - `remove_header(name_ptr, name_len)`
+//! - `get_body(out_ptr, out_cap) -> i32`
+//! - `set_body(ptr, len)`
+//! - `get_status() -> i32` (`0` during `on_request`)
+//! - `set_status(status)` (response phase only)
+//!
+//! Each hook call gets `proxy.plugin_fuel` units of fuel (roughly one per
+//! instruction); a hook that runs out is aborted and the request fails with
+//! a 502. Hooks run on the blocking thread pool, never on an async worker.
+//!
+//! See `examples/plugins/add_header.wat` for a complete plugin.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::http::HeaderMap;
+
+/// Data exposed to a plugin hook
+#[derive(Debug, Default)]
+pub struct PluginContext {
+    /// Request or response headers
+    pub headers: HeaderMap,
+    /// Request or response body
+    pub body: Vec<u8>,
+    /// Response status (`0` during the request phase)
+    pub status: u16,
+}
+
+/// Result of the request hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginOutcome {
+    /// Forward the (possibly modified) request upstream
+    Continue,
+    /// Respond immediately with this status
+    Respond(u16),
+}
+
+/// Run a hook on the blocking thread pool, handing the context back afterwards
+pub async fn run_blocking<T: Send + 'static>(
+    plugin: &Arc<WasmPlugin>,
+    mut ctx: PluginContext,
+    hook: fn(&WasmPlugin, &mut PluginContext) -> Result<T>,
+) -> Result<(T, PluginContext)> {
+    let plugin = plugin.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = hook(&plugin, &mut ctx)?;
+        Ok((result, ctx))
+    })
+    .await?
+}
+
+#[cfg(feature = "wasm-plugins")]
+mod wasm {
+    use super::{PluginContext, PluginOutcome};
+    use anyhow::{anyhow, Context, Result};
+    use axum::http::{HeaderName, HeaderValue};
+    use wasmtime::{Caller, Config, Engine, Linker, Memory, Module, Store};
+
+    /// A loaded WASM plugin
+    pub struct WasmPlugin {
+        engine: Engine,
+        module: Module,
+        linker: Linker<PluginContext>,
+        fuel: u64,
+    }
+
+    fn memory(caller: &mut Caller<'_, PluginContext>) -> Result<Memory> {
+        caller
+            .get_export("memory")
+            .and_then(|e| e.into_memory())
+            .ok_or_else(|| anyhow!("plugin does not export memory"))
+    }
+
+    fn read_bytes(caller: &mut Caller<'_, PluginContext>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+        let memory = memory(caller)?;
+        let mut buf = vec![0u8; len.max(0) as usize];
+        memory.read(&*caller, ptr as usize, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn write_out(
+        caller: &mut Caller<'_, PluginContext>,
+        data: &[u8],
+        out_ptr: i32,
+        out_cap: i32,
+    ) -> Result<i32> {
+        if data.len() <= out_cap.max(0) as usize {
+            let memory = memory(caller)?;
+            memory.write(&mut *caller, out_ptr as usize, data)?;
+        }
+        Ok(data.len() as i32)
+    }
+
+    fn header_name(
+        caller: &mut Caller<'_, PluginContext>,
+        ptr: i32,
+        len: i32,
+    ) -> Result<HeaderName> {
+        let name = read_bytes(caller, ptr, len)?;
+        Ok(HeaderName::from_bytes(&name)?)
+    }
+
+    impl WasmPlugin {
+        /// Load and compile a plugin module whose hooks may each burn `fuel`
+        pub fn load(path: &str, fuel: u64) -> Result<Self> {
+            let engine = Engine::new(Config::new().consume_fuel(true))?;
+            let module = Module::from_file(&engine, path)
+                .with_context(|| format!("Failed to load plugin {}", path))?;
+            let mut linker = Linker::new(&engine);
+
+            linker.func_wrap(
+                "api_check",
+                "get_header",
+                |mut caller: Caller<'_, PluginContext>,
+                 name_ptr: i32,
+                 name_len: i32,
+                 out_ptr: i32,
+                 out_cap: i32|
+                 -> Result<i32> {
+                    let name = header_name(&mut caller, name_ptr, name_len)?;
+                    match caller.data().headers.get(&name) {
+                        Some(value) => {
+                            let value = value.as_bytes().to_vec();
+                            write_out(&mut caller, &value, out_ptr, out_cap)
+                        }
+                        None => Ok(-1),
+                    }
+                },
+            )?;
+            linker.func_wrap(
+                "api_check",
+                "set_header",
+                |mut caller: Caller<'_, PluginContext>,
+                 name_ptr: i32,
+                 name_len: i32,
+                 value_ptr: i32,
+                 value_len: i32|
+                 -> Result<()> {
+                    let name = header_name(&mut caller, name_ptr, name_len)?;
+                    let value =
+                        HeaderValue::from_bytes(&read_bytes(&mut caller, value_ptr, value_len)?)?;
+                    caller.data_mut().headers.insert(name, value);
+                    Ok(())
+                },
+            )?;
+            linker.func_wrap(
+                "api_check",
+                "remove_header",
+                |mut caller: Caller<'_, PluginContext>,
+                 name_ptr: i32,
+                 name_len: i32|
+                 -> Result<()> {
+                    let name = header_name(&mut caller, name_ptr, name_len)?;
+                    caller.data_mut().headers.remove(name);
+                    Ok(())
+                },
+            )?;
+            linker.func_wrap(
+                "api_check",
+                "get_body",
+                |mut caller: Caller<'_, PluginContext>,
+                 out_ptr: i32,
+                 out_cap: i32|
+                 -> Result<i32> {
+                    let body = caller.data().body.clone();
+                    write_out(&mut caller, &body, out_ptr, out_cap)
+                },
+            )?;
+            linker.func_wrap(
+                "api_check",
+                "set_body",
+                |mut caller: Caller<'_, PluginContext>, ptr: i32, len: i32| -> Result<()> {
+                    let body = read_bytes(&mut caller, ptr, len)?;
+                    caller.data_mut().body = body;
+                    Ok(())
+                },
+            )?;
+            linker.func_wrap(
+                "api_check",
+                "get_status",
+                |caller: Caller<'_, PluginContext>| -> i32 { caller.data().status as i32 },
+            )?;
+            linker.func_wrap(
+                "api_check",
+                "set_status",
+                |mut caller: Caller<'_, PluginContext>, status: i32| {
+                    if (100..=599).contains(&status) {
+                        caller.data_mut().status = status as u16;
+                    }
+                },
+            )?;
+
+            Ok(Self {
+                engine,
+                module,
+                linker,
+                fuel,
+            })
+        }
+
+        /// Run an exported hook, returning its result or `None` if not exported
+        fn call_hook(&self, name: &str, ctx: &mut PluginContext) -> Result<Option<i32>> {
+            let mut store = Store::new(&self.engine, std::mem::take(ctx));
+            store.set_fuel(self.fuel)?;
+            let instance = self.linker.instantiate(&mut store, &self.module)?;
+
+            let result = match instance.get_typed_func::<(), i32>(&mut store, name) {
+                Ok(hook) => Some(
+                    hook.call(&mut store, ())
+                        .with_context(|| format!("Plugin hook {} failed", name))?,
+                ),
+                Err(_) => None,
+            };
+
+            *ctx = store.into_data();
+            Ok(result)
+        }
+
+        /// Run the request hook
+        pub fn on_request(&self, ctx: &mut PluginContext) -> Result<PluginOutcome> {
+            match self.call_hook("on_request", ctx)? {
+                Some(status @ 100..=599) => Ok(PluginOutcome::Respond(status as u16)),
+                _ => Ok(PluginOutcome::Continue),
+            }
+        }
+
+        /// Run the response hook
+        pub fn on_response(&self, ctx: &mut PluginContext) -> Result<()> {
+            self.call_hook("on_response", ctx)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+pub use wasm::WasmPlugin;
+
+/// Placeholder used when built without the `wasm-plugins` feature
+#[cfg(not(feature = "wasm-plugins"))]
+pub struct WasmPlugin {
+    _private: (),
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+impl WasmPlugin {
+    /// Always fails: plugin support is not compiled in
+    pub fn load(path: &str, _fuel: u64) -> anyhow::Result<Self> {
+        anyhow::bail!(
+            "Cannot load plugin {}: api-check was built without the `wasm-plugins` feature",
+            path
+        )
+    }
+
+    /// Run the request hook
+    pub fn on_request(&self, _ctx: &mut PluginContext) -> Result<PluginOutcome> {
+        Ok(PluginOutcome::Continue)
+    }
+
+    /// Run the response hook
+    pub fn on_response(&self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "wasm-plugins"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_plugin() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/examples/plugins/add_header.wat"
+        );
+        let plugin = WasmPlugin::load(path, 1_000_000).unwrap();
+
+        let mut ctx = PluginContext::default();
+        assert_eq!(
+            plugin.on_request(&mut ctx).unwrap(),
+            PluginOutcome::Continue
+        );
+        assert_eq!(ctx.headers.get("x-plugin").unwrap(), "api-check");
+
+        let mut ctx = PluginContext {
+            status: 200,
+            ..Default::default()
+        };
+        plugin.on_response(&mut ctx).unwrap();
+        assert_eq!(ctx.headers.get("x-plugin").unwrap(), "api-check");
+        assert!(ctx.headers.get("server").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_looping_plugin_runs_out_of_fuel() {
+        let path = std::env::temp_dir().join(format!("loop-{}.wat", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "on_request") (result i32)
+                    (loop $spin (br $spin))
+                    (i32.const 0)))"#,
+        )
+        .unwrap();
+        let plugin = Arc::new(WasmPlugin::load(path.to_str().unwrap(), 100_000).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let err = run_blocking(&plugin, PluginContext::default(), WasmPlugin::on_request)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("on_request"), "{:#}", err);
+    }
+}
@@ -0,0 +1,104 @@
+//! Proxy bandwidth throttling
+//!
+//! A token bucket paces proxied bodies to `proxy.max_upstream_bytes_per_sec`
+//! and `proxy.max_downstream_bytes_per_sec`, simulating constrained links.
+
+use axum::body::Bytes;
+use futures::{Stream, StreamExt};
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting throughput to a fixed number of bytes per second
+#[derive(Debug)]
+pub struct Throttle {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Throttle {
+    /// Create a throttle allowing `bytes_per_sec`, with a burst of 100ms worth of bytes
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec.max(1) as f64;
+        Self {
+            rate,
+            capacity: (rate / 10.0).max(1.0),
+            tokens: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    /// Largest chunk worth sending at once to keep pacing smooth
+    pub fn chunk_size(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Wait until `bytes` may be transferred
+    pub async fn consume(&mut self, bytes: usize) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+
+        // Going into debt lets chunks larger than the burst through at the right average rate
+        self.tokens -= bytes as f64;
+        if self.tokens < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-self.tokens / self.rate)).await;
+        }
+    }
+}
+
+/// Stream a buffered body in chunks paced by a throttle
+pub fn paced_stream(
+    body: Bytes,
+    throttle: Throttle,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static {
+    futures::stream::unfold((body, throttle), |(mut body, mut throttle)| async move {
+        if body.is_empty() {
+            return None;
+        }
+        let chunk = body.split_to(throttle.chunk_size().min(body.len()));
+        throttle.consume(chunk.len()).await;
+        Some((Ok(chunk), (body, throttle)))
+    })
+}
+
+/// Pace an already-chunked stream by a throttle, passing errors through
+pub fn throttled_stream<S, E>(
+    stream: S,
+    throttle: Throttle,
+) -> impl Stream<Item = Result<Bytes, E>> + Send + 'static
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    futures::stream::unfold(
+        (Box::pin(stream), throttle),
+        |(mut stream, mut throttle)| async move {
+            let item = stream.next().await?;
+            if let Ok(chunk) = &item {
+                throttle.consume(chunk.len()).await;
+            }
+            Some((item, (stream, throttle)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_paced_stream_respects_rate() {
+        let body = Bytes::from(vec![0u8; 2000]);
+        let start = Instant::now();
+
+        let chunks: Vec<_> = paced_stream(body, Throttle::new(10_000)).collect().await;
+        let elapsed = start.elapsed();
+
+        assert_eq!(chunks.len(), 2);
+        // 2000 bytes at 10 KB/s takes ~200ms
+        assert!(elapsed >= Duration::from_millis(180), "took {:?}", elapsed);
+    }
+}
@@ -0,0 +1,191 @@
+//! Upstream target pool
+//!
+//! Round-robins across `proxy.targets` and tracks each upstream's health,
+//! updated by a background task probing `proxy.health_path`.
+
+use crate::config::{apply_dns_overrides, SharedConfig};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Health state of a single upstream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamStatus {
+    /// Upstream base URL
+    pub url: String,
+    /// Whether the upstream is eligible for routing
+    pub healthy: bool,
+    /// When the upstream was last probed
+    pub last_checked: Option<DateTime<Utc>>,
+    /// Error from the last failed probe
+    pub last_error: Option<String>,
+}
+
+impl UpstreamStatus {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            healthy: true,
+            last_checked: None,
+            last_error: None,
+        }
+    }
+}
+
+/// Pool of upstream targets with round-robin selection
+#[derive(Debug, Default)]
+pub struct UpstreamPool {
+    upstreams: RwLock<Vec<UpstreamStatus>>,
+    next: AtomicUsize,
+}
+
+impl UpstreamPool {
+    /// Create an empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sync pool membership with the configured targets, keeping known health state
+    pub fn sync_targets(&self, targets: &[String]) {
+        let in_sync = {
+            let upstreams = self.upstreams.read();
+            upstreams.len() == targets.len()
+                && upstreams.iter().zip(targets).all(|(u, t)| &u.url == t)
+        };
+        if in_sync {
+            return;
+        }
+
+        let mut upstreams = self.upstreams.write();
+        let updated = targets
+            .iter()
+            .map(|target| {
+                upstreams
+                    .iter()
+                    .find(|u| &u.url == target)
+                    .cloned()
+                    .unwrap_or_else(|| UpstreamStatus::new(target.clone()))
+            })
+            .collect();
+        *upstreams = updated;
+    }
+
+    /// Pick the next healthy upstream in round-robin order
+    pub fn next_healthy(&self, targets: &[String]) -> Option<String> {
+        self.sync_targets(targets);
+
+        let upstreams = self.upstreams.read();
+        if upstreams.is_empty() {
+            return None;
+        }
+
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..upstreams.len())
+            .map(|offset| &upstreams[(start + offset) % upstreams.len()])
+            .find(|u| u.healthy)
+            .map(|u| u.url.clone())
+    }
+
+    /// Update an upstream's health, logging transitions
+    pub fn set_health(&self, url: &str, healthy: bool, error: Option<String>) {
+        let mut upstreams = self.upstreams.write();
+        let Some(upstream) = upstreams.iter_mut().find(|u| u.url == url) else {
+            return;
+        };
+
+        if upstream.healthy != healthy {
+            if healthy {
+                tracing::info!(upstream = %url, "Upstream is healthy again");
+            } else {
+                tracing::warn!(
+                    upstream = %url,
+                    error = %error.as_deref().unwrap_or("unknown"),
+                    "Upstream marked unhealthy"
+                );
+            }
+        }
+
+        upstream.healthy = healthy;
+        upstream.last_checked = Some(Utc::now());
+        upstream.last_error = error;
+    }
+
+    /// Get the current health of every upstream
+    pub fn snapshot(&self) -> Vec<UpstreamStatus> {
+        self.upstreams.read().clone()
+    }
+}
+
+/// Shared upstream pool
+pub type SharedUpstreamPool = Arc<UpstreamPool>;
+
+/// Spawn the background task that probes each upstream's health path
+pub fn spawn_health_checker(
+    pool: SharedUpstreamPool,
+    config: SharedConfig,
+) -> tokio::task::JoinHandle<()> {
+    let client = apply_dns_overrides(Client::builder(), &config.get())
+        .timeout(Duration::from_secs(5))
+        .build()
+        .expect("Failed to create HTTP client");
+
+    tokio::spawn(async move {
+        loop {
+            let proxy = config.get().proxy;
+            pool.sync_targets(&proxy.targets);
+
+            for target in &proxy.targets {
+                let Some(health_path) = &proxy.health_path else {
+                    // Without a health path every upstream is considered healthy
+                    pool.set_health(target, true, None);
+                    continue;
+                };
+
+                let url = format!("{}{}", target.trim_end_matches('/'), health_path);
+                match client.get(&url).send().await {
+                    Ok(response) if response.status().is_success() => {
+                        pool.set_health(target, true, None);
+                    }
+                    Ok(response) => {
+                        let error = format!("health check returned {}", response.status());
+                        pool.set_health(target, false, Some(error));
+                    }
+                    Err(e) => pool.set_health(target, false, Some(e.to_string())),
+                }
+            }
+
+            tokio::time::sleep(Duration::from_secs(proxy.health_interval_secs.max(1))).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin_skips_unhealthy() {
+        let pool = UpstreamPool::new();
+        let targets = vec![
+            "http://a".to_string(),
+            "http://b".to_string(),
+            "http://c".to_string(),
+        ];
+
+        pool.sync_targets(&targets);
+        pool.set_health("http://b", false, Some("down".to_string()));
+
+        let picks: Vec<String> = (0..4).filter_map(|_| pool.next_healthy(&targets)).collect();
+        assert!(picks.iter().all(|p| p != "http://b"));
+        assert!(picks.contains(&"http://a".to_string()));
+        assert!(picks.contains(&"http://c".to_string()));
+
+        pool.set_health("http://a", false, None);
+        pool.set_health("http://c", false, None);
+        assert_eq!(pool.next_healthy(&targets), None);
+    }
+}
@@ -0,0 +1,295 @@
+//! WebSocket proxying
+//!
+//! Requests with `Upgrade: websocket` are forwarded by opening a WebSocket to
+//! the upstream first (so an unreachable upstream still gets a plain 502),
+//! then accepting the client's upgrade and piping frames both ways until
+//! either side closes.
+//!
+//! Each session records two metrics with the synthetic status 101: one when it
+//! opens (latency is the upstream handshake) and one when it closes (latency
+//! is the session's lifetime, bytes the message payloads relayed).
+
+use super::{select_upstream, Proxied, ProxyState, HOP_BY_HOP_HEADERS};
+use crate::metrics::{MetricSource, RequestMetric};
+use axum::{
+    body::Body,
+    extract::{
+        ws::{self, WebSocket, WebSocketUpgrade},
+        FromRequestParts,
+    },
+    http::{header, HeaderMap, Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio_tungstenite::tungstenite::{
+    self,
+    client::IntoClientRequest,
+    protocol::{frame::coding::CloseCode, CloseFrame},
+};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Status recorded for WebSocket session open/close metrics
+pub const WEBSOCKET_STATUS: u16 = 101;
+
+type Upstream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Whether `headers` ask to upgrade the connection to a WebSocket
+pub fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+}
+
+/// Proxy a WebSocket upgrade request to the selected upstream
+pub async fn proxy_websocket(state: Arc<ProxyState>, req: Request<Body>) -> Response {
+    let start = Instant::now();
+    let (mut parts, _) = req.into_parts();
+    let path = parts.uri.path().to_string();
+    let record = |status: u16, upstream: Option<&str>| {
+        let mut metric = RequestMetric::new("GET".to_string(), path.clone())
+            .with_status(status)
+            .with_latency(start.elapsed().as_secs_f64() * 1000.0)
+            .with_source(MetricSource::Proxy)
+            .with_proxied(upstream.is_some());
+        if let Some(upstream) = upstream {
+            metric = metric.with_upstream(upstream.to_string());
+        }
+        state.metrics.record(metric);
+    };
+
+    let upgrade = match WebSocketUpgrade::from_request_parts(&mut parts, &()).await {
+        Ok(upgrade) => upgrade,
+        Err(rejection) => {
+            let response = rejection.into_response();
+            record(response.status().as_u16(), None);
+            return response;
+        }
+    };
+
+    let config = state.config.get();
+    let (target, url) =
+        match select_upstream(&config.proxy, &parts.uri, &parts.headers, &state.upstreams) {
+            Ok(Some(selected)) => selected,
+            Ok(None) => {
+                record(502, None);
+                return (StatusCode::BAD_GATEWAY, "No proxy target configured").into_response();
+            }
+            Err((status, message)) => {
+                record(status.as_u16(), None);
+                return (status, message).into_response();
+            }
+        };
+
+    let upstream = match connect_upstream(&url, &parts.headers).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            tracing::error!(error = %e, target = %url, "WebSocket proxy error");
+            record(502, Some(&target));
+            return (StatusCode::BAD_GATEWAY, format!("Proxy error: {}", e)).into_response();
+        }
+    };
+    record(WEBSOCKET_STATUS, Some(&target));
+    tracing::info!(target = %url, "Proxying WebSocket");
+
+    // Accept the subprotocol the upstream picked, if any
+    let protocol = upstream
+        .1
+        .headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let upgrade = match protocol {
+        Some(protocol) => upgrade.protocols([protocol]),
+        None => upgrade,
+    };
+
+    let metrics = state.metrics.clone();
+    let mut response = upgrade.on_upgrade(move |client| async move {
+        let session = Instant::now();
+        let bytes = pipe(client, upstream.0).await;
+        let latency = session.elapsed().as_secs_f64() * 1000.0;
+        metrics.record(
+            RequestMetric::new("GET".to_string(), path)
+                .with_status(WEBSOCKET_STATUS)
+                .with_latency(latency)
+                .with_source(MetricSource::Proxy)
+                .with_proxied(true)
+                .with_upstream(target)
+                .with_bytes(bytes),
+        );
+        tracing::info!(target = %url, duration_ms = %latency, bytes = %bytes, "WebSocket closed");
+    });
+    response.extensions_mut().insert(Proxied);
+    response
+}
+
+/// Open a WebSocket to `url` (`http`/`https` become `ws`/`wss`), passing on
+/// the client's end-to-end headers
+async fn connect_upstream(
+    url: &str,
+    headers: &HeaderMap,
+) -> tungstenite::Result<(Upstream, tungstenite::handshake::client::Response)> {
+    let url = match url.split_once("://") {
+        Some(("https", rest)) => format!("wss://{}", rest),
+        Some(("http", rest)) => format!("ws://{}", rest),
+        _ => url.to_string(),
+    };
+    let mut request = url.into_client_request()?;
+    for (name, value) in headers {
+        let name_str = name.as_str();
+        // The handshake headers are generated for the upstream connection
+        if name == header::HOST
+            || HOP_BY_HOP_HEADERS.contains(&name_str)
+            || (name_str.starts_with("sec-websocket-") && name != header::SEC_WEBSOCKET_PROTOCOL)
+        {
+            continue;
+        }
+        request.headers_mut().append(name, value.clone());
+    }
+    tokio_tungstenite::connect_async(request).await
+}
+
+/// Relay messages both ways until either side closes, returning the payload bytes relayed
+///
+/// Pings are answered by each connection itself rather than forwarded.
+async fn pipe(client: WebSocket, upstream: Upstream) -> u64 {
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+    let mut sent = 0;
+    let mut received = 0;
+
+    let client_to_upstream = async {
+        while let Some(Ok(message)) = client_rx.next().await {
+            let Some(message) = to_upstream(message) else {
+                continue;
+            };
+            let closing = message.is_close();
+            if !closing {
+                sent += message.len() as u64;
+            }
+            if upstream_tx.send(message).await.is_err() || closing {
+                break;
+            }
+        }
+        let _ = upstream_tx.close().await;
+    };
+    let upstream_to_client = async {
+        while let Some(Ok(message)) = upstream_rx.next().await {
+            let Some(message) = to_client(message) else {
+                continue;
+            };
+            let closing = matches!(message, ws::Message::Close(_));
+            if let ws::Message::Text(text) = &message {
+                received += text.len() as u64;
+            } else if let ws::Message::Binary(data) = &message {
+                received += data.len() as u64;
+            }
+            if client_tx.send(message).await.is_err() || closing {
+                break;
+            }
+        }
+        let _ = client_tx.close().await;
+    };
+
+    tokio::select! {
+        _ = client_to_upstream => {}
+        _ = upstream_to_client => {}
+    }
+    sent + received
+}
+
+fn to_upstream(message: ws::Message) -> Option<tungstenite::Message> {
+    match message {
+        ws::Message::Text(text) => Some(tungstenite::Message::Text(text)),
+        ws::Message::Binary(data) => Some(tungstenite::Message::Binary(data)),
+        ws::Message::Close(frame) => {
+            Some(tungstenite::Message::Close(frame.map(|frame| CloseFrame {
+                code: CloseCode::from(frame.code),
+                reason: frame.reason,
+            })))
+        }
+        ws::Message::Ping(_) | ws::Message::Pong(_) => None,
+    }
+}
+
+fn to_client(message: tungstenite::Message) -> Option<ws::Message> {
+    match message {
+        tungstenite::Message::Text(text) => Some(ws::Message::Text(text)),
+        tungstenite::Message::Binary(data) => Some(ws::Message::Binary(data)),
+        tungstenite::Message::Close(frame) => {
+            Some(ws::Message::Close(frame.map(|frame| ws::CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason,
+            })))
+        }
+        tungstenite::Message::Ping(_)
+        | tungstenite::Message::Pong(_)
+        | tungstenite::Message::Frame(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, ProxyConfig, SharedConfig};
+    use crate::metrics::create_shared_metrics;
+
+    #[tokio::test]
+    async fn test_proxies_websocket_frames() {
+        // Upstream echoing every message back
+        let echo = axum::Router::new().fallback(|upgrade: WebSocketUpgrade| async move {
+            upgrade.on_upgrade(|mut socket| async move {
+                while let Some(Ok(message)) = socket.recv().await {
+                    if socket.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            })
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, echo).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        config.update_proxy(ProxyConfig {
+            enabled: true,
+            target: Some(format!("http://{}", upstream)),
+            ..Default::default()
+        });
+        let metrics = create_shared_metrics(1000);
+        let state = Arc::new(ProxyState::new(config, metrics.clone()));
+        let proxy = axum::Router::new().fallback(move |req| proxy_websocket(state.clone(), req));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, proxy).await });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/chat", addr))
+            .await
+            .unwrap();
+        socket
+            .send(tungstenite::Message::Text("hello".to_string()))
+            .await
+            .unwrap();
+        let reply = socket.next().await.unwrap().unwrap();
+        assert_eq!(reply, tungstenite::Message::Text("hello".to_string()));
+        socket.close(None).await.unwrap();
+        while socket.next().await.is_some() {}
+
+        // The close metric is recorded once the session winds down
+        for _ in 0..50 {
+            if metrics.get_all().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let recorded = metrics.get_all();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded
+            .iter()
+            .all(|m| m.status_code == Some(WEBSOCKET_STATUS) && m.path == "/chat"));
+        assert_eq!(recorded[1].bytes, Some(10));
+    }
+}
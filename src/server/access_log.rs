@@ -0,0 +1,158 @@
+//! NDJSON access log
+//!
+//! With `server.access_log_path` set, every non-API request is appended to the
+//! file as one JSON object per line. Lines go over a bounded channel to a
+//! writer task, so a slow disk never holds up a request: when the channel is
+//! full, lines are dropped (and counted in a warning) rather than waited on.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot};
+
+/// Lines buffered for the writer before new ones are dropped
+pub const ACCESS_LOG_CAPACITY: usize = 8192;
+
+/// One access log line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub latency_ms: f64,
+    /// Whether the response came from a proxy upstream
+    pub proxied: bool,
+}
+
+/// Message sent to the writer task
+#[derive(Debug)]
+enum Message {
+    /// Append a line
+    Entry(Box<AccessLogEntry>),
+    /// Acknowledge once everything queued before it is on disk
+    Flush(oneshot::Sender<()>),
+}
+
+/// Handle feeding entries to the access log writer
+#[derive(Debug)]
+pub struct AccessLog {
+    sender: mpsc::Sender<Message>,
+    /// Entries dropped because the writer fell behind
+    dropped: Arc<AtomicU64>,
+}
+
+impl AccessLog {
+    /// Open `path` for appending and spawn the writer task
+    pub async fn spawn(path: &str) -> Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open access log {}", path))?;
+        let (sender, receiver) = mpsc::channel(ACCESS_LOG_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        tokio::spawn(run(BufWriter::new(file), receiver, dropped.clone()));
+
+        tracing::info!(path = %path, "Writing access log");
+        Ok(Self { sender, dropped })
+    }
+
+    /// Queue an entry without waiting; it is dropped if the writer is behind
+    pub fn send(&self, entry: AccessLogEntry) {
+        if self
+            .sender
+            .try_send(Message::Entry(Box::new(entry)))
+            .is_err()
+        {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Wait up to `timeout` for queued entries to be written and flushed
+    ///
+    /// Returns `false` if the writer did not confirm in time.
+    pub async fn flush(&self, timeout: Duration) -> bool {
+        let (ack, done) = oneshot::channel();
+        let confirmed =
+            async { self.sender.send(Message::Flush(ack)).await.is_ok() && done.await.is_ok() };
+        tokio::time::timeout(timeout, confirmed)
+            .await
+            .unwrap_or(false)
+    }
+}
+
+async fn run(
+    mut writer: BufWriter<tokio::fs::File>,
+    mut receiver: mpsc::Receiver<Message>,
+    dropped: Arc<AtomicU64>,
+) {
+    while let Some(message) = receiver.recv().await {
+        // Write everything already queued, then flush once
+        let mut acks = Vec::new();
+        let mut next = Some(message);
+        while let Some(message) = next {
+            match message {
+                Message::Entry(entry) => {
+                    let mut line = serde_json::to_vec(&entry).unwrap_or_default();
+                    line.push(b'\n');
+                    if let Err(e) = writer.write_all(&line).await {
+                        tracing::error!(error = %e, "Access log writer stopped");
+                        return;
+                    }
+                }
+                Message::Flush(ack) => acks.push(ack),
+            }
+            next = receiver.try_recv().ok();
+        }
+        if let Err(e) = writer.flush().await {
+            tracing::error!(error = %e, "Access log writer stopped");
+            return;
+        }
+        for ack in acks {
+            let _ = ack.send(());
+        }
+
+        let dropped = dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            tracing::warn!(dropped = %dropped, "Access log fell behind, dropped entries");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_appends_ndjson_lines() {
+        let path = std::env::temp_dir().join(format!("api-check-{}.ndjson", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "{\"existing\":true}\n").unwrap();
+
+        let log = AccessLog::spawn(path).await.unwrap();
+        for status in [200, 404] {
+            log.send(AccessLogEntry {
+                timestamp: Utc::now(),
+                method: "GET".to_string(),
+                path: "/items".to_string(),
+                status,
+                latency_ms: 1.5,
+                proxied: false,
+            });
+        }
+        assert!(log.flush(Duration::from_secs(5)).await);
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let entry: AccessLogEntry = serde_json::from_str(lines[2]).unwrap();
+        assert_eq!(entry.status, 404);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+//! Server lifecycle
+//!
+//! Tracks readiness and coordinates the drain-then-exit shutdown sequence,
+//! which `POST /api/drain` or a SIGINT/SIGTERM starts.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Readiness flag and shutdown signal shared by the server and API
+#[derive(Debug)]
+pub struct Lifecycle {
+    ready: AtomicBool,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lifecycle {
+    /// Create a lifecycle that starts ready
+    pub fn new() -> Self {
+        Self {
+            ready: AtomicBool::new(true),
+            shutdown: watch::Sender::new(false),
+        }
+    }
+
+    /// Whether the server should receive new traffic
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Flip readiness off and shut down after `grace`
+    ///
+    /// Returns `false` if a drain was already in progress.
+    pub fn drain(self: &Arc<Self>, grace: Duration) -> bool {
+        if !self.ready.swap(false, Ordering::Relaxed) {
+            return false;
+        }
+
+        tracing::info!(grace_secs = %grace.as_secs(), "Draining: readiness set to not-ready");
+        let lifecycle = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(grace).await;
+            tracing::info!("Drain grace period elapsed, shutting down");
+            lifecycle.shutdown();
+        });
+        true
+    }
+
+    /// Signal the server to stop accepting connections and exit
+    pub fn shutdown(&self) {
+        self.ready.store(false, Ordering::Relaxed);
+        self.shutdown.send_replace(true);
+    }
+
+    /// Whether shutdown has been signalled
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Wait until shutdown is signalled
+    pub async fn wait_for_shutdown(&self) {
+        let mut rx = self.shutdown.subscribe();
+        // The sender lives in `self`, so this only errors if it was dropped
+        let _ = rx.wait_for(|shutdown| *shutdown).await;
+    }
+}
+
+/// Shared server lifecycle
+pub type SharedLifecycle = Arc<Lifecycle>;
+
+/// Complete on Ctrl-C (SIGINT), or SIGTERM on Unix
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!(error = %e, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => tracing::info!("Received SIGINT, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_flips_readiness_then_shuts_down() {
+        let lifecycle = Arc::new(Lifecycle::new());
+        assert!(lifecycle.is_ready());
+
+        assert!(lifecycle.drain(Duration::from_millis(10)));
+        assert!(!lifecycle.is_ready());
+        assert!(!lifecycle.drain(Duration::from_millis(10)));
+
+        tokio::time::timeout(Duration::from_secs(1), lifecycle.wait_for_shutdown())
+            .await
+            .expect("shutdown was not signalled");
+        assert!(lifecycle.is_shutting_down());
+    }
+}
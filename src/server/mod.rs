@@ -2,23 +2,48 @@
 //!
 //! Provides the main HTTP server with request counting and timing middleware.
 
+pub mod access_log;
+pub mod lifecycle;
+
 use crate::api::{create_api_router, ApiState};
-use crate::config::SharedConfig;
-use crate::metrics::{RequestMetric, SharedMetrics};
-use crate::proxy::{proxy_handler, ProxyState};
+use crate::config::{CorsConfig, ServerConfig, SharedConfig};
+use crate::metrics::{MetricSource, RequestMetric, SharedMetrics};
+use crate::proxy::upstream::spawn_health_checker;
+#[cfg(feature = "websocket")]
+use crate::proxy::websocket;
+use crate::proxy::{
+    match_route, proxy_handler, Proxied, ProxyState, SharedUpstreamPool, UpstreamPool,
+    PROXY_TARGET_HEADER,
+};
+use crate::telemetry;
 use crate::testing::SharedTester;
+use access_log::{AccessLog, AccessLogEntry};
 use axum::{
-    body::Body,
-    http::{Request, Response, StatusCode},
+    body::{Body, HttpBody},
+    http::{
+        header, Extensions, HeaderMap, HeaderName, HeaderValue, Method, Request, Response,
+        StatusCode, Version,
+    },
     middleware::{self, Next},
     response::IntoResponse,
     routing::any,
     Router,
 };
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::Utc;
+use futures::future::try_join_all;
+use lifecycle::{shutdown_signal, Lifecycle, SharedLifecycle};
+use std::future::IntoFuture;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tower_http::compression::{
+    predicate::{DefaultPredicate, Predicate},
+    CompressionLayer,
+};
 use tower_http::cors::{Any, CorsLayer};
-use tower_http::trace::TraceLayer;
+use tower_http::trace::{DefaultMakeSpan, TraceLayer};
+use tracing::{Instrument, Level};
 
 /// Server state
 #[derive(Clone)]
@@ -26,6 +51,9 @@ pub struct ServerState {
     pub config: SharedConfig,
     pub metrics: SharedMetrics,
     pub tester: SharedTester,
+    pub upstreams: SharedUpstreamPool,
+    pub lifecycle: SharedLifecycle,
+    pub access_log: Option<Arc<AccessLog>>,
 }
 
 impl ServerState {
@@ -34,13 +62,31 @@ impl ServerState {
             config,
             metrics,
             tester,
+            upstreams: Arc::new(UpstreamPool::new()),
+            lifecycle: Arc::new(Lifecycle::new()),
+            access_log: None,
         }
     }
+
+    /// Use an existing lifecycle, so the caller can trigger shutdown
+    pub fn with_lifecycle(mut self, lifecycle: SharedLifecycle) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+
+    /// Append non-API requests to an NDJSON access log
+    pub fn with_access_log(mut self, access_log: Arc<AccessLog>) -> Self {
+        self.access_log = Some(access_log);
+        self
+    }
 }
 
 /// Request timing and counting middleware
+///
+/// Non-API requests are also written to the access log, when there is one.
 pub async fn metrics_middleware(
     metrics: SharedMetrics,
+    access_log: Option<Arc<AccessLog>>,
     req: Request<Body>,
     next: Next,
 ) -> Response<Body> {
@@ -51,17 +97,45 @@ pub async fn metrics_middleware(
     // Don't record metrics for API endpoints
     let skip_metrics = path.starts_with("/api/");
 
-    let response = next.run(req).await;
+    let span = tracing::info_span!(
+        "http_request",
+        http.method = %method,
+        http.path = %path,
+        http.status_code = tracing::field::Empty,
+        latency_ms = tracing::field::Empty,
+    );
+    telemetry::set_parent(&span, req.headers());
+    let request_bytes = content_length(req.headers());
+
+    let response = next.run(req).instrument(span.clone()).await;
+    let response_bytes = HttpBody::size_hint(response.body())
+        .exact()
+        .or_else(|| content_length(response.headers()));
+
+    let latency = start.elapsed().as_secs_f64() * 1000.0;
+    let status = response.status().as_u16();
+    span.record("http.status_code", status);
+    span.record("latency_ms", latency);
 
     if !skip_metrics {
-        let latency = start.elapsed().as_secs_f64() * 1000.0;
-        let status = response.status().as_u16();
-
         let metric = RequestMetric::new(method.clone(), path.clone())
             .with_status(status)
-            .with_latency(latency);
+            .with_latency(latency)
+            .with_source(MetricSource::ServerInbound)
+            .with_body_sizes(request_bytes, response_bytes);
         metrics.record(metric);
 
+        if let Some(access_log) = &access_log {
+            access_log.send(AccessLogEntry {
+                timestamp: Utc::now(),
+                method: method.clone(),
+                path: path.clone(),
+                status,
+                latency_ms: latency,
+                proxied: response.extensions().get::<Proxied>().is_some(),
+            });
+        }
+
         tracing::debug!(
             method = %method,
             path = %path,
@@ -74,20 +148,47 @@ pub async fn metrics_middleware(
     response
 }
 
+/// Body size declared by a `Content-Length` header
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
 /// Create the main server router
 pub fn create_server_router(state: Arc<ServerState>) -> Router {
     // Create API state
-    let api_state = Arc::new(ApiState::new(
-        state.config.clone(),
-        state.metrics.clone(),
-        state.tester.clone(),
-    ));
+    let api_state = Arc::new(
+        ApiState::new(
+            state.config.clone(),
+            state.metrics.clone(),
+            state.tester.clone(),
+        )
+        .with_upstreams(state.upstreams.clone())
+        .with_lifecycle(state.lifecycle.clone()),
+    );
 
     // Create proxy state
-    let proxy_state = Arc::new(ProxyState::new(state.config.clone(), state.metrics.clone()));
+    let proxy_state = Arc::new(
+        ProxyState::new(state.config.clone(), state.metrics.clone())
+            .with_upstreams(state.upstreams.clone()),
+    );
 
     // Clone metrics for middleware
     let metrics_for_middleware = state.metrics.clone();
+    let access_log = state.access_log.clone();
+
+    // Compression follows the live config; responses that already carry a
+    // Content-Encoding (e.g. compressed by a proxy upstream) are left alone
+    let config = state.config.clone();
+    let compression = CompressionLayer::new().compress_when(DefaultPredicate::new().and(
+        move |_: StatusCode, _: Version, _: &HeaderMap, _: &Extensions| {
+            config.get().server.compression
+        },
+    ));
 
     // Create the router
     Router::new()
@@ -101,15 +202,61 @@ pub fn create_server_router(state: Arc<ServerState>) -> Router {
         )
         // Add middleware
         .layer(middleware::from_fn(move |req, next| {
-            metrics_middleware(metrics_for_middleware.clone(), req, next)
+            metrics_middleware(
+                metrics_for_middleware.clone(),
+                access_log.clone(),
+                req,
+                next,
+            )
         }))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
-        .layer(TraceLayer::new_for_http())
+        .layer(cors_layer(&state.config.get().server.cors))
+        .layer(compression)
+        // INFO-level spans, so they are exported when tower_http=info is enabled
+        .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::new().level(Level::INFO)))
+}
+
+/// CORS layer for `config`, allowing anything a list leaves open
+///
+/// Entries that aren't valid origins, methods or header names are skipped.
+fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    fn is_any(values: &[String]) -> bool {
+        values.is_empty() || values.iter().any(|v| v == "*")
+    }
+    fn parse_all<T>(values: &[String], kind: &str, parse: impl Fn(&str) -> Option<T>) -> Vec<T> {
+        values
+            .iter()
+            .filter_map(|value| {
+                let parsed = parse(value);
+                if parsed.is_none() {
+                    tracing::warn!(value = %value, "Ignoring invalid CORS {}", kind);
+                }
+                parsed
+            })
+            .collect()
+    }
+
+    let mut layer = CorsLayer::new();
+    layer = if is_any(&config.allowed_origins) {
+        layer.allow_origin(Any)
+    } else {
+        layer.allow_origin(parse_all(&config.allowed_origins, "origin", |v| {
+            HeaderValue::from_str(v).ok()
+        }))
+    };
+    layer = if is_any(&config.allowed_methods) {
+        layer.allow_methods(Any)
+    } else {
+        layer.allow_methods(parse_all(&config.allowed_methods, "method", |v| {
+            Method::from_bytes(v.as_bytes()).ok()
+        }))
+    };
+    if is_any(&config.allowed_headers) {
+        layer.allow_headers(Any)
+    } else {
+        layer.allow_headers(parse_all(&config.allowed_headers, "header", |v| {
+            HeaderName::from_bytes(v.as_bytes()).ok()
+        }))
+    }
 }
 
 /// Dev handler for root path
@@ -124,48 +271,272 @@ async fn dev_handler() -> impl IntoResponse {
 async fn proxy_or_echo(proxy_state: Arc<ProxyState>, req: Request<Body>) -> impl IntoResponse {
     let config = proxy_state.config.get();
 
-    if config.proxy.enabled && config.proxy.target.is_some() {
+    let has_target = config.proxy.target.is_some()
+        || !config.proxy.targets.is_empty()
+        || match_route(&config.proxy.routes, req.uri().path()).is_some()
+        || (config.proxy.allow_target_header && req.headers().contains_key(PROXY_TARGET_HEADER));
+
+    #[cfg(feature = "websocket")]
+    if config.proxy.enabled && has_target && websocket::is_websocket_upgrade(req.headers()) {
+        return websocket::proxy_websocket(proxy_state, req).await;
+    }
+
+    if config.proxy.enabled && has_target {
         // Forward to proxy
         proxy_handler(axum::extract::State(proxy_state), req)
             .await
             .into_response()
     } else {
-        // Echo request details
+        // Echo request details, after any injected delay/status
+        let (delay, status) = match echo_fault(req.headers(), &config.server) {
+            Ok(fault) => fault,
+            Err(message) => {
+                let response = serde_json::json!({ "error": message });
+                return (StatusCode::BAD_REQUEST, axum::Json(response)).into_response();
+            }
+        };
         let method = req.method().to_string();
         let path = req.uri().path().to_string();
-        let headers: Vec<(String, String)> = req
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
+        let headers = echo_headers(req.headers(), &config.server);
+        let base64_headers = binary_header_names(req.headers());
+
+        let body =
+            match axum::body::to_bytes(req.into_body(), config.server.max_echo_body_bytes).await {
+                Ok(body) => body,
+                Err(e) => {
+                    let status = if is_length_limit_error(&e) {
+                        StatusCode::PAYLOAD_TOO_LARGE
+                    } else {
+                        StatusCode::BAD_REQUEST
+                    };
+                    let response = serde_json::json!({ "error": e.to_string() });
+                    return (status, axum::Json(response)).into_response();
+                }
+            };
+        let (body_text, body_encoding) = match std::str::from_utf8(&body) {
+            Ok(text) => (text.to_string(), "utf8"),
+            Err(_) => (BASE64_STANDARD.encode(&body), "base64"),
+        };
 
         let response = serde_json::json!({
             "method": method,
             "path": path,
             "headers": headers,
+            "base64_headers": base64_headers,
+            "body": body_text,
+            "body_bytes": body.len(),
+            "body_encoding": body_encoding,
             "message": "Echo response from dev server"
         });
 
-        (StatusCode::OK, axum::Json(response)).into_response()
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        (status, axum::Json(response)).into_response()
+    }
+}
+
+/// Request header delaying the echo response by this many milliseconds
+pub const DELAY_HEADER: &str = "x-delay-ms";
+/// Request header choosing the echo response's status code
+pub const STATUS_HEADER: &str = "x-status";
+/// Longest delay an echo request can inject
+pub const MAX_INJECTED_DELAY_MS: u64 = 60_000;
+
+/// Delay and status for an echo response, from `X-Delay-Ms`/`X-Status` or
+/// `server.inject_delay_ms`
+fn echo_fault(
+    headers: &HeaderMap,
+    config: &ServerConfig,
+) -> Result<(Duration, StatusCode), String> {
+    let header = |name| {
+        headers
+            .get(name)
+            .map(|v: &HeaderValue| v.to_str().unwrap_or(""))
+    };
+
+    let delay_ms = match header(DELAY_HEADER) {
+        Some(value) => value
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|&ms| ms <= MAX_INJECTED_DELAY_MS)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid X-Delay-Ms: expected 0-{} milliseconds",
+                    MAX_INJECTED_DELAY_MS
+                )
+            })?,
+        None => config.inject_delay_ms.unwrap_or(0),
+    };
+    let status = match header(STATUS_HEADER) {
+        Some(value) => value
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|code| (200..600).contains(code))
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .ok_or_else(|| {
+                "Invalid X-Status: expected a status code from 200 to 599".to_string()
+            })?,
+        None => StatusCode::OK,
+    };
+
+    Ok((Duration::from_millis(delay_ms), status))
+}
+
+/// Whether reading a body failed because it exceeded the size limit
+pub(crate) fn is_length_limit_error(error: &axum::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(e) = source {
+        if e.is::<http_body_util::LengthLimitError>() {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// Render a header value for the echo response: UTF-8 as text, anything else as base64
+fn echo_header_value(value: &HeaderValue) -> String {
+    match std::str::from_utf8(value.as_bytes()) {
+        Ok(text) => text.to_string(),
+        Err(_) => BASE64_STANDARD.encode(value.as_bytes()),
+    }
+}
+
+/// Names of headers whose values are not UTF-8 and are echoed as base64
+fn binary_header_names(headers: &HeaderMap) -> Vec<String> {
+    let mut names: Vec<String> = headers
+        .iter()
+        .filter(|(_, v)| std::str::from_utf8(v.as_bytes()).is_err())
+        .map(|(k, _)| k.to_string())
+        .collect();
+    names.dedup();
+    names
+}
+
+/// Render request headers for the echo response
+fn echo_headers(headers: &HeaderMap, config: &ServerConfig) -> serde_json::Value {
+    let pairs = headers
+        .iter()
+        .map(|(k, v)| (k.to_string(), echo_header_value(v)));
+
+    if !config.echo_headers_as_object {
+        return serde_json::json!(pairs.collect::<Vec<_>>());
+    }
+
+    let mut object = serde_json::Map::new();
+    for (key, value) in pairs {
+        if !config.echo_headers_collect_duplicates {
+            object.insert(key, value.into());
+            continue;
+        }
+        match object.get_mut(&key) {
+            Some(serde_json::Value::Array(values)) => values.push(value.into()),
+            Some(existing) => *existing = serde_json::json!([existing.take(), value]),
+            None => {
+                object.insert(key, value.into());
+            }
+        }
     }
+    serde_json::Value::Object(object)
 }
 
-/// Start the HTTP server
+/// Start the HTTP server, running until SIGINT/SIGTERM or a drain completes
 pub async fn start_server(
     config: SharedConfig,
     metrics: SharedMetrics,
     tester: SharedTester,
 ) -> anyhow::Result<()> {
-    let server_config = config.get().server;
-    let addr = format!("{}:{}", server_config.host, server_config.port);
+    start_server_with_lifecycle(config, metrics, tester, Arc::new(Lifecycle::new())).await
+}
 
-    let state = Arc::new(ServerState::new(config, metrics, tester));
+/// Start the HTTP server, also stopping when `lifecycle` is shut down
+///
+/// On shutdown the listeners stop accepting connections and in-flight requests
+/// get `server.shutdown_grace_secs` to finish before they are abandoned. Queued
+/// CSV/SQLite metric writes and access log lines are flushed before returning.
+pub async fn start_server_with_lifecycle(
+    config: SharedConfig,
+    metrics: SharedMetrics,
+    tester: SharedTester,
+    lifecycle: SharedLifecycle,
+) -> anyhow::Result<()> {
+    let addrs = config.get().server.listen_addrs();
+
+    let mut state =
+        ServerState::new(config.clone(), metrics.clone(), tester).with_lifecycle(lifecycle.clone());
+    let access_log = match &config.get().server.access_log_path {
+        Some(path) => match AccessLog::spawn(path).await {
+            Ok(access_log) => Some(Arc::new(access_log)),
+            Err(e) => {
+                tracing::error!(error = %e, "Access log disabled");
+                None
+            }
+        },
+        None => None,
+    };
+    if let Some(access_log) = &access_log {
+        state = state.with_access_log(access_log.clone());
+    }
+    let state = Arc::new(state);
+    spawn_health_checker(state.upstreams.clone(), config.clone());
     let app = create_server_router(state);
 
-    tracing::info!(addr = %addr, "Starting HTTP server");
+    // Bind every address up front so a bad address fails fast
+    let mut listeners = Vec::with_capacity(addrs.len());
+    for addr in &addrs {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!(addr = %listener.local_addr()?, "Starting HTTP server");
+        listeners.push(listener);
+    }
+
+    let signalled = lifecycle.clone();
+    let signal_listener = tokio::spawn(async move {
+        shutdown_signal().await;
+        signalled.shutdown();
+    });
+
+    // Serve the same router on every listener until shutdown, letting
+    // in-flight requests finish
+    let servers = listeners.into_iter().map(|listener| {
+        let lifecycle = lifecycle.clone();
+        // Connect info lets the proxy report the client address upstream
+        axum::serve(
+            listener,
+            app.clone()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move { lifecycle.wait_for_shutdown().await })
+        .into_future()
+    });
+    let grace_secs = config.get().server.shutdown_grace_secs;
+    let grace_elapsed = async {
+        lifecycle.wait_for_shutdown().await;
+        tokio::time::sleep(Duration::from_secs(grace_secs)).await;
+    };
+    tokio::select! {
+        result = try_join_all(servers) => {
+            result?;
+        }
+        _ = grace_elapsed => {
+            tracing::warn!(grace_secs = %grace_secs, "Shutdown grace period elapsed, abandoning in-flight requests");
+        }
+    }
+    signal_listener.abort();
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    let flushed =
+        tokio::task::spawn_blocking(move || metrics.flush(Duration::from_secs(5))).await?;
+    if !flushed {
+        tracing::warn!("Timed out flushing metrics");
+    }
+    if let Some(access_log) = access_log {
+        if !access_log.flush(Duration::from_secs(5)).await {
+            tracing::warn!("Timed out flushing access log");
+        }
+    }
+    tracing::info!("Server stopped");
 
     Ok(())
 }
@@ -224,4 +595,233 @@ mod tests {
         let response = app.oneshot(request).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn test_echo_body() {
+        let mut app_config = AppConfig::default();
+        app_config.server.max_echo_body_bytes = 8;
+        let config = SharedConfig::new(app_config);
+        let metrics = create_shared_metrics(1000);
+        let tester = create_shared_tester(config.clone(), metrics.clone());
+        let app = create_server_router(Arc::new(ServerState::new(config, metrics, tester)));
+
+        let echo = |body: &'static [u8]| {
+            let request = Request::builder()
+                .uri("/echo")
+                .method("POST")
+                .body(Body::from(body))
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let response = echo(b"hello").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["body"], "hello");
+        assert_eq!(json["body_bytes"], 5);
+        assert_eq!(json["body_encoding"], "utf8");
+
+        let response = echo(&[0xff, 0x00]).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["body"], "/wA=");
+        assert_eq!(json["body_encoding"], "base64");
+
+        let response = echo(b"way too long").await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_echo_fault_injection() {
+        let config = SharedConfig::new(AppConfig::default());
+        let metrics = create_shared_metrics(1000);
+        let tester = create_shared_tester(config.clone(), metrics.clone());
+        let app = create_server_router(Arc::new(ServerState::new(config, metrics.clone(), tester)));
+
+        let request = Request::builder()
+            .uri("/flaky")
+            .header(DELAY_HEADER, "50")
+            .header(STATUS_HEADER, "503")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let recorded = metrics.get_all();
+        assert_eq!(recorded[0].status_code, Some(503));
+        assert!(recorded[0].latency_ms >= 50.0);
+
+        for (name, value) in [(STATUS_HEADER, "99"), (DELAY_HEADER, "-1")] {
+            let request = Request::builder()
+                .uri("/flaky")
+                .header(name, value)
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cors_allowed_origins() {
+        let mut app_config = AppConfig::default();
+        app_config.server.cors.allowed_origins = vec!["http://allowed.example".to_string()];
+        let config = SharedConfig::new(app_config);
+        let metrics = create_shared_metrics(1000);
+        let tester = create_shared_tester(config.clone(), metrics.clone());
+        let app = create_server_router(Arc::new(ServerState::new(config, metrics, tester)));
+
+        let request = |origin: &str| {
+            Request::builder()
+                .uri("/api/health")
+                .header(header::ORIGIN, origin)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(request("http://allowed.example"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://allowed.example"
+        );
+
+        let response = app.oneshot(request("http://evil.example")).await.unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_compression() {
+        // Upstream that serves an already-encoded body
+        let upstream = axum::Router::new()
+            .fallback(|| async { ([(header::CONTENT_ENCODING, "br")], "x".repeat(4096)) });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let metrics = create_shared_metrics(1000);
+        let tester = create_shared_tester(config.clone(), metrics.clone());
+        let app = create_server_router(Arc::new(ServerState::new(config.clone(), metrics, tester)));
+        let request = |path: &str| {
+            Request::builder()
+                .uri(path)
+                .method("POST")
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .body(Body::from("a".repeat(64 * 1024)))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/echo")).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.len() < 64 * 1024);
+
+        let mut app_config = config.get();
+        app_config.server.compression = false;
+        config.update(app_config);
+        let response = app.clone().oneshot(request("/echo")).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+
+        let mut app_config = config.get();
+        app_config.server.compression = true;
+        app_config.proxy.enabled = true;
+        app_config.proxy.target = Some(format!("http://{}", upstream_addr));
+        config.update(app_config);
+        let response = app.oneshot(request("/proxied")).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+    }
+
+    #[test]
+    fn test_echo_binary_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-text",
+            HeaderValue::from_bytes("café".as_bytes()).unwrap(),
+        );
+        headers.insert("x-raw", HeaderValue::from_bytes(&[0xff, 0xfe]).unwrap());
+
+        let json = echo_headers(&headers, &ServerConfig::default());
+        assert_eq!(
+            json,
+            serde_json::json!([["x-text", "café"], ["x-raw", "//4="]])
+        );
+        assert_eq!(binary_header_names(&headers), vec!["x-raw"]);
+    }
+
+    #[test]
+    fn test_echo_headers_as_object() {
+        let mut headers = HeaderMap::new();
+        headers.append("accept", "text/html".parse().unwrap());
+        headers.append("x-tag", "a".parse().unwrap());
+        headers.append("x-tag", "b".parse().unwrap());
+
+        let mut config = ServerConfig::default();
+        assert_eq!(
+            echo_headers(&headers, &config),
+            serde_json::json!([["accept", "text/html"], ["x-tag", "a"], ["x-tag", "b"]])
+        );
+
+        config.echo_headers_as_object = true;
+        assert_eq!(
+            echo_headers(&headers, &config),
+            serde_json::json!({"accept": "text/html", "x-tag": "b"})
+        );
+
+        config.echo_headers_collect_duplicates = true;
+        assert_eq!(
+            echo_headers(&headers, &config),
+            serde_json::json!({"accept": "text/html", "x-tag": ["a", "b"]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_bounded_by_grace_period() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut app_config = AppConfig::default();
+        app_config.server.port = port;
+        app_config.server.shutdown_grace_secs = 1;
+        let config = SharedConfig::new(app_config);
+        let metrics = create_shared_metrics(1000);
+        let tester = create_shared_tester(config.clone(), metrics.clone());
+        let lifecycle = Arc::new(Lifecycle::new());
+
+        let server = tokio::spawn(start_server_with_lifecycle(
+            config,
+            metrics,
+            tester,
+            lifecycle.clone(),
+        ));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // A request that never finishes sending keeps its connection busy
+        let mut stalled = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        tokio::io::AsyncWriteExt::write_all(&mut stalled, b"GET /slow HTTP/1.1\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        lifecycle.shutdown();
+        let stopped = tokio::time::timeout(Duration::from_secs(3), server).await;
+        assert!(stopped
+            .expect("server outlived its grace period")
+            .unwrap()
+            .is_ok());
+        assert!(!lifecycle.is_ready());
+    }
 }
@@ -0,0 +1,125 @@
+//! Telemetry module
+//!
+//! Optional OpenTelemetry trace export (behind the `otel` feature) and W3C
+//! trace context propagation. Without the feature, every function is a no-op.
+
+use axum::http::HeaderMap;
+use tracing_subscriber::{registry::Registry, Layer};
+
+/// Boxed tracing layer exporting spans to an OTLP collector
+pub type OtelLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+#[cfg(feature = "otel")]
+mod otel {
+    use super::OtelLayer;
+    use axum::http::{HeaderMap, HeaderName, HeaderValue};
+    use opentelemetry::propagation::{Extractor, Injector};
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    pub fn layer(endpoint: &str) -> anyhow::Result<OtelLayer> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                env!("CARGO_PKG_NAME"),
+            )]))
+            .build();
+
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        opentelemetry::global::set_tracer_provider(provider);
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        Ok(Box::new(tracing_opentelemetry::layer().with_tracer(tracer)))
+    }
+
+    pub fn shutdown() {
+        opentelemetry::global::shutdown_tracer_provider();
+    }
+
+    struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+    impl Injector for HeaderInjector<'_> {
+        fn set(&mut self, key: &str, value: String) {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(key.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                self.0.insert(name, value);
+            }
+        }
+    }
+
+    struct HeaderExtractor<'a>(&'a HeaderMap);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|v| v.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|k| k.as_str()).collect()
+        }
+    }
+
+    pub fn inject_context(headers: &mut HeaderMap) {
+        let cx = tracing::Span::current().context();
+        opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&cx, &mut HeaderInjector(headers));
+        });
+    }
+
+    pub fn set_parent(span: &tracing::Span, headers: &HeaderMap) {
+        let cx = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(headers))
+        });
+        span.set_parent(cx);
+    }
+}
+
+/// Create a tracing layer exporting spans to the given OTLP/HTTP endpoint
+///
+/// Returns `None` when the crate was built without the `otel` feature.
+#[cfg(feature = "otel")]
+pub fn otel_layer(endpoint: &str) -> anyhow::Result<Option<OtelLayer>> {
+    otel::layer(endpoint).map(Some)
+}
+
+/// Create a tracing layer exporting spans to the given OTLP/HTTP endpoint
+///
+/// Returns `None` when the crate was built without the `otel` feature.
+#[cfg(not(feature = "otel"))]
+pub fn otel_layer(_endpoint: &str) -> anyhow::Result<Option<OtelLayer>> {
+    Ok(None)
+}
+
+/// Flush and shut down the OpenTelemetry tracer provider
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    otel::shutdown();
+}
+
+/// Inject the current span's trace context (`traceparent`) into outgoing headers
+pub fn inject_context(headers: &mut HeaderMap) {
+    #[cfg(feature = "otel")]
+    otel::inject_context(headers);
+    #[cfg(not(feature = "otel"))]
+    let _ = headers;
+}
+
+/// Parent a span on the trace context carried by incoming headers
+pub fn set_parent(span: &tracing::Span, headers: &HeaderMap) {
+    #[cfg(feature = "otel")]
+    otel::set_parent(span, headers);
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, headers);
+}
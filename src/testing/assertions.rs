@@ -0,0 +1,122 @@
+//! Test run assertions
+//!
+//! Thresholds evaluated against a finished run so `api-check test` can gate
+//! CI pipelines on latency percentiles and success rate.
+
+use super::TestRunSummary;
+use std::fmt;
+
+/// Limits a test run must stay within
+#[derive(Debug, Clone, Default)]
+pub struct Thresholds {
+    /// Maximum 95th percentile latency in milliseconds
+    pub p95_ms: Option<f64>,
+    /// Maximum 99th percentile latency in milliseconds
+    pub p99_ms: Option<f64>,
+    /// Minimum percentage of successful requests
+    pub success_rate_pct: Option<f64>,
+    /// Maximum latency of any request in milliseconds
+    pub max_latency_ms: Option<f64>,
+}
+
+/// A threshold the run did not meet
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssertionFailure {
+    /// Name of the asserted metric
+    pub name: &'static str,
+    /// Threshold, as a human-readable bound (e.g. `<= 500ms`)
+    pub expected: String,
+    /// Measured value
+    pub actual: String,
+}
+
+impl fmt::Display for AssertionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.name, self.expected, self.actual
+        )
+    }
+}
+
+impl Thresholds {
+    /// Whether any threshold is set
+    pub fn is_empty(&self) -> bool {
+        self.p95_ms.is_none()
+            && self.p99_ms.is_none()
+            && self.success_rate_pct.is_none()
+            && self.max_latency_ms.is_none()
+    }
+
+    /// Check the summary against every threshold, returning those that failed
+    pub fn evaluate(&self, summary: &TestRunSummary) -> Vec<AssertionFailure> {
+        let latency_limits = [
+            ("p95", self.p95_ms, summary.percentile(95.0)),
+            ("p99", self.p99_ms, summary.percentile(99.0)),
+            ("max latency", self.max_latency_ms, summary.max_latency_ms),
+        ];
+
+        let mut failures: Vec<AssertionFailure> = latency_limits
+            .into_iter()
+            .filter_map(|(name, limit, actual)| {
+                let limit = limit?;
+                (actual > limit).then(|| AssertionFailure {
+                    name,
+                    expected: format!("<= {}ms", limit),
+                    actual: format!("{:.2}ms", actual),
+                })
+            })
+            .collect();
+
+        if let Some(min_rate) = self.success_rate_pct {
+            let rate = summary.success_rate();
+            if rate < min_rate {
+                failures.push(AssertionFailure {
+                    name: "success rate",
+                    expected: format!(">= {}%", min_rate),
+                    actual: format!("{:.2}%", rate),
+                });
+            }
+        }
+
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestResult;
+
+    #[test]
+    fn test_evaluate_reports_failed_thresholds() {
+        let results: Vec<TestResult> = (1..=100)
+            .map(|i| TestResult {
+                index: i,
+                success: i <= 90,
+                status_code: Some(if i <= 90 { 200 } else { 500 }),
+                latency_ms: i as f64,
+                ..Default::default()
+            })
+            .collect();
+        let summary = TestRunSummary {
+            total_requests: 100,
+            successful: 90,
+            failed: 10,
+            max_latency_ms: 100.0,
+            results,
+            ..Default::default()
+        };
+
+        let thresholds = Thresholds {
+            p95_ms: Some(96.0),
+            p99_ms: Some(50.0),
+            success_rate_pct: Some(95.0),
+            max_latency_ms: Some(100.0),
+        };
+        let failures = thresholds.evaluate(&summary);
+        let names: Vec<&str> = failures.iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["p99", "success rate"]);
+    }
+}
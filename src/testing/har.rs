@@ -0,0 +1,435 @@
+//! HAR (HTTP Archive) replay
+//!
+//! Parses a HAR file exported by a browser or proxy and replays each entry's
+//! method, URL, headers and body, optionally against a different host and
+//! with the original spacing between requests. Recorded metrics can be
+//! exported in the same format, so captured traffic can be replayed later.
+
+use super::{TestResult, TestRunSummary};
+use crate::metrics::{MetricSource, RequestMetric, SharedMetrics};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Headers that are recomputed by the client and never replayed
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length", "connection"];
+
+/// Top-level HAR document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarFile {
+    pub log: HarLog,
+}
+
+/// HAR log containing the recorded entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarLog {
+    #[serde(default = "default_har_version")]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creator: Option<HarCreator>,
+    #[serde(default)]
+    pub entries: Vec<HarEntry>,
+}
+
+fn default_har_version() -> String {
+    "1.2".to_string()
+}
+
+/// Application that wrote the HAR file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+/// A single recorded request/response exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    /// When the request was started
+    pub started_date_time: DateTime<Utc>,
+    /// Total time of the exchange in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<f64>,
+    /// The recorded request
+    pub request: HarRequest,
+    /// The recorded response, if there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<HarResponse>,
+    /// Time spent in each phase of the exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<HarTimings>,
+}
+
+/// Phase timings of an exchange in milliseconds, -1 where not measured
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarTimings {
+    /// Time queued before the request could be sent
+    #[serde(default = "not_measured")]
+    pub blocked: f64,
+    #[serde(default = "not_measured")]
+    pub dns: f64,
+    /// Time opening the connection; reqwest does not expose it, so exports use -1
+    #[serde(default = "not_measured")]
+    pub connect: f64,
+    #[serde(default)]
+    pub send: f64,
+    /// Time waiting for the response
+    #[serde(default)]
+    pub wait: f64,
+    #[serde(default)]
+    pub receive: f64,
+}
+
+fn not_measured() -> f64 {
+    -1.0
+}
+
+/// A recorded request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<HarHeader>,
+    #[serde(default)]
+    pub post_data: Option<HarPostData>,
+}
+
+/// A recorded response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    /// Body size in bytes, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_size: Option<u64>,
+}
+
+/// A recorded header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// A recorded request body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl HarFile {
+    /// Load a HAR file from disk
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read HAR file {}", path))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid HAR file {}", path))
+    }
+
+    /// Build a HAR log from recorded metrics
+    ///
+    /// Metrics only keep the method, path, status, timing and body sizes, so
+    /// entries have no headers or bodies. Relative paths are resolved against
+    /// `base_url`; coalesced repeats appear once.
+    pub fn from_metrics(metrics: &[RequestMetric], base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        let entries = metrics
+            .iter()
+            .map(|metric| {
+                let url = if metric.path.starts_with('/') {
+                    format!("{}{}", base_url, metric.path)
+                } else {
+                    metric.path.clone()
+                };
+                HarEntry {
+                    started_date_time: metric.timestamp,
+                    time: Some(metric.latency_ms),
+                    request: HarRequest {
+                        method: metric.method.clone(),
+                        url,
+                        headers: Vec::new(),
+                        post_data: None,
+                    },
+                    response: metric.status_code.map(|status| HarResponse {
+                        status,
+                        body_size: metric.response_bytes,
+                    }),
+                    // Metrics don't split sending and receiving out of the latency
+                    timings: Some(HarTimings {
+                        blocked: metric.queue_wait_ms.unwrap_or_else(not_measured),
+                        dns: not_measured(),
+                        connect: not_measured(),
+                        send: 0.0,
+                        wait: metric.latency_ms - metric.queue_wait_ms.unwrap_or(0.0),
+                        receive: 0.0,
+                    }),
+                }
+            })
+            .collect();
+
+        Self {
+            log: HarLog {
+                version: default_har_version(),
+                creator: Some(HarCreator {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                }),
+                entries,
+            },
+        }
+    }
+}
+
+/// Replace the scheme, host and port of `url` with those of `base`
+pub fn rewrite_host(url: &str, base: &str) -> Result<String> {
+    let mut url = Url::parse(url).with_context(|| format!("Invalid URL {}", url))?;
+    let base = Url::parse(base).with_context(|| format!("Invalid rewrite host {}", base))?;
+
+    url.set_scheme(base.scheme())
+        .map_err(|_| anyhow::anyhow!("Cannot rewrite scheme of {}", url))?;
+    url.set_host(base.host_str())?;
+    url.set_port(base.port())
+        .map_err(|_| anyhow::anyhow!("Cannot rewrite port of {}", url))?;
+    Ok(url.to_string())
+}
+
+/// Replays HAR entries
+pub struct HarReplayer {
+    client: Client,
+    rewrite_host: Option<String>,
+    preserve_timing: bool,
+    /// Where replayed requests are recorded, if anywhere
+    metrics: Option<SharedMetrics>,
+}
+
+impl Default for HarReplayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HarReplayer {
+    /// Create a new replayer
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            rewrite_host: None,
+            preserve_timing: false,
+            metrics: None,
+        }
+    }
+
+    /// Send requests with this client instead of a default one
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Record each replayed request as a test client metric
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Send every request to this base URL instead of the recorded host
+    pub fn with_rewrite_host(mut self, base: Option<String>) -> Self {
+        self.rewrite_host = base;
+        self
+    }
+
+    /// Wait between requests as long as the recording did
+    pub fn with_preserve_timing(mut self, preserve: bool) -> Self {
+        self.preserve_timing = preserve;
+        self
+    }
+
+    /// Replay the entries in order
+    pub async fn replay(&self, entries: &[HarEntry]) -> Result<TestRunSummary> {
+        let start = Instant::now();
+        let first_started = entries.iter().map(|e| e.started_date_time).min();
+        let mut results = Vec::with_capacity(entries.len());
+
+        for (i, entry) in entries.iter().enumerate() {
+            if let (true, Some(first)) = (self.preserve_timing, first_started) {
+                let offset = (entry.started_date_time - first)
+                    .to_std()
+                    .unwrap_or_default();
+                if let Some(wait) = offset.checked_sub(start.elapsed()) {
+                    tokio::time::sleep(wait).await;
+                }
+            }
+
+            let index = i as u32 + 1;
+            let result = match self.send(&entry.request).await {
+                // Recorded sessions include revalidations, so 3xx counts as success
+                Ok((status, ttfb_ms, total_ms)) => TestResult {
+                    index,
+                    success: (200..400).contains(&status),
+                    status_code: Some(status),
+                    latency_ms: total_ms,
+                    ttfb_ms: Some(ttfb_ms),
+                    download_ms: Some(total_ms - ttfb_ms),
+                    total_ms: Some(total_ms),
+                    ..Default::default()
+                },
+                Err(e) => TestResult {
+                    index,
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                },
+            };
+
+            tracing::debug!(
+                index = %index,
+                method = %entry.request.method,
+                url = %entry.request.url,
+                status = ?result.status_code,
+                "Replayed HAR entry"
+            );
+            if let Some(metrics) = &self.metrics {
+                let mut metric =
+                    RequestMetric::new(entry.request.method.clone(), entry.request.url.clone())
+                        .with_latency(result.latency_ms)
+                        .with_source(MetricSource::TestClient);
+                if let Some(status) = result.status_code {
+                    metric = metric.with_status(status);
+                }
+                metrics.record(metric);
+            }
+            results.push(result);
+        }
+
+        Ok(TestRunSummary::from_results(
+            results,
+            start.elapsed().as_secs_f64() * 1000.0,
+        ))
+    }
+
+    /// Send one request, returning its status, time to first byte and total time
+    async fn send(&self, request: &HarRequest) -> Result<(u16, f64, f64)> {
+        let url = match &self.rewrite_host {
+            Some(base) => rewrite_host(&request.url, base)?,
+            None => request.url.clone(),
+        };
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .with_context(|| format!("Invalid method {}", request.method))?;
+
+        let mut builder = self.client.request(method, &url);
+        for header in &request.headers {
+            // HTTP/2 pseudo-headers (`:authority`, ...) are not real headers
+            let name = header.name.to_ascii_lowercase();
+            if name.starts_with(':') || SKIPPED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            builder = builder.header(&header.name, &header.value);
+        }
+        if let Some(text) = request.post_data.as_ref().and_then(|p| p.text.clone()) {
+            builder = builder.body(text);
+        }
+
+        let start = Instant::now();
+        let response = builder.send().await?;
+        let status = response.status().as_u16();
+        let ttfb_ms = start.elapsed().as_secs_f64() * 1000.0;
+        response.bytes().await?;
+        let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        Ok((status, ttfb_ms, total_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_har_and_rewrite_host() {
+        let har: HarFile = serde_json::from_value(serde_json::json!({
+            "log": {
+                "version": "1.2",
+                "entries": [{
+                    "startedDateTime": "2024-01-01T00:00:00.000Z",
+                    "time": 12.5,
+                    "request": {
+                        "method": "POST",
+                        "url": "https://prod.example.com/api/items?page=2",
+                        "headers": [{"name": ":authority", "value": "prod.example.com"}],
+                        "postData": {"mimeType": "application/json", "text": "{}"}
+                    },
+                    "response": {"status": 201}
+                }]
+            }
+        }))
+        .unwrap();
+
+        let request = &har.log.entries[0].request;
+        assert_eq!(request.method, "POST");
+        assert_eq!(
+            request.post_data.as_ref().unwrap().text.as_deref(),
+            Some("{}")
+        );
+        assert_eq!(
+            rewrite_host(&request.url, "http://localhost:8080").unwrap(),
+            "http://localhost:8080/api/items?page=2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_and_replay_metrics() {
+        let app = axum::Router::new().route(
+            "/items",
+            axum::routing::post(|| async { axum::http::StatusCode::CREATED }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let recorded = crate::metrics::create_shared_metrics(100);
+        recorded.record(
+            RequestMetric::new("POST".to_string(), "/items".to_string())
+                .with_status(201)
+                .with_latency(4.0),
+        );
+        let har = recorded.export_har(&format!("http://{}/", addr));
+        assert_eq!(har.log.version, "1.2");
+        assert_eq!(
+            har.log.entries[0].request.url,
+            format!("http://{}/items", addr)
+        );
+        assert_eq!(har.log.entries[0].response.as_ref().unwrap().status, 201);
+        let timings = har.log.entries[0].timings.as_ref().unwrap();
+        assert_eq!((timings.connect, timings.wait), (-1.0, 4.0));
+
+        let path = std::env::temp_dir().join(format!("api-check-{}.har", uuid::Uuid::new_v4()));
+        std::fs::write(&path, serde_json::to_string(&har).unwrap()).unwrap();
+
+        let metrics = crate::metrics::create_shared_metrics(100);
+        let config = crate::config::SharedConfig::new(crate::config::AppConfig::default());
+        let tester = crate::testing::ApiTester::new(config, metrics.clone());
+        let summary = tester.run_from_har(path.to_str().unwrap()).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(summary.successful, 1);
+        assert_eq!(
+            summary.results[0].total_ms,
+            Some(summary.results[0].latency_ms)
+        );
+        let replayed = metrics.get_all();
+        assert_eq!(replayed[0].status_code, Some(201));
+        assert_eq!(replayed[0].source, MetricSource::TestClient);
+        assert_eq!(tester.history().len(), 1);
+    }
+}
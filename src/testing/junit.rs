@@ -0,0 +1,121 @@
+//! JUnit XML reports
+//!
+//! Writes a test run as a single `<testsuite>` with one `<testcase>` per
+//! request, so CI systems can show synthetic checks next to unit tests.
+
+use super::{TestResult, TestRunSummary};
+use anyhow::{Context, Result};
+use std::fmt::Write;
+
+/// Render a run as JUnit XML, naming the suite (and test class) `name`
+pub fn render(summary: &TestRunSummary, name: &str) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\"{}>",
+        escape(name),
+        summary.total_requests,
+        summary.failed,
+        summary.total_duration_ms / 1000.0,
+        summary
+            .started_at
+            .map(|at| format!(" timestamp=\"{}\"", at.format("%Y-%m-%dT%H:%M:%S")))
+            .unwrap_or_default(),
+    );
+    for result in &summary.results {
+        render_case(&mut xml, result, name);
+    }
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+/// Write a run's JUnit XML report to `path`
+pub fn write(summary: &TestRunSummary, name: &str, path: &str) -> Result<()> {
+    std::fs::write(path, render(summary, name))
+        .with_context(|| format!("Failed to write JUnit report {}", path))
+}
+
+fn render_case(xml: &mut String, result: &TestResult, class: &str) {
+    let name = match &result.operation {
+        Some(operation) => operation.clone(),
+        None => format!("request {}", result.index),
+    };
+    let _ = write!(
+        xml,
+        "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+        escape(class),
+        escape(&name),
+        result.latency_ms / 1000.0,
+    );
+    if result.success {
+        xml.push_str("/>\n");
+        return;
+    }
+
+    let status = result
+        .status_code
+        .map_or_else(|| "no response".to_string(), |s| format!("status {}", s));
+    let message = match &result.error {
+        Some(error) => format!("{}: {}", status, error),
+        None => status,
+    };
+    let _ = write!(
+        xml,
+        ">\n    <failure message=\"{0}\" type=\"RequestFailed\">{0}</failure>\n  </testcase>\n",
+        escape(&message),
+    );
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab/newline are not allowed in XML 1.0
+            c if c.is_control() && c != '\t' && c != '\n' => escaped.push('?'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_junit() {
+        let results = vec![
+            TestResult {
+                index: 1,
+                success: true,
+                status_code: Some(200),
+                latency_ms: 12.0,
+                ..Default::default()
+            },
+            TestResult {
+                index: 2,
+                success: false,
+                status_code: Some(500),
+                latency_ms: 30.0,
+                error: Some("expected <201>".to_string()),
+                ..Default::default()
+            },
+        ];
+        let summary = TestRunSummary::from_results(results, 1500.0);
+
+        let xml = render(&summary, "http://api/items?a=1&b=2");
+        assert!(xml.contains(
+            "<testsuite name=\"http://api/items?a=1&amp;b=2\" tests=\"2\" failures=\"1\" errors=\"0\" time=\"1.500\">"
+        ));
+        assert!(xml.contains("name=\"request 1\" time=\"0.012\"/>"));
+        assert!(xml.contains(
+            "<failure message=\"status 500: expected &lt;201&gt;\" type=\"RequestFailed\">"
+        ));
+        assert!(xml.ends_with("</testsuite>\n"));
+    }
+}
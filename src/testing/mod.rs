@@ -2,17 +2,38 @@
 //!
 //! Provides functionality to test APIs with configurable parameters.
 
-use crate::config::{SharedConfig, TestConfig};
-use crate::metrics::{RequestMetric, SharedMetrics};
-use anyhow::Result;
+use crate::config::{
+    apply_dns_overrides, AuthConfig, BodyType, Extraction, GraphQlRequest, SharedConfig, TestConfig,
+};
+use crate::metrics::{MetricSource, RequestMetric, SharedMetrics};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use parking_lot::Mutex;
+use rand::distributions::{Distribution, WeightedIndex};
 use reqwest::Client;
+use scenario::render_template;
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use template::render_builtins;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+
+pub mod assertions;
+pub mod har;
+pub mod junit;
+pub mod openapi;
+pub mod scenario;
+pub mod snapshot;
+pub mod template;
 
 /// Test result for a single API call
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TestResult {
     /// Request index (1-based)
     pub index: u32,
@@ -24,11 +45,403 @@ pub struct TestResult {
     pub latency_ms: f64,
     /// Error message (if failed)
     pub error: Option<String>,
+    /// Time spent opening the connection, in milliseconds
+    ///
+    /// reqwest does not expose connect time, so this is currently always
+    /// `None`; connection setup is included in `ttfb_ms`.
+    #[serde(default)]
+    pub connect_ms: Option<f64>,
+    /// Time until response headers arrived, in milliseconds (includes connection setup)
+    #[serde(default)]
+    pub ttfb_ms: Option<f64>,
+    /// Time spent reading the response body, in milliseconds
+    #[serde(default)]
+    pub download_ms: Option<f64>,
+    /// Time from sending the request to reading the whole body, in milliseconds
+    #[serde(default)]
+    pub total_ms: Option<f64>,
+    /// Index of the scenario step this call used, for weighted scenarios
+    #[serde(default)]
+    pub step: Option<usize>,
+    /// Attempts made, including retries (timings are from the last attempt)
+    #[serde(default)]
+    pub attempts: u32,
+    /// Spec operation this call exercised (e.g. `GET /items/{id}`), for OpenAPI runs
+    #[serde(default)]
+    pub operation: Option<String>,
+}
+
+/// A request a test run can issue
+#[derive(Debug, Clone)]
+struct CallTarget {
+    url: String,
+    method: reqwest::Method,
+    body: Option<String>,
+    /// Index of the scenario step this target came from
+    step: Option<usize>,
+}
+
+/// The requests a test run issues, and how calls are spread across them
+struct CallPlan {
+    targets: Vec<CallTarget>,
+    /// Weighted choice over `targets` when a scenario is configured
+    weights: Option<WeightedIndex<u32>>,
+    /// Client for this run's requests, configured from the test config
+    client: Client,
+}
+
+impl CallPlan {
+    /// Build the plan for `config`, resolving scenario paths against `target_url`
+    ///
+    /// Fails on an invalid method rather than sending something else.
+    fn new(config: &TestConfig, target_url: &str, client: Client) -> Result<Self> {
+        let parse_method = |field: &str, method: &str| {
+            reqwest::Method::from_bytes(method.as_bytes())
+                .with_context(|| format!("Invalid {} {:?}", field, method))
+        };
+        check_upload_files(&config.body_type)?;
+
+        let Some(steps) = &config.scenario else {
+            let (method, body) = match &config.graphql {
+                Some(graphql) => (reqwest::Method::POST, Some(graphql.body())),
+                None => (
+                    parse_method("test.method", &config.method)?,
+                    config.body.clone(),
+                ),
+            };
+            return Ok(Self {
+                targets: vec![CallTarget {
+                    url: target_url.to_string(),
+                    method,
+                    body,
+                    step: None,
+                }],
+                weights: None,
+                client,
+            });
+        };
+
+        let weights = WeightedIndex::new(steps.iter().map(|step| step.weight))
+            .map_err(|e| anyhow::anyhow!("Invalid scenario weights: {}", e))?;
+        let targets = steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                Ok(CallTarget {
+                    url: resolve_step_url(target_url, &step.path_or_url)?,
+                    method: parse_method(&format!("scenario[{}].method", i), &step.method)?,
+                    body: step.body.clone(),
+                    step: Some(i),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            targets,
+            weights: Some(weights),
+            client,
+        })
+    }
+
+    /// Choose the target for the next call
+    fn pick(&self) -> &CallTarget {
+        match &self.weights {
+            Some(weights) => &self.targets[weights.sample(&mut rand::thread_rng())],
+            None => &self.targets[0],
+        }
+    }
+}
+
+/// Resolve a scenario step's path against the base URL, leaving absolute URLs as-is
+///
+/// Joined as text rather than with `Url::join` so `{{var}}` placeholders survive.
+fn resolve_step_url(base: &str, path_or_url: &str) -> Result<String> {
+    if path_or_url.contains("://") {
+        return Ok(path_or_url.to_string());
+    }
+    if path_or_url.starts_with('/') {
+        let base = reqwest::Url::parse(base)
+            .map_err(|e| anyhow::anyhow!("Invalid target URL {}: {}", base, e))?;
+        return Ok(format!(
+            "{}{}",
+            base.origin().ascii_serialization(),
+            path_or_url
+        ));
+    }
+    Ok(format!("{}/{}", base.trim_end_matches('/'), path_or_url))
+}
+
+/// Timing of a single completed request
+#[derive(Debug, Clone)]
+struct RequestTiming {
+    status: u16,
+    ttfb_ms: f64,
+    total_ms: f64,
+    /// Delay requested by the server via `Retry-After` (429/503 only)
+    retry_after: Option<Duration>,
+    /// Cache validators returned by the server
+    validators: CacheValidators,
+    /// Response body, read only when `expect_body_contains` or `extract` is set
+    body: Option<String>,
+}
+
+/// Cache validators captured for conditional requests
+#[derive(Debug, Clone, Default)]
+struct CacheValidators {
+    /// `ETag`, sent back as `If-None-Match`
+    etag: Option<String>,
+    /// `Last-Modified`, sent back as `If-Modified-Since`
+    last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// State shared by all workers during a test run
+struct RunState {
+    /// Maximum number of calls in the run
+    limit: u32,
+    /// Calls left to dispatch
+    remaining: AtomicU32,
+    /// Instant after which no new calls are dispatched (duration-based runs)
+    deadline: Option<Instant>,
+    /// Instant until which dispatch is paused after a `Retry-After`
+    paused_until: Mutex<Option<Instant>>,
+    /// Total time dispatch was paused, in milliseconds
+    backoff_ms: Mutex<f64>,
+    /// Latest validators for conditional requests
+    validators: Mutex<CacheValidators>,
+    /// Variables captured by `extract`, carried across calls
+    variables: Mutex<HashMap<String, Value>>,
+    /// Earliest instant the next call may be dispatched
+    next_dispatch: Mutex<Instant>,
+    /// Interval between dispatched calls, shared by all workers
+    interval: Duration,
+}
+
+impl RunState {
+    fn new(limit: u32, interval: Duration, deadline: Option<Instant>) -> Self {
+        Self {
+            limit,
+            remaining: AtomicU32::new(limit),
+            deadline,
+            next_dispatch: Mutex::new(Instant::now()),
+            interval,
+            paused_until: Mutex::new(None),
+            backoff_ms: Mutex::new(0.0),
+            validators: Mutex::new(CacheValidators::default()),
+            variables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Pause dispatch for `delay`, extending any pause already in effect
+    fn pause_for(&self, delay: Duration) {
+        let now = Instant::now();
+        let new_until = now + delay;
+        let mut paused_until = self.paused_until.lock();
+
+        let current = paused_until.filter(|u| *u > now).unwrap_or(now);
+        if new_until > current {
+            *self.backoff_ms.lock() += (new_until - current).as_secs_f64() * 1000.0;
+            *paused_until = Some(new_until);
+        }
+    }
+
+    /// Reserve the next dispatch slot, returning when it starts
+    fn claim_slot(&self) -> Instant {
+        let mut next = self.next_dispatch.lock();
+        let slot = (*next).max(Instant::now());
+        *next = slot + self.interval;
+        slot
+    }
+
+    /// Wait out any active pause, returning early if the test is stopped
+    async fn wait_for_pause(&self, running: &AtomicBool) {
+        loop {
+            let Some(until) = *self.paused_until.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            if until <= now || !running.load(Ordering::Relaxed) {
+                return;
+            }
+            tokio::time::sleep((until - now).min(Duration::from_millis(100))).await;
+        }
+    }
+}
+
+/// Parse a `Retry-After` header value (delay-seconds or HTTP-date)
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Add the configured `Authorization` header unless `headers` already sets one
+fn apply_auth(builder: reqwest::RequestBuilder, config: &TestConfig) -> reqwest::RequestBuilder {
+    let explicit = config
+        .headers
+        .iter()
+        .any(|(key, _)| key.eq_ignore_ascii_case("authorization"));
+
+    match &config.auth {
+        _ if explicit => builder,
+        Some(AuthConfig::Bearer { token }) => builder.bearer_auth(token),
+        Some(AuthConfig::Basic { username, password }) => {
+            builder.basic_auth(username, Some(password))
+        }
+        None => builder,
+    }
+}
+
+/// Attach a rendered `body` to `builder`, encoded as `body_type` describes
+async fn apply_body(
+    builder: reqwest::RequestBuilder,
+    body_type: &BodyType,
+    body: Option<String>,
+) -> Result<reqwest::RequestBuilder> {
+    match (body_type, body) {
+        (BodyType::Raw, Some(body)) => Ok(builder.body(body)),
+        (BodyType::Json, Some(body)) => Ok(builder
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)),
+        (BodyType::Form, Some(body)) => Ok(builder.form(&form_fields(&body)?)),
+        (BodyType::Multipart { files }, body) => {
+            let mut form = reqwest::multipart::Form::new();
+            for (name, value) in body
+                .as_deref()
+                .map(form_fields)
+                .transpose()?
+                .unwrap_or_default()
+            {
+                form = form.text(name, value);
+            }
+            // Files are streamed from disk on every call
+            for (field, path) in files {
+                form = form
+                    .file(field.clone(), path)
+                    .await
+                    .with_context(|| format!("Failed to open upload file {}", path))?;
+            }
+            Ok(builder.multipart(form))
+        }
+        (_, None) => Ok(builder),
+    }
+}
+
+/// Fields of a form or multipart `body`, which must be a JSON object
+///
+/// String values are sent as-is, anything else as its JSON text.
+fn form_fields(body: &str) -> Result<Vec<(String, String)>> {
+    let Ok(Value::Object(fields)) = serde_json::from_str(body) else {
+        anyhow::bail!("Form body must be a JSON object of fields");
+    };
+    Ok(fields
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::String(value) => (name, value),
+            value => (name, value.to_string()),
+        })
+        .collect())
+}
+
+/// Fail unless every multipart upload file exists
+fn check_upload_files(body_type: &BodyType) -> Result<()> {
+    let BodyType::Multipart { files } = body_type else {
+        return Ok(());
+    };
+    for (field, path) in files {
+        if !std::path::Path::new(path).is_file() {
+            anyhow::bail!("Upload file {} for field {:?} does not exist", path, field);
+        }
+    }
+    Ok(())
+}
+
+/// Capture `extract` values from a JSON response body into `variables`
+///
+/// A path that matches nothing (or a body that is not JSON) leaves the
+/// variable set to an empty string and logs a warning.
+fn apply_extractions(extract: &[Extraction], body: &str, variables: &mut HashMap<String, Value>) {
+    let json = serde_json::from_str(body).unwrap_or(Value::Null);
+    let unmatched = scenario::extract_into(
+        extract
+            .iter()
+            .map(|e| (e.json_path.as_str(), e.var_name.as_str())),
+        &json,
+        variables,
+    );
+    for (json_path, var_name) in unmatched {
+        tracing::warn!(
+            json_path = %json_path,
+            var = %var_name,
+            "Extraction matched nothing, leaving variable empty"
+        );
+        variables.insert(var_name.to_string(), Value::String(String::new()));
+    }
+}
+
+/// The GraphQL operation calls send, unless a scenario replaces it
+fn graphql(config: &TestConfig) -> Option<&GraphQlRequest> {
+    config
+        .graphql
+        .as_ref()
+        .filter(|_| config.scenario.is_none())
+}
+
+/// First error of a GraphQL response's top-level `errors` array, if any
+fn graphql_error(body: &str) -> Option<String> {
+    let response: Value = serde_json::from_str(body).ok()?;
+    let error = response.get("errors")?.as_array()?.first()?;
+    Some(match error.get("message").and_then(Value::as_str) {
+        Some(message) => message.to_string(),
+        None => error.to_string(),
+    })
+}
+
+/// Check a response against `expect_status`, GraphQL errors and `expect_body_contains`
+///
+/// Returns a description of the first failed expectation, if any.
+fn check_expectations(config: &TestConfig, status: u16, body: Option<&str>) -> Option<String> {
+    if let Some(expected) = config.expect_status.filter(|&s| s != status) {
+        return Some(format!("expected status {}, got {}", expected, status));
+    }
+    if graphql(config).is_some() {
+        if let Some(message) = body.and_then(graphql_error) {
+            return Some(format!("GraphQL error: {}", message));
+        }
+    }
+    if let Some(needle) = &config.expect_body_contains {
+        if !body.unwrap_or_default().contains(needle.as_str()) {
+            return Some(format!("response body does not contain {:?}", needle));
+        }
+    }
+    None
+}
+
+/// Average time spent in one phase of a request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PhaseTiming {
+    /// Phase name
+    pub phase: String,
+    /// Average duration in milliseconds
+    pub avg_ms: f64,
 }
 
 /// Aggregated test run results
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TestRunSummary {
+    /// When the run started (set for runs made by `ApiTester`)
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
     /// Total number of requests made
     pub total_requests: u32,
     /// Number of successful requests
@@ -43,22 +456,201 @@ pub struct TestRunSummary {
     pub max_latency_ms: f64,
     /// Total test duration in milliseconds
     pub total_duration_ms: f64,
+    /// 95th percentile latency in milliseconds
+    #[serde(default)]
+    pub p95_latency_ms: f64,
+    /// Whether the run met the configured SLO (`slo_p95_ms`, `slo_error_rate`);
+    /// `None` when no SLO is configured
+    #[serde(default)]
+    pub met_slo: Option<bool>,
+    /// Total time dispatch was paused honoring `Retry-After`, in milliseconds
+    #[serde(default)]
+    pub backoff_ms: f64,
+    /// Number of 304 Not Modified responses (cache hits in conditional mode)
+    #[serde(default)]
+    pub not_modified_count: u32,
+    /// Connections opened before the run by `prime_connections`
+    #[serde(default)]
+    pub connections_primed: u32,
+    /// Extra attempts made by retries across all calls
+    #[serde(default)]
+    pub retries: u32,
+    /// Average latency by request index, bucketed into at most
+    /// `TREND_BUCKETS` points (one point per request for short runs)
+    #[serde(default)]
+    pub latency_over_index: Vec<f64>,
     /// Individual test results
     pub results: Vec<TestResult>,
 }
 
+impl TestRunSummary {
+    /// Summarize results sorted by index
+    pub fn from_results(results: Vec<TestResult>, total_duration_ms: f64) -> Self {
+        // Calculate summary with single iteration for efficiency
+        let total_requests = results.len() as u32;
+        let successful = results.iter().filter(|r| r.success).count() as u32;
+        let failed = total_requests - successful;
+
+        // Calculate latency stats in a single iteration
+        let (sum_latency, min_latency_ms, max_latency_ms) = results
+            .iter()
+            .map(|r| r.latency_ms)
+            .fold((0.0, f64::MAX, 0.0_f64), |(sum, min, max), lat| {
+                (sum + lat, min.min(lat), max.max(lat))
+            });
+
+        let avg_latency_ms = if results.is_empty() {
+            0.0
+        } else {
+            sum_latency / results.len() as f64
+        };
+
+        let mut summary = Self {
+            total_requests,
+            successful,
+            failed,
+            avg_latency_ms,
+            min_latency_ms: if min_latency_ms == f64::MAX {
+                0.0
+            } else {
+                min_latency_ms
+            },
+            max_latency_ms,
+            total_duration_ms,
+            p95_latency_ms: 0.0,
+            met_slo: None,
+            backoff_ms: 0.0,
+            not_modified_count: results
+                .iter()
+                .filter(|r| r.status_code == Some(304))
+                .count() as u32,
+            started_at: None,
+            connections_primed: 0,
+            retries: results.iter().map(|r| r.attempts.saturating_sub(1)).sum(),
+            latency_over_index: latency_trend(&results, TREND_BUCKETS),
+            results,
+        };
+        summary.p95_latency_ms = summary.percentile(95.0);
+        summary
+    }
+
+    /// Fraction (0..1) of requests that failed
+    pub fn error_rate(&self) -> f64 {
+        if self.total_requests == 0 {
+            return 0.0;
+        }
+        self.failed as f64 / self.total_requests as f64
+    }
+
+    /// Check the run against an SLO, or `None` if neither threshold is set
+    ///
+    /// The SLO is met only when every threshold that is set is satisfied.
+    pub fn evaluate_slo(&self, p95_ms: Option<f64>, error_rate: Option<f64>) -> Option<bool> {
+        if p95_ms.is_none() && error_rate.is_none() {
+            return None;
+        }
+        Some(
+            p95_ms.is_none_or(|limit| self.p95_latency_ms <= limit)
+                && error_rate.is_none_or(|limit| self.error_rate() <= limit),
+        )
+    }
+
+    /// Latency at the given percentile (0-100) using nearest-rank, in milliseconds
+    pub fn percentile(&self, pct: f64) -> f64 {
+        let mut latencies: Vec<f64> = self.results.iter().map(|r| r.latency_ms).collect();
+        if latencies.is_empty() {
+            return 0.0;
+        }
+        latencies.sort_by(|a, b| a.total_cmp(b));
+
+        let rank = (pct.clamp(0.0, 100.0) / 100.0 * latencies.len() as f64).ceil() as usize;
+        latencies[rank.saturating_sub(1)]
+    }
+
+    /// Percentage of requests that succeeded
+    pub fn success_rate(&self) -> f64 {
+        if self.total_requests == 0 {
+            return 0.0;
+        }
+        self.successful as f64 / self.total_requests as f64 * 100.0
+    }
+
+    /// Average time per request phase, over requests that measured it
+    ///
+    /// Phases are returned in the order they occur during a request.
+    pub fn phase_breakdown(&self) -> Vec<PhaseTiming> {
+        let average = |values: Vec<f64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+
+        let phases = [
+            (
+                "connect",
+                average(self.results.iter().filter_map(|r| r.connect_ms).collect()),
+            ),
+            (
+                "ttfb",
+                average(self.results.iter().filter_map(|r| r.ttfb_ms).collect()),
+            ),
+            (
+                "download",
+                average(self.results.iter().filter_map(|r| r.download_ms).collect()),
+            ),
+        ];
+
+        phases
+            .into_iter()
+            .filter_map(|(phase, avg)| {
+                avg.map(|avg_ms| PhaseTiming {
+                    phase: phase.to_string(),
+                    avg_ms,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Maximum number of points in `TestRunSummary::latency_over_index`
+pub const TREND_BUCKETS: usize = 50;
+
+/// Number of past run summaries kept for `/api/test/history`
+pub const HISTORY_LIMIT: usize = 20;
+
+/// Average the latencies of answered requests, in index order, into at most `buckets` points
+pub fn latency_trend(results: &[TestResult], buckets: usize) -> Vec<f64> {
+    let latencies: Vec<f64> = results
+        .iter()
+        .filter(|r| r.status_code.is_some())
+        .map(|r| r.latency_ms)
+        .collect();
+
+    if latencies.is_empty() || buckets == 0 {
+        return vec![];
+    }
+
+    let chunk_size = latencies.len().div_ceil(buckets);
+    latencies
+        .chunks(chunk_size)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect()
+}
+
 /// API Tester
 pub struct ApiTester {
     client: Client,
     config: SharedConfig,
     metrics: SharedMetrics,
     running: Arc<AtomicBool>,
+    /// Wakes workers blocked on a request or dispatch slot when the test is stopped
+    stopped: Arc<Notify>,
+    /// Summaries of the last `HISTORY_LIMIT` runs
+    history: Mutex<VecDeque<TestRunSummary>>,
 }
 
 impl ApiTester {
     /// Create a new API tester
     pub fn new(config: SharedConfig, metrics: SharedMetrics) -> Self {
-        let client = Client::builder()
+        let client = apply_dns_overrides(Client::builder(), &config.get())
             .timeout(Duration::from_secs(30))
             .build()
             .expect("Failed to create HTTP client");
@@ -68,6 +660,8 @@ impl ApiTester {
             config,
             metrics,
             running: Arc::new(AtomicBool::new(false)),
+            stopped: Arc::new(Notify::new()),
+            history: Mutex::new(VecDeque::new()),
         }
     }
 
@@ -79,6 +673,20 @@ impl ApiTester {
     /// Stop the current test run
     pub fn stop(&self) {
         self.running.store(false, Ordering::Relaxed);
+        self.stopped.notify_waiters();
+    }
+
+    /// Run `fut` to completion, or return `None` as soon as the test is stopped
+    async fn unless_stopped<F: Future>(&self, fut: F) -> Option<F::Output> {
+        // Registered before checking `running` so a concurrent stop is never missed
+        let stopped = self.stopped.notified();
+        if !self.running.load(Ordering::Relaxed) {
+            return None;
+        }
+        tokio::select! {
+            output = fut => Some(output),
+            _ = stopped => None,
+        }
     }
 
     /// Run API tests with the current configuration
@@ -92,167 +700,674 @@ impl ApiTester {
             anyhow::bail!("Test is already running");
         }
 
-        let start = Instant::now();
-        let mut results = Vec::with_capacity(test_config.num_calls as usize);
-
-        // Determine target URL
-        let app_config = self.config.get();
-        let target_url = test_config.target_url.clone().unwrap_or_else(|| {
-            format!(
-                "http://{}:{}/",
-                app_config.server.host, app_config.server.port
-            )
-        });
+        let target_url = self.target_url(&test_config);
 
-        let method: reqwest::Method = test_config.method.parse().unwrap_or(reqwest::Method::GET);
+        let plan = match self
+            .client_for(&test_config)
+            .and_then(|client| CallPlan::new(&test_config, &target_url, client))
+        {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.running.store(false, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
 
         tracing::info!(
             target = %target_url,
-            method = %method,
+            method = %test_config.method,
+            scenario_steps = ?test_config.scenario.as_ref().map(Vec::len),
             num_calls = %test_config.num_calls,
+            duration_secs = ?test_config.duration_secs,
+            concurrency = %test_config.concurrency,
             frequency_ms = %test_config.frequency_ms,
             "Starting API test"
         );
 
-        for i in 0..test_config.num_calls {
-            if !self.running.load(Ordering::Relaxed) {
-                tracing::info!("Test stopped by user");
-                break;
-            }
+        let connections_primed = if test_config.prime_connections {
+            self.prime_connections(&plan.client, &target_url, test_config.concurrency.max(1))
+                .await
+        } else {
+            0
+        };
 
-            let result = self
-                .make_request(&target_url, method.clone(), &test_config)
-                .await;
+        let start = Instant::now();
+        let started_at = Utc::now();
 
-            let test_result = match result {
-                Ok((status, latency)) => {
-                    // Record metric
-                    let metric = RequestMetric::new(method.to_string(), target_url.clone())
-                        .with_status(status)
-                        .with_latency(latency);
-                    self.metrics.record(metric);
+        // Workers share a countdown of remaining calls so exactly `num_calls`
+        // requests are issued in total, and a dispatch schedule so calls start
+        // `frequency_ms` apart regardless of concurrency. Duration-based runs
+        // also stop dispatching at the deadline, whichever limit comes first.
+        let limit = match (test_config.duration_secs, test_config.num_calls) {
+            (Some(_), 0) => u32::MAX,
+            (_, num_calls) => num_calls,
+        };
+        let deadline = test_config
+            .duration_secs
+            .map(|secs| start + Duration::from_secs(secs));
+        let run_state = RunState::new(limit, dispatch_interval(&test_config), deadline);
+        let workers = (0..test_config.concurrency.max(1))
+            .map(|_| self.run_worker(&run_state, &plan, &test_config));
+        let mut results: Vec<TestResult> = join_all(workers).await.into_iter().flatten().collect();
+        results.sort_by_key(|r| r.index);
 
-                    TestResult {
-                        index: i + 1,
-                        success: (200..300).contains(&status),
-                        status_code: Some(status),
-                        latency_ms: latency,
-                        error: None,
-                    }
-                }
-                Err(e) => {
-                    let latency = 0.0;
-                    let metric = RequestMetric::new(method.to_string(), target_url.clone())
-                        .with_latency(latency);
-                    self.metrics.record(metric);
+        self.running.store(false, Ordering::Relaxed);
 
-                    TestResult {
-                        index: i + 1,
-                        success: false,
-                        status_code: None,
-                        latency_ms: latency,
-                        error: Some(e.to_string()),
-                    }
-                }
-            };
+        let mut summary = TestRunSummary {
+            started_at: Some(started_at),
+            backoff_ms: *run_state.backoff_ms.lock(),
+            connections_primed,
+            ..TestRunSummary::from_results(results, start.elapsed().as_secs_f64() * 1000.0)
+        };
+        summary.met_slo = summary.evaluate_slo(test_config.slo_p95_ms, test_config.slo_error_rate);
 
-            results.push(test_result);
+        tracing::info!(
+            total = %summary.total_requests,
+            successful = %summary.successful,
+            failed = %summary.failed,
+            avg_latency = %summary.avg_latency_ms,
+            met_slo = ?summary.met_slo,
+            "Test completed"
+        );
 
-            // Wait between requests (unless it's the last one)
-            if i < test_config.num_calls - 1 && test_config.frequency_ms > 0 {
-                tokio::time::sleep(Duration::from_millis(test_config.frequency_ms)).await;
+        if let Some(path) = &test_config.save_results_path {
+            match save_summary(&summary, path).await {
+                Ok(file) => tracing::info!(path = %file.display(), "Saved test results"),
+                Err(e) => tracing::error!(error = %e, "Failed to save test results"),
             }
         }
 
-        self.running.store(false, Ordering::Relaxed);
-
-        // Calculate summary with single iteration for efficiency
-        let total_requests = results.len() as u32;
-        let successful = results.iter().filter(|r| r.success).count() as u32;
-        let failed = total_requests - successful;
-
-        // Calculate latency stats in a single iteration
-        let (sum_latency, min_latency_ms, max_latency_ms) = results
-            .iter()
-            .map(|r| r.latency_ms)
-            .fold((0.0, f64::MAX, 0.0_f64), |(sum, min, max), lat| {
-                (sum + lat, min.min(lat), max.max(lat))
-            });
+        self.remember(&summary);
+        Ok(summary)
+    }
 
-        let avg_latency_ms = if results.is_empty() {
-            0.0
-        } else {
-            sum_latency / results.len() as f64
-        };
+    /// Replay the requests of a HAR file in order, recording them as test metrics
+    ///
+    /// Each entry's method, URL, headers and body are sent as recorded.
+    pub async fn run_from_har(&self, path: &str) -> Result<TestRunSummary> {
+        let har = har::HarFile::load(path)?;
+        tracing::info!(entries = %har.log.entries.len(), path = %path, "Replaying HAR as a test run");
 
+        let started_at = Utc::now();
+        let summary = har::HarReplayer::new()
+            .with_client(self.client.clone())
+            .with_metrics(self.metrics.clone())
+            .replay(&har.log.entries)
+            .await?;
         let summary = TestRunSummary {
-            total_requests,
-            successful,
-            failed,
-            avg_latency_ms,
-            min_latency_ms: if min_latency_ms == f64::MAX {
-                0.0
-            } else {
-                min_latency_ms
-            },
-            max_latency_ms,
-            total_duration_ms: start.elapsed().as_secs_f64() * 1000.0,
-            results,
+            started_at: Some(started_at),
+            ..summary
         };
 
-        tracing::info!(
-            total = %total_requests,
-            successful = %successful,
-            failed = %failed,
-            avg_latency = %avg_latency_ms,
-            "Test completed"
-        );
-
+        self.remember(&summary);
         Ok(summary)
     }
 
-    /// Make a single HTTP request
-    async fn make_request(
+    /// Call every operation of an OpenAPI spec once against `base_url`
+    ///
+    /// Path parameters and JSON bodies come from the spec's examples;
+    /// operations with a path parameter lacking one are logged and skipped.
+    pub async fn run_from_openapi(
         &self,
-        url: &str,
-        method: reqwest::Method,
-        config: &TestConfig,
-    ) -> Result<(u16, f64)> {
-        let start = Instant::now();
+        spec_path: &str,
+        base_url: &str,
+    ) -> Result<TestRunSummary> {
+        let spec = openapi::OpenApiSpec::load(spec_path)?;
+        tracing::info!(spec = %spec_path, base_url = %base_url, "Smoke-testing OpenAPI operations");
 
-        let mut builder = self.client.request(method, url);
+        let started_at = Utc::now();
+        let summary = openapi::OpenApiRunner::new()
+            .with_client(self.client.clone())
+            .with_metrics(self.metrics.clone())
+            .run(&spec, base_url)
+            .await?;
+        let summary = TestRunSummary {
+            started_at: Some(started_at),
+            ..summary
+        };
 
-        // Add custom headers
-        for (key, value) in &config.headers {
-            builder = builder.header(key, value);
-        }
+        self.remember(&summary);
+        Ok(summary)
+    }
 
-        // Add body for POST/PUT requests
-        if let Some(body) = &config.body {
-            builder = builder.body(body.clone());
-            builder = builder.header("Content-Type", "application/json");
+    /// Keep a run's summary in the history
+    fn remember(&self, summary: &TestRunSummary) {
+        let mut history = self.history.lock();
+        if history.len() == HISTORY_LIMIT {
+            history.pop_front();
         }
+        history.push_back(summary.clone());
+    }
 
-        let response = builder.send().await?;
-        let status = response.status().as_u16();
-        let latency = start.elapsed().as_secs_f64() * 1000.0;
-
-        Ok((status, latency))
+    /// Summaries of the most recent runs, oldest first
+    pub fn history(&self) -> Vec<TestRunSummary> {
+        self.history.lock().iter().cloned().collect()
     }
-}
 
-/// Shared API tester
-pub type SharedTester = Arc<ApiTester>;
+    /// URL a run with `config` targets, defaulting to this server
+    fn target_url(&self, config: &TestConfig) -> String {
+        config.target_url.clone().unwrap_or_else(|| {
+            let server = self.config.get().server;
+            format!("http://{}:{}/", server.host, server.port)
+        })
+    }
 
-/// Create a shared API tester
-pub fn create_shared_tester(config: SharedConfig, metrics: SharedMetrics) -> SharedTester {
-    Arc::new(ApiTester::new(config, metrics))
-}
+    /// Describe what a run with `config` would send, without sending anything
+    ///
+    /// URLs, headers and bodies are shown as the first call would render them;
+    /// later calls get fresh `{{uuid}}`/`{{random:..}}` values. Credentials from
+    /// `auth` are redacted.
+    pub fn describe_plan(&self, config: &TestConfig) -> String {
+        let mut plan = String::new();
+        let target_url = self.target_url(config);
+        let render =
+            |template: &str| render_builtins(&render_template(template, &HashMap::new()), 1);
+        let mut line = |text: String| {
+            plan.push_str(&text);
+            plan.push('\n');
+        };
 
-#[cfg(test)]
-mod tests {
+        match &config.scenario {
+            None => match graphql(config) {
+                Some(graphql) => {
+                    line(format!("Request: POST {} (GraphQL)", render(&target_url)));
+                    line(format!("Body (Json): {}", render(&graphql.body())));
+                }
+                None => {
+                    line(format!(
+                        "Request: {} {}",
+                        config.method,
+                        render(&target_url)
+                    ));
+                    if let Some(body) = &config.body {
+                        line(format!("Body ({:?}): {}", config.body_type, render(body)));
+                    }
+                }
+            },
+            Some(steps) => {
+                let total: u32 = steps.iter().map(|step| step.weight).sum();
+                line(format!("Scenario ({} steps):", steps.len()));
+                for step in steps {
+                    let url = resolve_step_url(&target_url, &step.path_or_url)
+                        .map(|url| render(&url))
+                        .unwrap_or_else(|e| format!("<{}>", e));
+                    line(format!(
+                        "  {:>5.1}%  {} {}",
+                        step.weight as f64 * 100.0 / total.max(1) as f64,
+                        step.method,
+                        url
+                    ));
+                    if let Some(body) = &step.body {
+                        line(format!(
+                            "          Body ({:?}): {}",
+                            config.body_type,
+                            render(body)
+                        ));
+                    }
+                }
+            }
+        }
+        if let BodyType::Multipart { files } = &config.body_type {
+            for (field, path) in files {
+                line(format!("Upload: {} = {}", field, path));
+            }
+        }
+
+        let explicit_auth = config
+            .headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("authorization"));
+        let auth = match &config.auth {
+            _ if explicit_auth => None,
+            Some(AuthConfig::Bearer { .. }) => Some("Bearer <redacted>"),
+            Some(AuthConfig::Basic { .. }) => Some("Basic <redacted>"),
+            None => None,
+        };
+        if !config.headers.is_empty() || auth.is_some() {
+            line("Headers:".to_string());
+            for (key, value) in &config.headers {
+                line(format!("  {}: {}", key, render(value)));
+            }
+            if let Some(auth) = auth {
+                line(format!("  Authorization: {}", auth));
+            }
+        }
+
+        let calls = match (config.num_calls, config.duration_secs) {
+            (0, Some(secs)) => format!("unlimited, for {}s", secs),
+            (calls, Some(secs)) => format!("{}, stopping after {}s", calls, secs),
+            (calls, None) => calls.to_string(),
+        };
+        line(format!("Calls: {}", calls));
+        let interval = dispatch_interval(config);
+        let rate = if interval.is_zero() {
+            "as fast as possible".to_string()
+        } else {
+            format!(
+                "one call every {}ms (at most {:.1}/s)",
+                interval.as_millis(),
+                1.0 / interval.as_secs_f64()
+            )
+        };
+        line(format!(
+            "Rate: {}, concurrency {}",
+            rate,
+            config.concurrency.max(1)
+        ));
+        if config.duration_secs.is_none() && config.num_calls > 0 {
+            let secs = interval.as_secs_f64() * (config.num_calls - 1) as f64;
+            line(format!("Estimated duration: at least {:.1}s", secs));
+        }
+
+        line(format!(
+            "Timeout: {}s per call, retries: {}",
+            config.timeout_secs, config.retries
+        ));
+        if let Some(status) = config.expect_status {
+            line(format!("Expect status: {}", status));
+        }
+        if let Some(text) = &config.expect_body_contains {
+            line(format!("Expect body contains: {}", text));
+        }
+        plan
+    }
+
+    /// Client for a run with `config`
+    ///
+    /// Shares the tester's connection pool unless redirects are disabled, which
+    /// needs a client with its own redirect policy, or cookies are enabled,
+    /// which gives each run a fresh cookie jar.
+    fn client_for(&self, config: &TestConfig) -> Result<Client> {
+        if config.follow_redirects && !config.enable_cookies {
+            return Ok(self.client.clone());
+        }
+        let mut builder = apply_dns_overrides(Client::builder(), &self.config.get())
+            .timeout(Duration::from_secs(30))
+            .cookie_store(config.enable_cookies);
+        if !config.follow_redirects {
+            builder = builder.redirect(reqwest::redirect::Policy::none());
+        }
+        builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))
+    }
+
+    /// Warm the connection pool with `count` concurrent untimed `HEAD` requests
+    ///
+    /// Any response leaves an idle keep-alive connection behind, so the first
+    /// measured requests skip connection setup. Returns the connections opened.
+    async fn prime_connections(&self, client: &Client, target_url: &str, count: u32) -> u32 {
+        let requests = (0..count).map(|_| client.head(target_url).send());
+        let primed = join_all(requests)
+            .await
+            .into_iter()
+            .filter_map(|r| r.ok())
+            .count() as u32;
+
+        tracing::debug!(primed = %primed, requested = %count, "Primed connections");
+        primed
+    }
+
+    /// Issue requests until the shared countdown reaches zero or the test is stopped
+    async fn run_worker(
+        &self,
+        run_state: &RunState,
+        plan: &CallPlan,
+        test_config: &TestConfig,
+    ) -> Vec<TestResult> {
+        let mut results = Vec::new();
+
+        loop {
+            if !self.running.load(Ordering::Relaxed) {
+                tracing::info!("Test stopped by user");
+                break;
+            }
+
+            // Honor any server-requested pause before dispatching
+            run_state.wait_for_pause(&self.running).await;
+            if !self.running.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            // Claim the next call, if any are left
+            let Ok(prev) =
+                run_state
+                    .remaining
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            else {
+                break;
+            };
+            let index = run_state.limit - prev + 1;
+
+            // Wait for this call's dispatch slot
+            let slot = run_state.claim_slot();
+            if run_state.deadline.is_some_and(|deadline| slot >= deadline) {
+                break;
+            }
+            if self
+                .unless_stopped(tokio::time::sleep_until(slot.into()))
+                .await
+                .is_none()
+            {
+                continue;
+            }
+
+            let validators = if test_config.conditional {
+                run_state.validators.lock().clone()
+            } else {
+                CacheValidators::default()
+            };
+
+            let variables = if test_config.extract.is_empty() {
+                HashMap::new()
+            } else {
+                run_state.variables.lock().clone()
+            };
+
+            let target = plan.pick();
+            let Some((result, attempts)) = self
+                .unless_stopped(self.make_request_with_retries(
+                    &plan.client,
+                    target,
+                    test_config,
+                    &validators,
+                    &variables,
+                    index,
+                ))
+                .await
+            else {
+                continue;
+            };
+
+            let test_result = match result {
+                Ok(timing) => {
+                    let status = timing.status;
+                    let latency = timing.total_ms;
+
+                    if let Some(delay) =
+                        timing.retry_after.filter(|_| test_config.honor_retry_after)
+                    {
+                        tracing::info!(
+                            status = %status,
+                            delay_ms = %delay.as_millis(),
+                            "Pausing dispatch for Retry-After"
+                        );
+                        run_state.pause_for(delay);
+                    }
+
+                    if test_config.conditional && !timing.validators.is_empty() {
+                        *run_state.validators.lock() = timing.validators.clone();
+                    }
+
+                    if let Some(body) = timing
+                        .body
+                        .as_deref()
+                        .filter(|_| !test_config.extract.is_empty())
+                    {
+                        apply_extractions(
+                            &test_config.extract,
+                            body,
+                            &mut run_state.variables.lock(),
+                        );
+                    }
+
+                    // Record metric
+                    let metric = RequestMetric::new(target.method.to_string(), target.url.clone())
+                        .with_status(status)
+                        .with_latency(latency)
+                        .with_source(MetricSource::TestClient);
+                    self.metrics.record(metric);
+
+                    // A 304 is the expected answer to a conditional request
+                    let not_modified = test_config.conditional && status == 304;
+                    let status_ok = test_config.expect_status.is_some()
+                        || (200..300).contains(&status)
+                        || not_modified;
+                    let error = check_expectations(test_config, status, timing.body.as_deref());
+
+                    TestResult {
+                        index,
+                        success: status_ok && error.is_none(),
+                        status_code: Some(status),
+                        latency_ms: latency,
+                        error,
+                        connect_ms: None,
+                        ttfb_ms: Some(timing.ttfb_ms),
+                        download_ms: Some(timing.total_ms - timing.ttfb_ms),
+                        total_ms: Some(timing.total_ms),
+                        step: target.step,
+                        attempts,
+                        operation: None,
+                    }
+                }
+                Err(e) => {
+                    let latency = 0.0;
+                    let metric = RequestMetric::new(target.method.to_string(), target.url.clone())
+                        .with_latency(latency)
+                        .with_source(MetricSource::TestClient);
+                    self.metrics.record(metric);
+
+                    TestResult {
+                        index,
+                        success: false,
+                        status_code: None,
+                        latency_ms: latency,
+                        error: Some(e.to_string()),
+                        step: target.step,
+                        attempts,
+                        ..Default::default()
+                    }
+                }
+            };
+
+            results.push(test_result);
+        }
+
+        results
+    }
+
+    /// Make a request, retrying connection errors and `retry_on_status` responses
+    ///
+    /// Returns the last attempt's outcome and the number of attempts made.
+    async fn make_request_with_retries(
+        &self,
+        client: &Client,
+        target: &CallTarget,
+        config: &TestConfig,
+        validators: &CacheValidators,
+        variables: &HashMap<String, Value>,
+        index: u32,
+    ) -> (Result<RequestTiming>, u32) {
+        let mut attempts = 1;
+        loop {
+            let result = self
+                .make_request(client, target, config, validators, variables, index)
+                .await;
+            let retryable = match &result {
+                Ok(timing) => config.retry_on_status.contains(&timing.status),
+                Err(_) => true,
+            };
+            if !retryable || attempts > config.retries {
+                return (result, attempts);
+            }
+
+            let delay = config
+                .retry_backoff
+                .delay(config.retry_backoff_ms, attempts);
+            tracing::debug!(
+                url = %target.url,
+                attempt = %attempts,
+                delay_ms = %delay.as_millis(),
+                "Retrying request"
+            );
+            tokio::time::sleep(delay).await;
+            attempts += 1;
+        }
+    }
+
+    /// Make call number `index`, filling `{{var}}` placeholders from `variables`,
+    /// then built-in placeholders like `{{i}}` and `{{uuid}}`
+    async fn make_request(
+        &self,
+        client: &Client,
+        target: &CallTarget,
+        config: &TestConfig,
+        validators: &CacheValidators,
+        variables: &HashMap<String, Value>,
+        index: u32,
+    ) -> Result<RequestTiming> {
+        let start = Instant::now();
+        let render = |template: &str| render_builtins(&render_template(template, variables), index);
+
+        let url = render(&target.url);
+        let mut builder = client
+            .request(target.method.clone(), url)
+            .timeout(Duration::from_secs(config.timeout_secs));
+
+        // Add custom headers
+        for (key, value) in &config.headers {
+            builder = builder.header(key, render(value));
+        }
+        builder = apply_auth(builder, config);
+
+        // Revalidate with the validators from an earlier response
+        if let Some(etag) = &validators.etag {
+            builder = builder.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            builder = builder.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        // Add body for POST/PUT requests; GraphQL envelopes are always JSON
+        let body = target.body.as_deref().map(render);
+        let body_type = match graphql(config) {
+            Some(_) => &BodyType::Json,
+            None => &config.body_type,
+        };
+        builder = apply_body(builder, body_type, body).await?;
+
+        let timeout_error = |e: reqwest::Error| {
+            if e.is_timeout() {
+                anyhow::anyhow!("timeout after {}s", config.timeout_secs)
+            } else {
+                e.into()
+            }
+        };
+
+        let response = builder.send().await.map_err(timeout_error)?;
+        let status = response.status().as_u16();
+        let retry_after = if status == 429 || status == 503 {
+            response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after)
+        } else {
+            None
+        };
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let validators = CacheValidators {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        };
+        let ttfb_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        // Read the body so download time is measured and the connection can be reused
+        let bytes = response.bytes().await.map_err(timeout_error)?;
+        let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+        let body = (config.expect_body_contains.is_some()
+            || !config.extract.is_empty()
+            || graphql(config).is_some())
+        .then(|| String::from_utf8_lossy(&bytes).into_owned());
+
+        Ok(RequestTiming {
+            status,
+            ttfb_ms,
+            total_ms,
+            retry_after,
+            validators,
+            body,
+        })
+    }
+}
+
+/// Time between dispatched calls: `frequency_ms`, stretched to honor `max_rps`
+///
+/// The shared dispatch schedule then acts as a token bucket holding a single
+/// token, so the ceiling holds at any concurrency without allowing bursts.
+fn dispatch_interval(config: &TestConfig) -> Duration {
+    let interval = Duration::from_millis(config.frequency_ms);
+    match config.max_rps {
+        Some(rps) if rps > 0 => interval.max(Duration::from_secs(1) / rps),
+        _ => interval,
+    }
+}
+
+/// Write `summary` as JSON to `path`, or to a timestamped file inside it when
+/// `path` is a directory, returning the file written
+///
+/// Timestamped files are never overwritten: runs starting in the same
+/// millisecond get a numeric suffix.
+async fn save_summary(summary: &TestRunSummary, path: &str) -> Result<std::path::PathBuf> {
+    let json = serde_json::to_string_pretty(summary)?;
+    let dir = std::path::Path::new(path);
+    if !dir.is_dir() {
+        tokio::fs::write(dir, json)
+            .await
+            .with_context(|| format!("Failed to write {}", path))?;
+        return Ok(dir.to_path_buf());
+    }
+
+    let started_at = summary.started_at.unwrap_or_else(Utc::now);
+    let stem = format!("test-run-{}", started_at.format("%Y%m%d-%H%M%S-%3f"));
+    let mut attempt = 0u32;
+    loop {
+        let file = match attempt {
+            0 => dir.join(format!("{}.json", stem)),
+            n => dir.join(format!("{}-{}.json", stem, n)),
+        };
+        let opened = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&file)
+            .await;
+        match opened {
+            Ok(mut out) => {
+                let written = async {
+                    out.write_all(json.as_bytes()).await?;
+                    out.flush().await
+                };
+                written
+                    .await
+                    .with_context(|| format!("Failed to write {}", file.display()))?;
+                return Ok(file);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", file.display()))
+            }
+        }
+    }
+}
+
+/// Shared API tester
+pub type SharedTester = Arc<ApiTester>;
+
+/// Create a shared API tester
+pub fn create_shared_tester(config: SharedConfig, metrics: SharedMetrics) -> SharedTester {
+    Arc::new(ApiTester::new(config, metrics))
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
-    use crate::config::AppConfig;
+    use crate::config::{AppConfig, RetryBackoff, WeightedStep};
     use crate::metrics::create_shared_metrics;
 
     #[test]
@@ -264,6 +1379,803 @@ mod tests {
         assert!(!tester.is_running());
     }
 
+    /// Start a local server that answers every request with 200
+    async fn spawn_ok_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().fallback(|| async { "ok" });
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn test_exact_total_across_concurrency() {
+        let target = spawn_ok_server().await;
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+
+        for concurrency in [1, 3, 8, 40] {
+            let test_config = TestConfig {
+                num_calls: 17,
+                frequency_ms: 0,
+                concurrency,
+                target_url: Some(target.clone()),
+                ..Default::default()
+            };
+
+            let summary = tester.run_with_config(test_config).await.unwrap();
+            assert_eq!(summary.total_requests, 17, "concurrency {}", concurrency);
+
+            let indices: Vec<u32> = summary.results.iter().map(|r| r.index).collect();
+            assert_eq!(indices, (1..=17).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_interval_shared_across_workers() {
+        let target = spawn_ok_server().await;
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+
+        let start = Instant::now();
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 6,
+                frequency_ms: 40,
+                concurrency: 6,
+                target_url: Some(target),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.total_requests, 6);
+        // Six calls 40ms apart span at least 200ms, even with six workers
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_auth_header() {
+        let authorization = |config: &TestConfig| {
+            let builder = config
+                .headers
+                .iter()
+                .fold(Client::new().get("http://localhost/"), |b, (k, v)| {
+                    b.header(k, v)
+                });
+            apply_auth(builder, config)
+                .build()
+                .unwrap()
+                .headers()
+                .get("authorization")
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+
+        let mut config: TestConfig = toml::from_str(
+            r#"auth = { type = "basic", username = "aladdin", password = "opensesame" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            authorization(&config).as_deref(),
+            Some("Basic YWxhZGRpbjpvcGVuc2VzYW1l")
+        );
+
+        config.auth = Some(AuthConfig::Bearer {
+            token: "abc".to_string(),
+        });
+        assert_eq!(authorization(&config).as_deref(), Some("Bearer abc"));
+
+        // An explicit header takes precedence
+        config.headers = vec![("Authorization".to_string(), "Token xyz".to_string())];
+        assert_eq!(authorization(&config).as_deref(), Some("Token xyz"));
+    }
+
+    #[tokio::test]
+    async fn test_body_and_status_expectations() {
+        let target = spawn_ok_server().await;
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let run = |expect_status, expect_body_contains: &str| TestConfig {
+            num_calls: 2,
+            frequency_ms: 0,
+            target_url: Some(target.clone()),
+            expect_status,
+            expect_body_contains: Some(expect_body_contains.to_string()),
+            ..Default::default()
+        };
+
+        let summary = tester.run_with_config(run(Some(200), "ok")).await.unwrap();
+        assert_eq!(summary.successful, 2);
+        let result = &summary.results[0];
+        assert_eq!(result.total_ms, Some(result.latency_ms));
+        assert!(result.ttfb_ms.unwrap() <= result.latency_ms);
+        assert_eq!(result.connect_ms, None);
+
+        let summary = tester.run_with_config(run(None, "missing")).await.unwrap();
+        assert_eq!(summary.failed, 2);
+        assert!(summary.results[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("does not contain"));
+
+        let summary = tester.run_with_config(run(Some(404), "ok")).await.unwrap();
+        assert_eq!(summary.failed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_errors_fail_calls() {
+        // Answers 200 either way, with an `errors` array for unknown ids
+        let app = axum::Router::new().route(
+            "/graphql",
+            axum::routing::post(|axum::Json(request): axum::Json<Value>| async move {
+                assert!(request["query"].as_str().unwrap().starts_with("query"));
+                axum::Json(match request["variables"]["id"].as_u64() {
+                    Some(1) => serde_json::json!({"data": {"item": {"id": 1}}}),
+                    _ => serde_json::json!({"data": null, "errors": [{"message": "not found"}]}),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let tester = ApiTester::new(
+            SharedConfig::new(AppConfig::default()),
+            create_shared_metrics(1000),
+        );
+        let run = |id: u64| TestConfig {
+            num_calls: 1,
+            frequency_ms: 0,
+            target_url: Some(format!("http://{}/graphql", addr)),
+            graphql: Some(GraphQlRequest {
+                query: "query($id: Int!) { item(id: $id) { id } }".to_string(),
+                variables: Some(serde_json::json!({ "id": id })),
+            }),
+            ..Default::default()
+        };
+
+        let summary = tester.run_with_config(run(1)).await.unwrap();
+        assert_eq!(summary.successful, 1);
+
+        let summary = tester.run_with_config(run(2)).await.unwrap();
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.results[0].status_code, Some(200));
+        assert_eq!(
+            summary.results[0].error.as_deref(),
+            Some("GraphQL error: not found")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cookies_persist_across_calls() {
+        // Like httpbin's /cookies/set + /cookies: set a session cookie, echo what was sent
+        let app = axum::Router::new().fallback(|headers: axum::http::HeaderMap| async move {
+            let sent = headers
+                .get("cookie")
+                .map_or("none".to_string(), |c| c.to_str().unwrap().to_string());
+            (
+                [("set-cookie", "session=abc; Path=/")],
+                format!("cookies: {}", sent),
+            )
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}/cookies", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let run = |enable_cookies| TestConfig {
+            num_calls: 2,
+            frequency_ms: 1,
+            target_url: Some(target.clone()),
+            expect_body_contains: Some("session=abc".to_string()),
+            enable_cookies,
+            ..Default::default()
+        };
+
+        // Only the second call sends the cookie the first one received
+        let summary = tester.run_with_config(run(true)).await.unwrap();
+        assert_eq!(summary.successful, 1);
+        assert!(!summary.results[0].success);
+
+        let summary = tester.run_with_config(run(false)).await.unwrap();
+        assert_eq!(summary.successful, 0);
+    }
+
+    #[tokio::test]
+    async fn test_form_and_multipart_bodies() {
+        // Echo the request's content type and body back
+        let app = axum::Router::new().fallback(
+            |headers: axum::http::HeaderMap, body: String| async move {
+                format!("{}\n{}", headers["content-type"].to_str().unwrap(), body)
+            },
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let upload = std::env::temp_dir().join(format!("api-check-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&upload, "file contents").unwrap();
+        let upload_path = upload.to_str().unwrap().to_string();
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let run = |body_type, expect_body_contains: &str| TestConfig {
+            num_calls: 1,
+            method: "POST".to_string(),
+            target_url: Some(target.clone()),
+            body: Some(r#"{"name": "x", "n": 2}"#.to_string()),
+            body_type,
+            expect_body_contains: Some(expect_body_contains.to_string()),
+            ..Default::default()
+        };
+
+        let summary = tester
+            .run_with_config(run(BodyType::Form, "x-www-form-urlencoded\nn=2&name=x"))
+            .await
+            .unwrap();
+        assert_eq!(summary.successful, 1);
+
+        let multipart = |files| BodyType::Multipart { files };
+        let files = vec![("doc".to_string(), upload_path.clone())];
+        for needle in [
+            "multipart/form-data; boundary=",
+            "name=\"n\"",
+            "file contents",
+        ] {
+            let summary = tester
+                .run_with_config(run(multipart(files.clone()), needle))
+                .await
+                .unwrap();
+            assert_eq!(summary.successful, 1, "missing {}", needle);
+        }
+
+        std::fs::remove_file(&upload).unwrap();
+        let error = tester
+            .run_with_config(run(multipart(files), "file contents"))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("does not exist"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_method_fails_run() {
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(100));
+        let run = |method: &str, scenario| TestConfig {
+            target_url: Some("http://127.0.0.1:9/".to_string()),
+            method: method.to_string(),
+            scenario,
+            num_calls: 1,
+            ..Default::default()
+        };
+
+        let error = tester.run_with_config(run("GE T", None)).await.unwrap_err();
+        assert_eq!(error.to_string(), "Invalid test.method \"GE T\"");
+
+        let step = WeightedStep {
+            weight: 1,
+            method: "P OST".to_string(),
+            path_or_url: "/items".to_string(),
+            body: None,
+        };
+        let error = tester
+            .run_with_config(run("GET", Some(vec![step])))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Invalid scenario[0].method \"P OST\"");
+        assert!(!tester.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_extracted_variables_chain_calls() {
+        // Each response hands out the next token and the server logs the token it received
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let app = axum::Router::new().fallback(move |headers: axum::http::HeaderMap| {
+            let log = log.clone();
+            async move {
+                let mut log = log.lock();
+                let token = headers["x-token"].to_str().unwrap().to_string();
+                log.push(token);
+                axum::Json(serde_json::json!({ "token": format!("t{}", log.len()) }))
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let extraction = |json_path: &str, var_name: &str| Extraction {
+            json_path: json_path.to_string(),
+            var_name: var_name.to_string(),
+        };
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 3,
+                frequency_ms: 0,
+                target_url: Some(format!("http://{}/", addr)),
+                headers: vec![("X-Token".to_string(), "{{token}}|{{missing}}".to_string())],
+                extract: vec![
+                    extraction("$.token", "token"),
+                    extraction("$.nope", "missing"),
+                ],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.successful, 3);
+        assert_eq!(*seen.lock(), vec!["{{token}}|{{missing}}", "t1|", "t2|"]);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_scenario_mixes_steps() {
+        let target = spawn_ok_server().await;
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let step = |weight, method: &str, path_or_url: &str| WeightedStep {
+            weight,
+            method: method.to_string(),
+            path_or_url: path_or_url.to_string(),
+            body: None,
+        };
+        let run = |scenario| TestConfig {
+            num_calls: 40,
+            frequency_ms: 0,
+            target_url: Some(target.clone()),
+            scenario: Some(scenario),
+            ..Default::default()
+        };
+
+        let summary = tester
+            .run_with_config(run(vec![
+                step(3, "GET", "/items"),
+                step(1, "POST", "items"),
+                step(0, "DELETE", "/never"),
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(summary.successful, 40);
+        let count = |i| summary.results.iter().filter(|r| r.step == Some(i)).count();
+        assert!(count(0) > 0 && count(1) > 0);
+        assert_eq!(count(0) + count(1), 40);
+
+        assert!(tester
+            .run_with_config(run(vec![step(0, "GET", "/")]))
+            .await
+            .is_err());
+        assert!(!tester.is_running());
+
+        assert_eq!(
+            resolve_step_url("http://api.local/v1/", "/items").unwrap(),
+            "http://api.local/items"
+        );
+        assert_eq!(
+            resolve_step_url("http://api.local/v1/", "items/{{id}}").unwrap(),
+            "http://api.local/v1/items/{{id}}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_record_one_metric() {
+        // Fails the first two requests it sees with 503
+        let hits = Arc::new(AtomicU32::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().fallback(move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    axum::http::StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    axum::http::StatusCode::OK
+                }
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let metrics = create_shared_metrics(1000);
+        let tester = ApiTester::new(config, metrics.clone());
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 1,
+                target_url: Some(format!("http://{}/", addr)),
+                retries: 3,
+                retry_backoff_ms: 1,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.successful, 1);
+        assert_eq!(summary.results[0].attempts, 3);
+        assert_eq!(summary.retries, 2);
+        assert_eq!(metrics.count(), 1);
+
+        assert_eq!(
+            RetryBackoff::Linear.delay(100, 3),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            RetryBackoff::Exponential.delay(100, 3),
+            Duration::from_millis(400)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duration_limits_run() {
+        let target = spawn_ok_server().await;
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+
+        // No call limit: runs until the deadline
+        let start = Instant::now();
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 0,
+                duration_secs: Some(1),
+                frequency_ms: 100,
+                target_url: Some(target.clone()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        assert!((9..=11).contains(&summary.total_requests));
+
+        // The call limit is hit first
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 3,
+                duration_secs: Some(60),
+                frequency_ms: 0,
+                target_url: Some(target),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(summary.total_requests, 3);
+    }
+
+    #[tokio::test]
+    async fn test_prime_connections_not_counted() {
+        let target = spawn_ok_server().await;
+        let metrics = create_shared_metrics(1000);
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, metrics.clone());
+
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 3,
+                frequency_ms: 0,
+                concurrency: 2,
+                prime_connections: true,
+                target_url: Some(target),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.connections_primed, 2);
+        assert_eq!(summary.total_requests, 3);
+        assert_eq!(metrics.count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stop_cancels_in_flight_requests() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().fallback(|| async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            "slow"
+        });
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = Arc::new(ApiTester::new(config, create_shared_metrics(1000)));
+        let runner = tester.clone();
+        let run = tokio::spawn(async move {
+            runner
+                .run_with_config(TestConfig {
+                    num_calls: 100,
+                    frequency_ms: 0,
+                    concurrency: 4,
+                    target_url: Some(format!("http://{}/", addr)),
+                    ..Default::default()
+                })
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tester.stop();
+        let summary = tokio::time::timeout(Duration::from_secs(1), run)
+            .await
+            .expect("stop should cancel promptly")
+            .unwrap()
+            .unwrap();
+        assert_eq!(summary.total_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_requests_count_not_modified() {
+        use axum::http::{header, HeaderMap, StatusCode};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().fallback(|headers: HeaderMap| async move {
+            if headers
+                .get(header::IF_NONE_MATCH)
+                .is_some_and(|v| v == "\"v1\"")
+            {
+                (StatusCode::NOT_MODIFIED, [(header::ETAG, "\"v1\"")], "")
+            } else {
+                (StatusCode::OK, [(header::ETAG, "\"v1\"")], "body")
+            }
+        });
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 5,
+                frequency_ms: 0,
+                conditional: true,
+                target_url: Some(format!("http://{}/", addr)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.not_modified_count, 4);
+        assert_eq!(summary.successful, 5);
+    }
+
+    #[tokio::test]
+    async fn test_redirects_recorded_when_not_followed() {
+        use axum::{response::Redirect, routing::get};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new()
+            .route("/", get(|| async { Redirect::permanent("/target") }))
+            .route("/target", get(|| async { "ok" }));
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let run = |follow_redirects| {
+            tester.run_with_config(TestConfig {
+                num_calls: 1,
+                frequency_ms: 0,
+                follow_redirects,
+                target_url: Some(format!("http://{}/", addr)),
+                ..Default::default()
+            })
+        };
+
+        let followed = run(true).await.unwrap();
+        assert_eq!(followed.results[0].status_code, Some(200));
+
+        let recorded = run(false).await.unwrap();
+        assert_eq!(recorded.results[0].status_code, Some(308));
+        assert!(!recorded.results[0].success);
+    }
+
+    #[tokio::test]
+    async fn test_run_saved_and_kept_in_history() {
+        let target = spawn_ok_server().await;
+        let dir = std::env::temp_dir().join(format!("api-check-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 2,
+                frequency_ms: 0,
+                target_url: Some(target),
+                save_results_path: Some(dir.to_string_lossy().into_owned()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let saved: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(saved.len(), 1);
+        let contents = std::fs::read_to_string(saved[0].as_ref().unwrap().path()).unwrap();
+        let loaded: TestRunSummary = serde_json::from_str(&contents).unwrap();
+        assert_eq!(loaded.total_requests, 2);
+        assert_eq!(loaded.started_at, summary.started_at);
+
+        assert_eq!(tester.history().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_max_rps_caps_dispatch() {
+        let target = spawn_ok_server().await;
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 0,
+                duration_secs: Some(2),
+                frequency_ms: 0,
+                concurrency: 8,
+                max_rps: Some(10),
+                target_url: Some(target),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert!(
+            (18..=21).contains(&summary.total_requests),
+            "{} requests",
+            summary.total_requests
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout_fails_call() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().fallback(|| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            "slow"
+        });
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let config = SharedConfig::new(AppConfig::default());
+        let tester = ApiTester::new(config, create_shared_metrics(1000));
+        let summary = tester
+            .run_with_config(TestConfig {
+                num_calls: 1,
+                timeout_secs: 1,
+                target_url: Some(format!("http://{}/", addr)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.failed, 1);
+        assert_eq!(
+            summary.results[0].error.as_deref(),
+            Some("timeout after 1s")
+        );
+    }
+
+    #[test]
+    fn test_latency_trend_buckets() {
+        let results: Vec<TestResult> = (1..=100)
+            .map(|i| TestResult {
+                index: i,
+                success: true,
+                status_code: Some(200),
+                latency_ms: i as f64,
+                ..Default::default()
+            })
+            .collect();
+
+        let trend = latency_trend(&results, 10);
+        assert_eq!(trend.len(), 10);
+        assert_eq!(trend[0], 5.5);
+        assert_eq!(trend[9], 95.5);
+
+        assert_eq!(latency_trend(&results[..3], 10), vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_pause_accumulates_backoff() {
+        let state = RunState::new(1, Duration::ZERO, None);
+        state.pause_for(Duration::from_millis(200));
+        // A shorter pause inside the active one adds no extra backoff
+        state.pause_for(Duration::from_millis(50));
+
+        let backoff = *state.backoff_ms.lock();
+        assert!((199.0..=201.0).contains(&backoff), "backoff {}", backoff);
+    }
+
+    #[test]
+    fn test_phase_breakdown() {
+        let summary = TestRunSummary {
+            results: vec![
+                TestResult {
+                    ttfb_ms: Some(10.0),
+                    download_ms: Some(2.0),
+                    ..Default::default()
+                },
+                TestResult {
+                    ttfb_ms: Some(30.0),
+                    download_ms: Some(4.0),
+                    ..Default::default()
+                },
+                TestResult::default(),
+            ],
+            ..Default::default()
+        };
+
+        let phases = summary.phase_breakdown();
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[0].phase, "ttfb");
+        assert_eq!(phases[0].avg_ms, 20.0);
+        assert_eq!(phases[1].avg_ms, 3.0);
+    }
+
+    #[test]
+    fn test_describe_plan() {
+        let tester = ApiTester::new(
+            SharedConfig::new(AppConfig::default()),
+            create_shared_metrics(100),
+        );
+        let config = TestConfig {
+            target_url: Some("http://api/items/{{i}}".to_string()),
+            method: "POST".to_string(),
+            body: Some(r#"{"n": {{random:7-7}}}"#.to_string()),
+            headers: vec![("X-Call".to_string(), "{{i}}".to_string())],
+            auth: Some(AuthConfig::Bearer {
+                token: "secret".to_string(),
+            }),
+            num_calls: 5,
+            frequency_ms: 200,
+            ..Default::default()
+        };
+
+        let plan = tester.describe_plan(&config);
+        assert!(plan.contains("Request: POST http://api/items/1\n"));
+        assert!(plan.contains(r#"Body (Json): {"n": 7}"#));
+        assert!(plan.contains("  X-Call: 1\n  Authorization: Bearer <redacted>\n"));
+        assert!(!plan.contains("secret"));
+        assert!(plan.contains("Calls: 5\n"));
+        assert!(plan.contains("one call every 200ms (at most 5.0/s), concurrency 1"));
+        assert!(plan.contains("Estimated duration: at least 0.8s"));
+    }
+
+    #[test]
+    fn test_evaluate_slo() {
+        let results = (1..=20)
+            .map(|i| TestResult {
+                index: i,
+                success: i != 20,
+                latency_ms: i as f64,
+                ..Default::default()
+            })
+            .collect();
+        let summary = TestRunSummary::from_results(results, 100.0);
+        assert_eq!(summary.p95_latency_ms, 19.0);
+
+        assert_eq!(summary.evaluate_slo(None, None), None);
+        assert_eq!(summary.evaluate_slo(Some(19.0), Some(0.05)), Some(true));
+        assert_eq!(summary.evaluate_slo(Some(18.0), Some(0.05)), Some(false));
+        assert_eq!(summary.evaluate_slo(None, Some(0.01)), Some(false));
+    }
+
     #[test]
     fn test_result_serialization() {
         let result = TestResult {
@@ -271,10 +2183,28 @@ mod tests {
             success: true,
             status_code: Some(200),
             latency_ms: 10.5,
-            error: None,
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("\"success\":true"));
     }
+
+    #[tokio::test]
+    async fn test_save_summary_never_overwrites() {
+        let dir = std::env::temp_dir().join(format!("api-check-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let summary = TestRunSummary {
+            started_at: Some(Utc::now()),
+            ..Default::default()
+        };
+
+        let first = save_summary(&summary, dir.to_str().unwrap()).await.unwrap();
+        let second = save_summary(&summary, dir.to_str().unwrap()).await.unwrap();
+        assert_ne!(first, second);
+        assert!(second.to_str().unwrap().ends_with("-1.json"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
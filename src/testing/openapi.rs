@@ -0,0 +1,498 @@
+//! OpenAPI smoke tests
+//!
+//! Walks the `paths` of an OpenAPI 3 (or Swagger 2) document and calls every
+//! operation once, filling path parameters, required query parameters and
+//! JSON request bodies from the spec's examples. Operations with a path
+//! parameter that has no example are skipped, since there is nothing sensible
+//! to call them with.
+
+use super::{TestResult, TestRunSummary};
+use crate::metrics::{MetricSource, RequestMetric, SharedMetrics};
+use anyhow::{Context, Result};
+use reqwest::{Client, Url};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Keys of a path item that define operations
+const METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Longest chain of `$ref`s followed before giving up
+const MAX_REF_DEPTH: usize = 16;
+
+/// A parsed OpenAPI document
+#[derive(Debug, Clone)]
+pub struct OpenApiSpec {
+    doc: Value,
+}
+
+/// A request built from one operation of the spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    /// Upper-case HTTP method
+    pub method: String,
+    /// Path template as written in the spec, e.g. `/items/{id}`
+    pub template: String,
+    /// Path with its parameters filled in
+    pub path: String,
+    /// Required query parameters, from their examples
+    pub query: Vec<(String, String)>,
+    /// Example JSON request body, if the spec has one
+    pub body: Option<Value>,
+}
+
+impl Operation {
+    /// Label used in results and logs, e.g. `GET /items/{id}`
+    pub fn name(&self) -> String {
+        format!("{} {}", self.method, self.template)
+    }
+}
+
+/// An operation that can't be called, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedOperation {
+    pub name: String,
+    pub reason: String,
+}
+
+impl OpenApiSpec {
+    /// Load a spec from a JSON file, or YAML (`.yaml`/`.yml`) with the `yaml` feature
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read OpenAPI spec {}", path))?;
+        let is_yaml = path.ends_with(".yaml") || path.ends_with(".yml");
+        let doc = if is_yaml {
+            parse_yaml(&contents)
+        } else {
+            serde_json::from_str(&contents).map_err(Into::into)
+        }
+        .with_context(|| format!("Invalid OpenAPI spec {}", path))?;
+        Ok(Self::new(doc))
+    }
+
+    /// Wrap an already parsed document
+    pub fn new(doc: Value) -> Self {
+        Self { doc }
+    }
+
+    /// Every operation in the spec, split into callable and skipped ones
+    pub fn operations(&self) -> (Vec<Operation>, Vec<SkippedOperation>) {
+        let mut operations = Vec::new();
+        let mut skipped = Vec::new();
+        let Some(paths) = self.doc.get("paths").and_then(Value::as_object) else {
+            return (operations, skipped);
+        };
+
+        for (template, item) in paths {
+            let item = self.resolve(item);
+            for method in METHODS {
+                let Some(operation) = item.get(*method) else {
+                    continue;
+                };
+                let name = format!("{} {}", method.to_ascii_uppercase(), template);
+                match self.build(method, template, item, operation) {
+                    Ok(operation) => operations.push(operation),
+                    Err(reason) => skipped.push(SkippedOperation { name, reason }),
+                }
+            }
+        }
+        (operations, skipped)
+    }
+
+    fn build(
+        &self,
+        method: &str,
+        template: &str,
+        item: &Value,
+        operation: &Value,
+    ) -> Result<Operation, String> {
+        // Operation-level parameters override path-level ones with the same name and location
+        let mut parameters: Vec<&Value> = Vec::new();
+        for parameter in [item, operation]
+            .iter()
+            .filter_map(|v| v.get("parameters").and_then(Value::as_array))
+            .flatten()
+            .map(|p| self.resolve(p))
+        {
+            let key = |p: &Value| (p.get("name").cloned(), p.get("in").cloned());
+            parameters.retain(|existing| key(existing) != key(parameter));
+            parameters.push(parameter);
+        }
+        let find = |location: &str, name: &str| {
+            parameters.iter().copied().find(|p| {
+                p.get("in").and_then(Value::as_str) == Some(location)
+                    && p.get("name").and_then(Value::as_str) == Some(name)
+            })
+        };
+
+        let mut path = template.to_string();
+        for name in path_parameters(template) {
+            let value = find("path", name)
+                .and_then(|p| self.parameter_example(p))
+                .ok_or_else(|| format!("no example for path parameter `{}`", name))?;
+            path = path.replace(&format!("{{{}}}", name), &value);
+        }
+
+        let query = parameters
+            .iter()
+            .filter(|p| p.get("in").and_then(Value::as_str) == Some("query"))
+            .filter(|p| p.get("required").and_then(Value::as_bool) == Some(true))
+            .filter_map(|p| {
+                let name = p.get("name")?.as_str()?.to_string();
+                Some((name, self.parameter_example(p)?))
+            })
+            .collect();
+
+        Ok(Operation {
+            method: method.to_ascii_uppercase(),
+            template: template.to_string(),
+            path,
+            query,
+            body: self.body_example(operation, &parameters),
+        })
+    }
+
+    /// Example value for a parameter, as it goes in a URL
+    fn parameter_example(&self, parameter: &Value) -> Option<String> {
+        let value = parameter
+            .get("example")
+            .cloned()
+            .or_else(|| self.first_example(parameter))
+            .or_else(|| {
+                let schema = self.resolve(parameter.get("schema")?);
+                schema_example(schema)
+            })
+            // Swagger 2 puts the schema keywords on the parameter itself
+            .or_else(|| schema_example(parameter))?;
+        Some(match value {
+            Value::String(s) => s,
+            other => other.to_string(),
+        })
+    }
+
+    /// Example JSON body for an operation
+    fn body_example(&self, operation: &Value, parameters: &[&Value]) -> Option<Value> {
+        if let Some(request_body) = operation.get("requestBody") {
+            let content = self.resolve(request_body).get("content")?.as_object()?;
+            let media = content
+                .get("application/json")
+                .or_else(|| {
+                    content
+                        .iter()
+                        .find(|(kind, _)| kind.contains("json"))
+                        .map(|(_, media)| media)
+                })
+                .map(|media| self.resolve(media))?;
+            return media
+                .get("example")
+                .cloned()
+                .or_else(|| self.first_example(media))
+                .or_else(|| self.resolve(media.get("schema")?).get("example").cloned());
+        }
+
+        // Swagger 2 bodies are `in: body` parameters
+        let parameter = parameters
+            .iter()
+            .find(|p| p.get("in").and_then(Value::as_str) == Some("body"))?;
+        self.resolve(parameter.get("schema")?)
+            .get("example")
+            .cloned()
+    }
+
+    /// Value of the first entry of an `examples` map
+    fn first_example(&self, value: &Value) -> Option<Value> {
+        let (_, example) = value.get("examples")?.as_object()?.iter().next()?;
+        self.resolve(example).get("value").cloned()
+    }
+
+    /// Follow local `$ref`s (`#/components/...`) to the value they point at
+    fn resolve<'a>(&'a self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_REF_DEPTH {
+            let target = value
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|r| r.strip_prefix('#'))
+                .and_then(|pointer| self.doc.pointer(pointer));
+            match target {
+                Some(target) => value = target,
+                None => break,
+            }
+        }
+        value
+    }
+}
+
+/// Names of the `{param}` placeholders in a path template, in order
+fn path_parameters(template: &str) -> Vec<&str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+        .collect()
+}
+
+/// A schema's example, falling back to its default or first enum value
+fn schema_example(schema: &Value) -> Option<Value> {
+    schema
+        .get("example")
+        .or_else(|| schema.get("default"))
+        .or_else(|| schema.get("enum")?.as_array()?.first())
+        .cloned()
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(contents: &str) -> Result<Value> {
+    Ok(serde_yaml::from_str(contents)?)
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_contents: &str) -> Result<Value> {
+    anyhow::bail!("api-check was built without the `yaml` feature")
+}
+
+/// Calls each operation of a spec once
+pub struct OpenApiRunner {
+    client: Client,
+    /// Where the calls are recorded, if anywhere
+    metrics: Option<SharedMetrics>,
+}
+
+impl Default for OpenApiRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenApiRunner {
+    /// Create a new runner
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            metrics: None,
+        }
+    }
+
+    /// Send requests with this client instead of the default one
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Record each call as a test client metric
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Call every operation of `spec` against `base_url`, in spec order
+    ///
+    /// Skipped operations are logged and left out of the summary.
+    pub async fn run(&self, spec: &OpenApiSpec, base_url: &str) -> Result<TestRunSummary> {
+        let (operations, skipped) = spec.operations();
+        for operation in &skipped {
+            tracing::warn!(
+                operation = %operation.name,
+                reason = %operation.reason,
+                "Skipping OpenAPI operation"
+            );
+        }
+
+        let base_url = base_url.trim_end_matches('/');
+        let start = Instant::now();
+        let mut results = Vec::with_capacity(operations.len());
+        for (i, operation) in operations.iter().enumerate() {
+            let index = i as u32 + 1;
+            let name = operation.name();
+            let url = format!("{}{}", base_url, operation.path);
+            let result = match self.send(&url, operation).await {
+                // Some operations answer with a redirect by design
+                Ok((status, ttfb_ms, total_ms)) => TestResult {
+                    index,
+                    success: (200..400).contains(&status),
+                    status_code: Some(status),
+                    latency_ms: total_ms,
+                    ttfb_ms: Some(ttfb_ms),
+                    download_ms: Some(total_ms - ttfb_ms),
+                    total_ms: Some(total_ms),
+                    operation: Some(name.clone()),
+                    ..Default::default()
+                },
+                Err(e) => TestResult {
+                    index,
+                    error: Some(e.to_string()),
+                    operation: Some(name.clone()),
+                    ..Default::default()
+                },
+            };
+
+            tracing::debug!(
+                operation = %name,
+                url = %url,
+                status = ?result.status_code,
+                "Called OpenAPI operation"
+            );
+            if let Some(metrics) = &self.metrics {
+                let mut metric = RequestMetric::new(operation.method.clone(), url)
+                    .with_latency(result.latency_ms)
+                    .with_source(MetricSource::TestClient);
+                if let Some(status) = result.status_code {
+                    metric = metric.with_status(status);
+                }
+                metrics.record(metric);
+            }
+            results.push(result);
+        }
+
+        Ok(TestRunSummary::from_results(
+            results,
+            start.elapsed().as_secs_f64() * 1000.0,
+        ))
+    }
+
+    /// Send one operation, returning its status, time to first byte and total time
+    async fn send(&self, url: &str, operation: &Operation) -> Result<(u16, f64, f64)> {
+        let mut url = Url::parse(url).with_context(|| format!("Invalid URL {}", url))?;
+        if !operation.query.is_empty() {
+            url.query_pairs_mut().extend_pairs(&operation.query);
+        }
+        let method = reqwest::Method::from_bytes(operation.method.as_bytes())
+            .with_context(|| format!("Invalid method {}", operation.method))?;
+
+        let mut builder = self.client.request(method, url);
+        if let Some(body) = &operation.body {
+            builder = builder.json(body);
+        }
+
+        let start = Instant::now();
+        let response = builder.send().await?;
+        let status = response.status().as_u16();
+        let ttfb_ms = start.elapsed().as_secs_f64() * 1000.0;
+        response.bytes().await?;
+        let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        Ok((status, ttfb_ms, total_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, SharedConfig};
+    use crate::metrics::create_shared_metrics;
+    use crate::testing::ApiTester;
+    use axum::{
+        extract::{Path, Query},
+        http::StatusCode,
+        routing::{get, post},
+        Json, Router,
+    };
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_run_from_openapi() {
+        let app = Router::new()
+            .route(
+                "/items/:id",
+                get(|Path(id): Path<String>| async move {
+                    if id == "42" {
+                        StatusCode::OK
+                    } else {
+                        StatusCode::NOT_FOUND
+                    }
+                })
+                .delete(|| async { StatusCode::NO_CONTENT }),
+            )
+            .route(
+                "/items",
+                post(|Json(body): Json<Value>| async move {
+                    if body["name"] == "widget" {
+                        StatusCode::CREATED
+                    } else {
+                        StatusCode::BAD_REQUEST
+                    }
+                })
+                .get(|Query(query): Query<HashMap<String, String>>| async move {
+                    match query.get("limit").map(String::as_str) {
+                        Some("10") => StatusCode::OK,
+                        _ => StatusCode::BAD_REQUEST,
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let spec = serde_json::json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/items": {
+                    "get": {
+                        "parameters": [{
+                            "name": "limit", "in": "query", "required": true,
+                            "schema": {"type": "integer", "example": 10}
+                        }]
+                    },
+                    "post": {
+                        "requestBody": {"$ref": "#/components/requestBodies/Item"}
+                    }
+                },
+                "/items/{id}": {
+                    "parameters": [{"name": "id", "in": "path", "required": true}],
+                    "get": {
+                        "parameters": [{
+                            "name": "id", "in": "path", "required": true,
+                            "examples": {"known": {"value": "42"}}
+                        }]
+                    },
+                    "delete": {}
+                }
+            },
+            "components": {
+                "requestBodies": {
+                    "Item": {
+                        "content": {"application/json": {"example": {"name": "widget"}}}
+                    }
+                }
+            }
+        });
+        let path = std::env::temp_dir().join(format!("api-check-{}.json", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, spec.to_string()).unwrap();
+
+        let (_, skipped) = OpenApiSpec::load(path).unwrap().operations();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].name, "DELETE /items/{id}");
+
+        let metrics = create_shared_metrics(1000);
+        let tester = ApiTester::new(SharedConfig::new(AppConfig::default()), metrics.clone());
+        let summary = tester
+            .run_from_openapi(path, &format!("http://{}/", addr))
+            .await
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(summary.total_requests, 3);
+        assert_eq!(summary.successful, 3);
+        let operations: Vec<_> = summary
+            .results
+            .iter()
+            .map(|r| (r.operation.as_deref().unwrap(), r.status_code.unwrap()))
+            .collect();
+        assert_eq!(
+            operations,
+            vec![
+                ("GET /items", 200),
+                ("POST /items", 201),
+                ("GET /items/{id}", 200)
+            ]
+        );
+        assert_eq!(metrics.get_all().len(), 3);
+        assert_eq!(tester.history().len(), 1);
+    }
+}
@@ -0,0 +1,337 @@
+//! Multi-step scenarios
+//!
+//! Runs a sequence of requests as one workflow. Steps capture values from
+//! their JSON responses into a shared context (`extract`), later steps use
+//! them as `{{key}}` in their URL, headers or body, and `assert_eq_ctx`
+//! checks that a response field equals a captured value.
+
+use super::snapshot::JsonPath;
+use anyhow::{Context, Result};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A scenario loaded from a JSON file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Base URL that relative step URLs are resolved against
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Steps, run in order
+    pub steps: Vec<ScenarioStep>,
+}
+
+/// A single request in a scenario
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioStep {
+    /// Label used in reports (defaults to method and URL)
+    #[serde(default)]
+    pub name: Option<String>,
+    /// HTTP method
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Absolute URL, or a path relative to the scenario's `base_url`
+    pub url: String,
+    /// Request headers as key-value pairs
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Response values to capture as `(json_path, ctx_key)`
+    #[serde(default)]
+    pub extract: Vec<(String, String)>,
+    /// Response values that must equal a captured value, as `(json_path, ctx_key)`
+    ///
+    /// Wildcard paths pass if any matched value is equal, e.g.
+    /// `("$.items[*].id", "created_id")` checks a list contains an item.
+    #[serde(default)]
+    pub assert_eq_ctx: Vec<(String, String)>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl ScenarioStep {
+    fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", self.method, self.url))
+    }
+}
+
+impl Scenario {
+    /// Load a scenario from a JSON file
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scenario {}", path))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid scenario {}", path))
+    }
+}
+
+/// Outcome of a single scenario step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    /// Step number (1-based)
+    pub index: usize,
+    /// Step label
+    pub name: String,
+    /// Response status code
+    pub status_code: Option<u16>,
+    /// Request latency in milliseconds
+    pub latency_ms: f64,
+    /// Error message if the request failed
+    pub error: Option<String>,
+    /// Failed extractions and context assertions
+    pub assertion_failures: Vec<String>,
+}
+
+impl StepResult {
+    /// Whether the request succeeded and every assertion held
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+            && self.status_code.is_some_and(|s| (200..400).contains(&s))
+            && self.assertion_failures.is_empty()
+    }
+}
+
+/// Outcome of a scenario run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioSummary {
+    /// Per-step results, in order
+    pub steps: Vec<StepResult>,
+    /// Context values captured during the run
+    pub context: HashMap<String, Value>,
+}
+
+impl ScenarioSummary {
+    /// Number of steps that did not pass
+    pub fn failed_steps(&self) -> usize {
+        self.steps.iter().filter(|s| !s.passed()).count()
+    }
+}
+
+/// Render a context value for substitution (strings without quotes)
+fn context_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Replace `{{key}}` placeholders with context values, leaving unknown keys as-is
+pub fn render_template(template: &str, context: &HashMap<String, Value>) -> String {
+    let mut out = template.to_string();
+    for (key, value) in context {
+        out = out.replace(&format!("{{{{{}}}}}", key), &context_string(value));
+    }
+    out
+}
+
+/// Capture `extract` values into the context, returning failures
+///
+/// A path that matches nothing fails the step and leaves the key as it was.
+fn apply_extractions(
+    step: &ScenarioStep,
+    body: &Value,
+    context: &mut HashMap<String, Value>,
+) -> Vec<String> {
+    extract_into(
+        step.extract
+            .iter()
+            .map(|(path, key)| (path.as_str(), key.as_str())),
+        body,
+        context,
+    )
+    .into_iter()
+    .map(|(path, key)| format!("extract {}: {} not found", key, path))
+    .collect()
+}
+
+/// Capture the value at each `(json_path, key)` into `context`, returning the
+/// pairs whose path matched nothing
+pub(crate) fn extract_into<'a>(
+    extractions: impl IntoIterator<Item = (&'a str, &'a str)>,
+    body: &Value,
+    context: &mut HashMap<String, Value>,
+) -> Vec<(&'a str, &'a str)> {
+    let mut unmatched = Vec::new();
+    for (path, key) in extractions {
+        match JsonPath::parse(path).select(body).first() {
+            Some(value) => {
+                context.insert(key.to_string(), (*value).clone());
+            }
+            None => unmatched.push((path, key)),
+        }
+    }
+    unmatched
+}
+
+/// Check `assert_eq_ctx` pairs against the context, returning failures
+///
+/// Values compare by their string form, so `"42"` and `42` are equal.
+pub fn check_context_assertions(
+    step: &ScenarioStep,
+    body: &Value,
+    context: &HashMap<String, Value>,
+) -> Vec<String> {
+    let mut failures = Vec::new();
+    for (path, key) in &step.assert_eq_ctx {
+        let Some(expected) = context.get(key) else {
+            failures.push(format!("{} == {{{{{}}}}}: context key not set", path, key));
+            continue;
+        };
+
+        let expected = context_string(expected);
+        let actual = JsonPath::parse(path).select(body);
+        if !actual.iter().any(|v| context_string(v) == expected) {
+            let found = match actual.as_slice() {
+                [] => "nothing".to_string(),
+                [single] => single.to_string(),
+                many => format!("{} values", many.len()),
+            };
+            failures.push(format!(
+                "{} == {{{{{}}}}}: expected {}, found {}",
+                path, key, expected, found
+            ));
+        }
+    }
+    failures
+}
+
+/// Runs scenarios step by step
+pub struct ScenarioRunner {
+    client: Client,
+}
+
+impl Default for ScenarioRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScenarioRunner {
+    /// Create a new scenario runner
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self { client }
+    }
+
+    /// Run every step in order, threading captured values through the context
+    pub async fn run(&self, scenario: &Scenario) -> Result<ScenarioSummary> {
+        let mut context = HashMap::new();
+        let mut steps = Vec::with_capacity(scenario.steps.len());
+
+        for (i, step) in scenario.steps.iter().enumerate() {
+            let start = Instant::now();
+            let mut result = StepResult {
+                index: i + 1,
+                name: step.label(),
+                status_code: None,
+                latency_ms: 0.0,
+                error: None,
+                assertion_failures: Vec::new(),
+            };
+
+            match self.send(scenario, step, &context).await {
+                Ok((status, body)) => {
+                    result.status_code = Some(status);
+                    result.assertion_failures = apply_extractions(step, &body, &mut context);
+                    result
+                        .assertion_failures
+                        .extend(check_context_assertions(step, &body, &context));
+                }
+                Err(e) => result.error = Some(e.to_string()),
+            }
+            result.latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+            tracing::debug!(
+                step = %result.index,
+                name = %result.name,
+                status = ?result.status_code,
+                passed = %result.passed(),
+                "Ran scenario step"
+            );
+            steps.push(result);
+        }
+
+        Ok(ScenarioSummary { steps, context })
+    }
+
+    /// Send one step, returning its status and body (JSON when possible, otherwise a string)
+    async fn send(
+        &self,
+        scenario: &Scenario,
+        step: &ScenarioStep,
+        context: &HashMap<String, Value>,
+    ) -> Result<(u16, Value)> {
+        let url = render_template(&step.url, context);
+        let url = match &scenario.base_url {
+            Some(base) if !url.contains("://") => Url::parse(base)
+                .with_context(|| format!("Invalid base URL {}", base))?
+                .join(&url)?
+                .to_string(),
+            _ => url,
+        };
+        let method = reqwest::Method::from_bytes(step.method.as_bytes())
+            .with_context(|| format!("Invalid method {}", step.method))?;
+
+        let mut builder = self.client.request(method, &url);
+        for (key, value) in &step.headers {
+            builder = builder.header(key, render_template(value, context));
+        }
+        if let Some(body) = &step.body {
+            builder = builder.body(render_template(body, context));
+        }
+
+        let response = builder
+            .send()
+            .await
+            .with_context(|| format!("Request to {} failed", url))?;
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+
+        Ok((status, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_context_assertions_across_steps() {
+        let create: ScenarioStep = serde_json::from_value(json!({
+            "method": "POST",
+            "url": "/items",
+            "extract": [["$.id", "item_id"]]
+        }))
+        .unwrap();
+        let list: ScenarioStep = serde_json::from_value(json!({
+            "url": "/items/{{item_id}}",
+            "assert_eq_ctx": [["$.items[*].id", "item_id"], ["$.owner", "user"]]
+        }))
+        .unwrap();
+
+        let mut context = HashMap::new();
+        assert!(apply_extractions(&create, &json!({"id": 42}), &mut context).is_empty());
+        assert_eq!(render_template(&list.url, &context), "/items/42");
+
+        let failures = check_context_assertions(
+            &list,
+            &json!({"items": [{"id": 7}, {"id": "42"}]}),
+            &context,
+        );
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("context key not set"));
+    }
+}
@@ -0,0 +1,444 @@
+//! Snapshot (golden-master) regression testing
+//!
+//! Records responses for a list of requests into a golden file and later
+//! re-runs them, diffing status, headers and body against the recording.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Headers that change on every response and are ignored by default
+pub const DEFAULT_IGNORED_HEADERS: &[&str] = &["date", "age", "expires"];
+
+/// A single request to record in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotRequest {
+    /// HTTP method
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Full request URL
+    pub url: String,
+    /// Request headers as key-value pairs
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// A recorded response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SnapshotResponse {
+    /// Response status code
+    pub status: u16,
+    /// Response headers (lowercased names); repeated headers such as
+    /// `set-cookie` have their values joined with `, ` in arrival order
+    pub headers: BTreeMap<String, String>,
+    /// Response body, parsed as JSON when possible, otherwise a string
+    pub body: Value,
+}
+
+/// A request together with its recorded response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub request: SnapshotRequest,
+    pub response: SnapshotResponse,
+}
+
+/// Golden file contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoldenFile {
+    /// When the snapshot was recorded
+    pub recorded_at: DateTime<Utc>,
+    /// JSON paths ignored during verification
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Recorded entries
+    pub entries: Vec<SnapshotEntry>,
+}
+
+impl GoldenFile {
+    /// Load a golden file from disk
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read golden file {}", path))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Write the golden file to disk as pretty-printed JSON
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write golden file {}", path))?;
+        Ok(())
+    }
+}
+
+/// A difference between the golden response and the live response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotMismatch {
+    /// Index of the entry in the golden file (0-based)
+    pub index: usize,
+    /// Request method and URL
+    pub request: String,
+    /// JSON path of the differing value (e.g. `$.body.items[0].id`)
+    pub path: String,
+    /// Value from the golden file
+    pub expected: Value,
+    /// Value from the live response
+    pub actual: Value,
+}
+
+/// Load a list of snapshot requests from a JSON file
+pub fn load_requests(path: &str) -> Result<Vec<SnapshotRequest>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read request list {}", path))?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Records and verifies snapshots
+pub struct SnapshotRunner {
+    client: Client,
+}
+
+impl Default for SnapshotRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnapshotRunner {
+    /// Create a new snapshot runner
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self { client }
+    }
+
+    /// Send every request and record its response into a golden file
+    pub async fn record(
+        &self,
+        requests: Vec<SnapshotRequest>,
+        ignore: Vec<String>,
+    ) -> Result<GoldenFile> {
+        let mut entries = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            let response = self.send(&request).await?;
+            entries.push(SnapshotEntry { request, response });
+        }
+
+        Ok(GoldenFile {
+            recorded_at: Utc::now(),
+            ignore,
+            entries,
+        })
+    }
+
+    /// Re-run every request in the golden file and report mismatches
+    pub async fn verify(
+        &self,
+        golden: &GoldenFile,
+        extra_ignore: &[String],
+    ) -> Result<Vec<SnapshotMismatch>> {
+        let ignore: Vec<JsonPath> = DEFAULT_IGNORED_HEADERS
+            .iter()
+            .map(|h| format!("$.headers.{}", h))
+            .chain(golden.ignore.iter().cloned())
+            .chain(extra_ignore.iter().cloned())
+            .map(|p| JsonPath::parse(&p))
+            .collect();
+
+        let mut mismatches = Vec::new();
+
+        for (index, entry) in golden.entries.iter().enumerate() {
+            let label = format!("{} {}", entry.request.method, entry.request.url);
+            let actual = self
+                .send(&entry.request)
+                .await
+                .with_context(|| format!("Snapshot {} ({}) failed", index, label))?;
+
+            let expected = serde_json::to_value(&entry.response)?;
+            let actual = serde_json::to_value(&actual)?;
+
+            for (path, expected, actual) in diff_values(&expected, &actual, &ignore) {
+                mismatches.push(SnapshotMismatch {
+                    index,
+                    request: label.clone(),
+                    path,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Send a single request and capture the response
+    async fn send(&self, request: &SnapshotRequest) -> Result<SnapshotResponse> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .with_context(|| format!("Invalid method {:?}", request.method))?;
+        let mut builder = self.client.request(method, &request.url);
+
+        for (key, value) in &request.headers {
+            builder = builder.header(key, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let response = builder
+            .send()
+            .await
+            .with_context(|| format!("Request to {} failed", request.url))?;
+
+        let status = response.status().as_u16();
+        let mut headers: BTreeMap<String, String> = BTreeMap::new();
+        for (key, value) in response.headers() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            headers
+                .entry(key.as_str().to_lowercase())
+                .and_modify(|joined| {
+                    joined.push_str(", ");
+                    joined.push_str(&value);
+                })
+                .or_insert_with(|| value.into_owned());
+        }
+        let text = response.text().await?;
+        let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+
+        Ok(SnapshotResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// A segment of a parsed JSON path
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+    /// `*` or `[*]`, matching any key or index
+    Wildcard,
+}
+
+/// A simple JSON path such as `$.body.items[*].id`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    /// Parse a dotted JSON path; a leading `$` is optional
+    pub fn parse(path: &str) -> Self {
+        let path = path.trim().trim_start_matches('$');
+        let mut segments = Vec::new();
+
+        for part in path.split('.').filter(|p| !p.is_empty()) {
+            let (key, rest) = match part.find('[') {
+                Some(pos) => (&part[..pos], &part[pos..]),
+                None => (part, ""),
+            };
+
+            if key == "*" {
+                segments.push(PathSegment::Wildcard);
+            } else if !key.is_empty() {
+                segments.push(PathSegment::Key(key.to_string()));
+            }
+
+            for index in rest.split('[').filter(|s| !s.is_empty()) {
+                let index = index.trim_end_matches(']');
+                match index.parse() {
+                    Ok(i) => segments.push(PathSegment::Index(i)),
+                    Err(_) => segments.push(PathSegment::Wildcard),
+                }
+            }
+        }
+
+        Self { segments }
+    }
+
+    /// Every value in `value` this path points at
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![value];
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|v| match (segment, v) {
+                    (PathSegment::Key(k), Value::Object(map)) => map.get(k).into_iter().collect(),
+                    (PathSegment::Index(i), Value::Array(items)) => {
+                        items.get(*i).into_iter().collect()
+                    }
+                    (PathSegment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    (PathSegment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    _ => Vec::new(),
+                })
+                .collect();
+        }
+        current
+    }
+
+    fn matches(&self, path: &[PathSegment]) -> bool {
+        self.segments.len() == path.len()
+            && self
+                .segments
+                .iter()
+                .zip(path)
+                .all(|(pattern, segment)| pattern == &PathSegment::Wildcard || pattern == segment)
+    }
+}
+
+fn format_path(path: &[PathSegment]) -> String {
+    let mut out = "$".to_string();
+    for segment in path {
+        match segment {
+            PathSegment::Key(k) => {
+                out.push('.');
+                out.push_str(k);
+            }
+            PathSegment::Index(i) => out.push_str(&format!("[{}]", i)),
+            PathSegment::Wildcard => out.push_str(".*"),
+        }
+    }
+    out
+}
+
+/// Recursively diff two JSON values, skipping ignored paths
+///
+/// Returns `(path, expected, actual)` for every differing leaf. Missing
+/// values are reported as `null`.
+pub fn diff_values(
+    expected: &Value,
+    actual: &Value,
+    ignore: &[JsonPath],
+) -> Vec<(String, Value, Value)> {
+    let mut diffs = Vec::new();
+    diff_inner(expected, actual, &mut Vec::new(), ignore, &mut diffs);
+    diffs
+}
+
+fn diff_inner(
+    expected: &Value,
+    actual: &Value,
+    path: &mut Vec<PathSegment>,
+    ignore: &[JsonPath],
+    diffs: &mut Vec<(String, Value, Value)>,
+) {
+    if ignore.iter().any(|p| p.matches(path)) {
+        return;
+    }
+
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            let keys: std::collections::BTreeSet<&String> = e.keys().chain(a.keys()).collect();
+            for key in keys {
+                path.push(PathSegment::Key(key.clone()));
+                diff_inner(
+                    e.get(key).unwrap_or(&Value::Null),
+                    a.get(key).unwrap_or(&Value::Null),
+                    path,
+                    ignore,
+                    diffs,
+                );
+                path.pop();
+            }
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            for i in 0..e.len().max(a.len()) {
+                path.push(PathSegment::Index(i));
+                diff_inner(
+                    e.get(i).unwrap_or(&Value::Null),
+                    a.get(i).unwrap_or(&Value::Null),
+                    path,
+                    ignore,
+                    diffs,
+                );
+                path.pop();
+            }
+        }
+        _ if expected != actual => {
+            diffs.push((format_path(path), expected.clone(), actual.clone()));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_record_keeps_repeated_headers() {
+        let app = axum::Router::new().fallback(|| async {
+            let cookies = [("set-cookie", "a=1"), ("set-cookie", "b=2")];
+            (axum::response::AppendHeaders(cookies), "ok")
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let request = |method: &str| SnapshotRequest {
+            method: method.to_string(),
+            url: format!("http://{}/", addr),
+            headers: Vec::new(),
+            body: None,
+        };
+        let runner = SnapshotRunner::new();
+        let golden = runner
+            .record(vec![request("GET")], Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(golden.entries[0].response.headers["set-cookie"], "a=1, b=2");
+
+        let mut changed = golden.clone();
+        changed.entries[0].request = request("GE T");
+        let error = runner.verify(&changed, &[]).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("Invalid method \"GE T\""));
+    }
+
+    #[test]
+    fn test_diff_reports_changed_fields() {
+        let expected = json!({"status": 200, "body": {"id": 1, "name": "a"}});
+        let actual = json!({"status": 200, "body": {"id": 2, "name": "a"}});
+
+        let diffs = diff_values(&expected, &actual, &[]);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].0, "$.body.id");
+    }
+
+    #[test]
+    fn test_json_path_select() {
+        let value = json!({"items": [{"id": 1}, {"id": 2}], "total": 2});
+
+        assert_eq!(JsonPath::parse("$.total").select(&value), vec![&json!(2)]);
+        assert_eq!(
+            JsonPath::parse("$.items[1].id").select(&value),
+            vec![&json!(2)]
+        );
+        assert_eq!(JsonPath::parse("$.items[*].id").select(&value).len(), 2);
+        assert!(JsonPath::parse("$.missing.id").select(&value).is_empty());
+    }
+
+    #[test]
+    fn test_diff_honors_ignore_paths() {
+        let expected = json!({"body": {"items": [{"id": 1, "ts": 10}, {"id": 2, "ts": 11}]}});
+        let actual = json!({"body": {"items": [{"id": 1, "ts": 20}, {"id": 2, "ts": 21}]}});
+
+        let ignore = vec![JsonPath::parse("$.body.items[*].ts")];
+        assert!(diff_values(&expected, &actual, &ignore).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+//! Built-in template placeholders for test calls
+//!
+//! Each call's URL, headers and body can use:
+//! - `{{i}}`: the call's 1-based index
+//! - `{{uuid}}`: a random UUID (v4)
+//! - `{{timestamp}}`: the current Unix time in milliseconds
+//! - `{{random:MIN-MAX}}`: a random integer in `MIN..=MAX`
+//!
+//! Every occurrence gets its own value. Anything else is left as-is.
+
+use parking_lot::Mutex;
+use rand::Rng;
+use std::collections::HashSet;
+
+/// Placeholders already reported as unknown, so each is logged only once
+static UNKNOWN_LOGGED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Substitute built-in placeholders for call number `index`
+pub fn render_builtins(template: &str, index: u32) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let Some(end) = placeholder.find("}}") else {
+            rest = placeholder;
+            break;
+        };
+        let name = &placeholder[2..end];
+        match builtin_value(name, index) {
+            Some(value) => out.push_str(&value),
+            None => {
+                log_unknown(name);
+                out.push_str(&placeholder[..end + 2]);
+            }
+        }
+        rest = &placeholder[end + 2..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn builtin_value(name: &str, index: u32) -> Option<String> {
+    match name {
+        "i" => Some(index.to_string()),
+        "uuid" => Some(uuid::Uuid::new_v4().to_string()),
+        "timestamp" => Some(chrono::Utc::now().timestamp_millis().to_string()),
+        _ => {
+            let (min, max) = name.strip_prefix("random:")?.split_once('-')?;
+            let (min, max): (u64, u64) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+            (min <= max).then(|| rand::thread_rng().gen_range(min..=max).to_string())
+        }
+    }
+}
+
+fn log_unknown(name: &str) {
+    let mut logged = UNKNOWN_LOGGED.lock();
+    if logged
+        .get_or_insert_with(HashSet::new)
+        .insert(name.to_string())
+    {
+        tracing::debug!(placeholder = %name, "Leaving unknown template placeholder as-is");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_builtins() {
+        assert_eq!(
+            render_builtins("/items?page={{i}}&q={{other}}", 7),
+            "/items?page=7&q={{other}}"
+        );
+
+        let rendered = render_builtins("{{uuid}} {{random:5-5}} {{random:9-1}}", 1);
+        let parts: Vec<&str> = rendered.split(' ').collect();
+        assert!(uuid::Uuid::parse_str(parts[0]).is_ok());
+        assert_eq!(parts[1], "5");
+        assert_eq!(parts[2], "{{random:9-1}}");
+
+        assert!(render_builtins("{{timestamp}}", 1).parse::<i64>().is_ok());
+        assert_eq!(render_builtins("open {{i", 1), "open {{i");
+    }
+}
@@ -3,7 +3,8 @@
 //! Provides a terminal user interface with realtime charts for metrics.
 
 use crate::config::SharedConfig;
-use crate::metrics::SharedMetrics;
+use crate::format::{format_duration_ms, format_duration_ms_with_precision};
+use crate::metrics::{EndpointSummary, MetricSource, MetricsSummary, RequestMetric, SharedMetrics};
 use crate::testing::SharedTester;
 use anyhow::Result;
 use crossterm::{
@@ -17,13 +18,20 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Bar, BarChart, BarGroup, Block, Borders, Gauge, List, ListItem, Paragraph, Sparkline, Wrap,
+        Bar, BarChart, BarGroup, Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState,
+        Paragraph, Row, Sparkline, Table, Wrap,
     },
     Frame, Terminal,
 };
 use std::io;
 use tokio::time::Duration;
 
+/// Number of requests shown in the "Recent Requests" list
+const RECENT_REQUESTS: usize = 10;
+
+/// Number of endpoints shown in the "Slowest Endpoints" table
+const SLOWEST_ENDPOINTS: usize = 5;
+
 /// TUI Application state
 pub struct TuiApp {
     config: SharedConfig,
@@ -32,12 +40,28 @@ pub struct TuiApp {
     should_quit: bool,
     /// Latency history for sparkline chart
     latency_history: Vec<u64>,
+    /// Smoothed (EWMA) latency history, sampled alongside `latency_history`
+    smoothed_history: Vec<u64>,
+    /// Chart the smoothed latency instead of the raw per-tick average
+    show_smoothed: bool,
     /// Request count history
     request_history: Vec<u64>,
     /// Last known request count
     last_request_count: usize,
     /// Status message
     status_message: String,
+    /// Show the latency histogram with log-spaced buckets
+    log_histogram: bool,
+    /// Only reflect metrics from this source (all sources if `None`)
+    source_filter: Option<MetricSource>,
+    /// Only list requests whose status is in this class, e.g. `4` for 4xx (all if `None`)
+    status_class_filter: Option<u16>,
+    /// Selected row in the "Recent Requests" list (0 = newest)
+    selected: usize,
+    /// Request shown in the detail popup, captured when it was opened
+    detail: Option<RequestMetric>,
+    /// Endpoints with the highest p95 latency as `(method, path, summary)`, slowest first
+    slowest: Vec<(String, String, EndpointSummary)>,
 }
 
 impl TuiApp {
@@ -49,9 +73,17 @@ impl TuiApp {
             tester,
             should_quit: false,
             latency_history: Vec::with_capacity(100),
+            smoothed_history: Vec::with_capacity(100),
+            show_smoothed: false,
             request_history: Vec::with_capacity(100),
             last_request_count: 0,
             status_message: "Press 'h' for help, 'q' to quit".to_string(),
+            log_histogram: true,
+            source_filter: None,
+            status_class_filter: None,
+            selected: 0,
+            detail: None,
+            slowest: Vec::new(),
         }
     }
 
@@ -95,7 +127,32 @@ impl TuiApp {
                                 self.should_quit = true;
                             }
                             KeyCode::Char('h') => {
-                                self.status_message = "q=quit, t=run test, s=stop test, c=clear metrics, p=toggle proxy".to_string();
+                                self.status_message = "q=quit, t=run test, s=stop test, c=clear metrics, p=toggle proxy, m=pause/resume metrics, l=log/linear histogram, e=raw/smoothed latency, v=cycle metric source, 1/2/4/5=list only 2xx/3xx/4xx/5xx, 0=list all, up/down=select request, enter=details, esc=close".to_string();
+                            }
+                            KeyCode::Char(c @ ('0' | '1' | '2' | '4' | '5')) => {
+                                self.status_class_filter = match c {
+                                    '1' => Some(2),
+                                    '2' => Some(3),
+                                    '4' => Some(4),
+                                    '5' => Some(5),
+                                    _ => None,
+                                };
+                                self.selected = 0;
+                                self.status_message =
+                                    format!("Listing {} requests", self.status_filter_label());
+                            }
+                            KeyCode::Up => {
+                                self.selected = self.selected.saturating_sub(1);
+                            }
+                            KeyCode::Down => {
+                                let last = self.recent_requests().len().saturating_sub(1);
+                                self.selected = (self.selected + 1).min(last);
+                            }
+                            KeyCode::Enter => {
+                                self.detail = self.recent_requests().into_iter().nth(self.selected);
+                            }
+                            KeyCode::Esc => {
+                                self.detail = None;
                             }
                             KeyCode::Char('t') => {
                                 if self.tester.is_running() {
@@ -119,10 +176,54 @@ impl TuiApp {
                             KeyCode::Char('c') => {
                                 self.metrics.clear();
                                 self.latency_history.clear();
+                                self.smoothed_history.clear();
                                 self.request_history.clear();
                                 self.last_request_count = 0;
                                 self.status_message = "Metrics cleared".to_string();
                             }
+                            KeyCode::Char('m') => {
+                                let paused = !self.metrics.is_paused();
+                                self.metrics.set_paused(paused);
+                                self.status_message = format!(
+                                    "Metrics collection {}",
+                                    if paused { "paused" } else { "resumed" }
+                                );
+                            }
+                            KeyCode::Char('l') => {
+                                self.log_histogram = !self.log_histogram;
+                                self.status_message = format!(
+                                    "Histogram scale: {}",
+                                    if self.log_histogram { "log" } else { "linear" }
+                                );
+                            }
+                            KeyCode::Char('e') => {
+                                self.show_smoothed = !self.show_smoothed;
+                                self.status_message = format!(
+                                    "Latency chart: {}",
+                                    if self.show_smoothed {
+                                        "smoothed (EWMA)"
+                                    } else {
+                                        "raw"
+                                    }
+                                );
+                            }
+                            KeyCode::Char('v') => {
+                                self.source_filter = match self.source_filter {
+                                    None => Some(MetricSource::ServerInbound),
+                                    Some(MetricSource::ServerInbound) => {
+                                        Some(MetricSource::TestClient)
+                                    }
+                                    Some(MetricSource::TestClient) => Some(MetricSource::Proxy),
+                                    Some(MetricSource::Proxy) => None,
+                                };
+                                // Restart the charts so sources don't mix in one history
+                                self.latency_history.clear();
+                                self.smoothed_history.clear();
+                                self.request_history.clear();
+                                self.last_request_count = self.summary().total_requests as usize;
+                                self.status_message =
+                                    format!("Showing {} metrics", self.source_label());
+                            }
                             KeyCode::Char('p') => {
                                 let mut config = self.config.get();
                                 config.proxy.enabled = !config.proxy.enabled;
@@ -147,9 +248,45 @@ impl TuiApp {
         Ok(())
     }
 
+    /// Summary of the metrics the dashboard currently reflects
+    fn summary(&self) -> MetricsSummary {
+        self.metrics.get_summary_for(self.source_filter)
+    }
+
+    /// Newest requests from the selected source and status class, newest first
+    fn recent_requests(&self) -> Vec<RequestMetric> {
+        self.metrics
+            .get_recent(60)
+            .into_iter()
+            .filter(|m| self.source_filter.is_none_or(|s| m.source == s))
+            .filter(|m| {
+                self.status_class_filter
+                    .is_none_or(|class| m.status_code.is_some_and(|s| s / 100 == class))
+            })
+            .rev()
+            .take(RECENT_REQUESTS)
+            .collect()
+    }
+
+    /// Human-readable name of the listed status class
+    fn status_filter_label(&self) -> String {
+        self.status_class_filter
+            .map_or("all".to_string(), |class| format!("{}xx", class))
+    }
+
+    /// Human-readable name of the selected metric source
+    fn source_label(&self) -> &'static str {
+        match self.source_filter {
+            None => "all",
+            Some(MetricSource::ServerInbound) => "server",
+            Some(MetricSource::TestClient) => "test",
+            Some(MetricSource::Proxy) => "proxy",
+        }
+    }
+
     /// Update metrics data for charts
     fn update_data(&mut self) {
-        let summary = self.metrics.get_summary();
+        let summary = self.summary();
 
         // Update latency history (convert to u64 for sparkline)
         if summary.total_requests > 0 {
@@ -159,16 +296,31 @@ impl TuiApp {
             if self.latency_history.len() > 100 {
                 self.latency_history.remove(0);
             }
+            self.smoothed_history
+                .push(summary.ewma_latency_ms.max(0.0).round() as u64);
+            if self.smoothed_history.len() > 100 {
+                self.smoothed_history.remove(0);
+            }
         }
 
         // Update request history (new requests since last update)
-        let current_count = self.metrics.count();
+        let current_count = summary.total_requests as usize;
         let new_requests = current_count.saturating_sub(self.last_request_count) as u64;
         self.request_history.push(new_requests);
         if self.request_history.len() > 100 {
             self.request_history.remove(0);
         }
         self.last_request_count = current_count;
+
+        let mut endpoints: Vec<(String, String, EndpointSummary)> = self
+            .metrics
+            .get_summary_by_endpoint()
+            .into_iter()
+            .map(|((method, path), summary)| (method, path, summary))
+            .collect();
+        endpoints.sort_by(|a, b| b.2.p95_latency_ms.total_cmp(&a.2.p95_latency_ms));
+        endpoints.truncate(SLOWEST_ENDPOINTS);
+        self.slowest = endpoints;
     }
 
     /// Draw the UI
@@ -177,19 +329,25 @@ impl TuiApp {
             .direction(Direction::Vertical)
             .margin(1)
             .constraints([
-                Constraint::Length(3), // Header
-                Constraint::Length(8), // Summary stats
-                Constraint::Length(8), // Sparkline charts
-                Constraint::Min(10),   // Status distribution
-                Constraint::Length(3), // Status bar
+                Constraint::Length(3),                            // Header
+                Constraint::Length(8),                            // Summary stats
+                Constraint::Length(8),                            // Sparkline charts
+                Constraint::Length(SLOWEST_ENDPOINTS as u16 + 3), // Slowest endpoints
+                Constraint::Min(8),                               // Status distribution
+                Constraint::Length(3),                            // Status bar
             ])
             .split(f.size());
 
         self.draw_header(f, chunks[0]);
         self.draw_summary(f, chunks[1]);
         self.draw_charts(f, chunks[2]);
-        self.draw_status_distribution(f, chunks[3]);
-        self.draw_status_bar(f, chunks[4]);
+        self.draw_slowest_endpoints(f, chunks[3]);
+        self.draw_status_distribution(f, chunks[4]);
+        self.draw_status_bar(f, chunks[5]);
+
+        if let Some(metric) = &self.detail {
+            draw_request_detail(f, metric);
+        }
     }
 
     fn draw_header(&self, f: &mut Frame, area: Rect) {
@@ -220,7 +378,7 @@ impl TuiApp {
     }
 
     fn draw_summary(&self, f: &mut Frame, area: Rect) {
-        let summary = self.metrics.get_summary();
+        let summary = self.summary();
 
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -250,11 +408,7 @@ impl TuiApp {
         f.render_widget(total, chunks[0]);
 
         // Success rate
-        let success_rate = if summary.total_requests > 0 {
-            (summary.successful_requests as f64 / summary.total_requests as f64 * 100.0) as u16
-        } else {
-            0
-        };
+        let success_rate = (summary.success_rate * 100.0) as u16;
         let success = Gauge::default()
             .block(Block::default().borders(Borders::ALL).title("Success Rate"))
             .gauge_style(Style::default().fg(Color::Green))
@@ -262,16 +416,20 @@ impl TuiApp {
             .label(format!("{}%", success_rate));
         f.render_widget(success, chunks[1]);
 
-        // Average latency
+        // Average and p95 latency
         let latency = Paragraph::new(vec![
             Line::from(Span::styled(
-                format!("{:.2} ms", summary.avg_latency_ms),
+                format!(
+                    "{} / {}",
+                    format_duration_ms(summary.avg_latency_ms),
+                    format_duration_ms(summary.p95_latency_ms)
+                ),
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
             )),
             Line::from(Span::styled(
-                "Avg Latency",
+                "Avg / p95 Latency",
                 Style::default().fg(Color::Gray),
             )),
         ])
@@ -297,18 +455,22 @@ impl TuiApp {
     fn draw_charts(&self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .constraints([
+                Constraint::Percentage(33),
+                Constraint::Percentage(33),
+                Constraint::Percentage(34),
+            ])
             .split(area);
 
-        // Latency sparkline
-        let latency_data: Vec<u64> = self.latency_history.clone();
+        // Latency sparkline, raw or smoothed
+        let (latency_data, latency_title) = if self.show_smoothed {
+            (&self.smoothed_history, "Latency EWMA (ms)")
+        } else {
+            (&self.latency_history, "Latency History (ms)")
+        };
         let latency_sparkline = Sparkline::default()
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Latency History (ms)"),
-            )
-            .data(&latency_data)
+            .block(Block::default().borders(Borders::ALL).title(latency_title))
+            .data(latency_data)
             .style(Style::default().fg(Color::Yellow));
         f.render_widget(latency_sparkline, chunks[0]);
 
@@ -319,10 +481,84 @@ impl TuiApp {
             .data(&request_data)
             .style(Style::default().fg(Color::Cyan));
         f.render_widget(request_sparkline, chunks[1]);
+
+        // Latency histogram
+        let (histogram, title) = if self.log_histogram {
+            (
+                self.metrics.get_log_histogram(2.0),
+                "Latency Histogram (log)",
+            )
+        } else {
+            (
+                self.metrics.get_latency_histogram(10),
+                "Latency Histogram (linear)",
+            )
+        };
+        let labels: Vec<String> = histogram
+            .iter()
+            .map(|(start, _)| format_duration_ms_with_precision(*start, 0))
+            .collect();
+        let bars: Vec<Bar> = histogram
+            .iter()
+            .zip(&labels)
+            .map(|((_, count), label)| {
+                Bar::default()
+                    .value(*count)
+                    .label(Line::from(label.clone()))
+                    .style(Style::default().fg(Color::Green))
+            })
+            .collect();
+        let histogram_chart = BarChart::default()
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(5)
+            .bar_gap(1);
+        f.render_widget(histogram_chart, chunks[2]);
+    }
+
+    fn draw_slowest_endpoints(&self, f: &mut Frame, area: Rect) {
+        let header = Row::new(["Method", "Path", "Count", "Avg", "p95", "Errors"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let rows: Vec<Row> = self
+            .slowest
+            .iter()
+            .map(|(method, path, summary)| {
+                let errors = if summary.error_count > 0 {
+                    Cell::from(summary.error_count.to_string())
+                        .style(Style::default().fg(Color::Red))
+                } else {
+                    Cell::from("0")
+                };
+                Row::new(vec![
+                    Cell::from(method.clone()),
+                    Cell::from(path.clone()),
+                    Cell::from(summary.count.to_string()),
+                    Cell::from(format_duration_ms_with_precision(summary.avg_latency_ms, 1)),
+                    Cell::from(format_duration_ms_with_precision(summary.p95_latency_ms, 1))
+                        .style(Style::default().fg(Color::Yellow)),
+                    errors,
+                ])
+            })
+            .collect();
+
+        let widths = [
+            Constraint::Length(7),
+            Constraint::Min(20),
+            Constraint::Length(8),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(7),
+        ];
+        let table = Table::new(rows).header(header).widths(&widths).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Slowest Endpoints (by p95, all sources)"),
+        );
+        f.render_widget(table, area);
     }
 
     fn draw_status_distribution(&self, f: &mut Frame, area: Rect) {
-        let summary = self.metrics.get_summary();
+        let summary = self.summary();
 
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -365,11 +601,9 @@ impl TuiApp {
         f.render_widget(bar_chart, chunks[0]);
 
         // Recent requests list
-        let recent = self.metrics.get_recent(60);
+        let recent = self.recent_requests();
         let items: Vec<ListItem> = recent
             .iter()
-            .rev()
-            .take(10)
             .map(|m| {
                 let status_color = match m.status_code {
                     Some(200..=299) => Color::Green,
@@ -379,17 +613,30 @@ impl TuiApp {
                     _ => Color::Gray,
                 };
                 let status = m.status_code.map_or("-".to_string(), |s| s.to_string());
-                let text = format!("{} {} [{}] {:.1}ms", m.method, m.path, status, m.latency_ms);
+                let mut text = format!(
+                    "{} {} [{}] {}",
+                    m.method,
+                    m.path,
+                    status,
+                    format_duration_ms_with_precision(m.latency_ms, 1)
+                );
+                if m.circuit_open {
+                    text.push_str(" (circuit open)");
+                }
                 ListItem::new(text).style(Style::default().fg(status_color))
             })
             .collect();
 
-        let list = List::new(items).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Recent Requests"),
-        );
-        f.render_widget(list, chunks[1]);
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Recent Requests (enter for details)"),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let selected = (!recent.is_empty()).then(|| self.selected.min(recent.len() - 1));
+        let mut state = ListState::default().with_selected(selected);
+        f.render_stateful_widget(list, chunks[1], &mut state);
     }
 
     fn draw_status_bar(&self, f: &mut Frame, area: Rect) {
@@ -399,10 +646,83 @@ impl TuiApp {
             "Test: IDLE"
         };
 
-        let status = Paragraph::new(format!("{} | {}", self.status_message, test_status))
-            .style(Style::default().fg(Color::White))
-            .wrap(Wrap { trim: true })
-            .block(Block::default().borders(Borders::ALL).title("Status"));
+        let metrics_status = if self.metrics.is_paused() {
+            " | Metrics: PAUSED"
+        } else {
+            ""
+        };
+
+        let status = Paragraph::new(format!(
+            "{} | {} | Source: {} | Listing: {}{}",
+            self.status_message,
+            test_status,
+            self.source_label(),
+            self.status_filter_label(),
+            metrics_status
+        ))
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title("Status"));
         f.render_widget(status, area);
     }
 }
+
+/// Draw a centered popup with every detail of `metric`
+fn draw_request_detail(f: &mut Frame, metric: &RequestMetric) {
+    let area = centered_rect(60, 50, f.size());
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<10}", name), Style::default().fg(Color::Gray)),
+            Span::raw(value),
+        ])
+    };
+    let mut lines = vec![
+        field("ID", metric.id.clone()),
+        field("Method", metric.method.clone()),
+        field("Path", metric.path.clone()),
+        field(
+            "Status",
+            metric
+                .status_code
+                .map_or("-".to_string(), |s| s.to_string()),
+        ),
+        field(
+            "Latency",
+            format_duration_ms_with_precision(metric.latency_ms, 2),
+        ),
+        field("Timestamp", metric.timestamp.to_rfc3339()),
+        field("Proxied", metric.proxied.to_string()),
+    ];
+    if metric.circuit_open {
+        lines.push(field("Circuit", "open, not sent upstream".to_string()));
+    }
+
+    let popup = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Request Detail (esc to close)")
+            .style(Style::default().fg(Color::White)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// A rectangle of `percent_x` by `percent_y` of `area`, centered in it
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
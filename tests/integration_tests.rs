@@ -19,6 +19,7 @@ fn test_shared_config_update() {
     let proxy = ProxyConfig {
         enabled: true,
         target: Some("http://example.com".to_string()),
+        ..Default::default()
     };
     shared.update_proxy(proxy);
 